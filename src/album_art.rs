@@ -0,0 +1,179 @@
+use std::{fs, io, process::Stdio, time::Duration};
+
+use tokio::{io::AsyncWriteExt, process::{Child, Command}, task, time::timeout};
+
+use crate::{http, log::{debug, warn}};
+
+/// Seconds before downloading remote art gives up
+const FETCH_TIMEOUT: u64 = 10;
+/// Big covers take ImageMagick a moment to scale down
+const CONVERT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Colors the cover is reduced to before picking the most common one
+const PALETTE_SIZE: &str = "8";
+
+/// Decodes the `%XX` escapes of a URL path, None when that isn't valid UTF-8
+fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    return String::from_utf8(decoded).ok();
+}
+
+/// The image `art_url` points to, `file://` or with `remote` also `http(s)://`
+async fn load(art_url: &str, remote: bool) -> Option<Vec<u8>> {
+    if let Some(path) = art_url.strip_prefix("file://") {
+        let path = percent_decode(path)?;
+        let read = {
+            let path = path.clone();
+            task::spawn_blocking(move || fs::read(path)).await
+        };
+        return match read {
+            Ok(Ok(image)) => Some(image),
+            // The read panicked, there's nothing better to do than go without art
+            Err(_) => None,
+            Ok(Err(err)) => {
+                debug!("Failed to read album art {}: {}", path, err);
+                None
+            }
+        };
+    }
+    if remote && (art_url.starts_with("http://") || art_url.starts_with("https://")) {
+        return http::get_bytes(art_url, FETCH_TIMEOUT).await;
+    }
+    return None;
+}
+
+/// The most common color in ImageMagick's `histogram:info:` output as `#rrggbb`. Lines look like
+/// `  1234: (255,128,0) #FF8000 srgb(255,128,0)`, fully transparent colors are skipped.
+fn dominant_color(histogram: &str) -> Option<String> {
+    return histogram.lines()
+        .filter_map(|line| {
+            let (count, rest) = line.split_once(':')?;
+            let count: u64 = count.trim().parse().ok()?;
+            let hex = rest.split_whitespace().find_map(|field| field.strip_prefix('#'))?;
+            if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            // Images with transparency come with alpha after the color
+            let (rgb, alpha) = match hex.len() {
+                6 => (hex, None),
+                8 => (&hex[..6], Some(&hex[6..])),
+                _ => return None,
+            };
+            if alpha.is_some_and(|alpha| alpha == "00") {
+                return None;
+            }
+            Some((count, rgb))
+        })
+        // The first of equally common colors wins
+        .fold(None, |best: Option<(u64, &str)>, (count, rgb)| match best {
+            Some((best_count, _)) if best_count >= count => best,
+            _ => Some((count, rgb)),
+        })
+        .map(|(_, rgb)| format!("#{}", rgb.to_lowercase()));
+}
+
+/// ImageMagick 7 has everything under `magick` and may come without the old `convert`
+fn spawn_imagemagick(args: &[&str]) -> io::Result<Child> {
+    let spawn = |program: &str| Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+    return match spawn("convert") {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => spawn("magick"),
+        result => result,
+    };
+}
+
+/// Most common color of `image` as `#rrggbb`, after ImageMagick shrinks it to a few colors
+async fn image_color(image: &[u8]) -> Option<String> {
+    // Scaling down first keeps quantizing big covers fast
+    let args = ["-", "-resize", "64x64", "-colors", PALETTE_SIZE, "-depth", "8", "-format", "%c", "histogram:info:"];
+    let mut child = match spawn_imagemagick(&args) {
+        Ok(child) => child,
+        Err(err) => {
+            warn!("Failed to run ImageMagick's convert or magick: {}", err);
+            return None;
+        }
+    };
+
+    let mut stdin = child.stdin.take()?;
+    // convert may stop reading images it can't decode
+    let _ = stdin.write_all(image).await;
+    drop(stdin);
+
+    let output = timeout(CONVERT_TIMEOUT, child.wait_with_output()).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    return dominant_color(&String::from_utf8_lossy(&output.stdout));
+}
+
+/// Color for tinting the track of `art_url`, None when the art can't be loaded
+pub async fn color(art_url: &str, remote: bool) -> Option<String> {
+    let image = load(art_url, remote).await?;
+    return image_color(&image).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_common_color_wins() {
+        let histogram = "\
+      120: (  0,  0,255) #0000FF blue
+     3000: (255,128,  0) #FF8000 srgb(255,128,0)
+      876: (255,255,255) #FFFFFF white
+";
+        assert_eq!(dominant_color(histogram).as_deref(), Some("#ff8000"));
+    }
+
+    #[test]
+    fn transparent_background_is_skipped() {
+        let histogram = "\
+     4000: (0,0,0,0) #00000000 none
+      500: (18,52,86,255) #123456FF srgba(18,52,86,1)
+       20: (255,255,255,128) #FFFFFF80 srgba(255,255,255,0.5)
+";
+        assert_eq!(dominant_color(histogram).as_deref(), Some("#123456"));
+    }
+
+    #[test]
+    fn ties_keep_the_first() {
+        assert_eq!(dominant_color("10: (1,2,3) #010203 a\n10: (4,5,6) #040506 b").as_deref(), Some("#010203"));
+    }
+
+    #[test]
+    fn garbage_has_no_color() {
+        assert_eq!(dominant_color(""), None);
+        assert_eq!(dominant_color("convert: no decode delegate for this image format"), None);
+        assert_eq!(dominant_color("12: (1,2,3) #12 short"), None);
+    }
+
+    #[test]
+    fn art_paths_are_decoded() {
+        assert_eq!(percent_decode("/music/cover%20a.png").as_deref(), Some("/music/cover a.png"));
+        assert_eq!(percent_decode("/100%").as_deref(), Some("/100%"));
+        assert_eq!(percent_decode("/%ff"), None);
+    }
+}
@@ -0,0 +1,410 @@
+use std::{any::Any, cell::{Cell, RefCell}, future::Future, panic::{self, AssertUnwindSafe}, sync::Arc, task::Poll, thread, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use crate::{bus::Bus, icons::IconSet, log::error, output::OutputMode};
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct ClickEvent {
+    pub name: Option<String>,
+    pub instance: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub button: i32,
+    pub relative_x: i32,
+    pub relative_y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    ScrollUp,
+    ScrollDown,
+    Unknown,
+}
+
+impl ClickEvent {
+    /// Parses a line of the endless click event array i3bar writes to stdin, the lines
+    /// opening the array or just separating events have no event
+    pub fn parse_line(line: &str) -> Result<Option<Self>, serde_json::Error> {
+        let line = line.trim();
+        let line = line.strip_prefix('[').unwrap_or(line).trim_start();
+        let line = line.strip_prefix(',').unwrap_or(line).trim_start();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        return serde_json::from_str(line).map(Some);
+    }
+
+    pub fn mouse_button(&self) -> MouseButton {
+        return match self.button {
+            1 => MouseButton::Left,
+            2 => MouseButton::Middle,
+            3 => MouseButton::Right,
+            4 => MouseButton::ScrollUp,
+            5 => MouseButton::ScrollDown,
+            _ => MouseButton::Unknown,
+        };
+    }
+}
+
+/// How i3bar interprets `full_text`
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Markup {
+    #[default]
+    None,
+    Pango,
+}
+
+impl Markup {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Markup::None => "none",
+            Markup::Pango => "pango",
+        };
+    }
+}
+
+/// i3bar's `align`, where the text goes when the item is wider because of `min_width`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Align::Left => "left",
+            Align::Center => "center",
+            Align::Right => "right",
+        };
+    }
+}
+
+/// i3bar's `min_width`, either in pixels or as wide as a sample text like `CPU 100%`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum MinWidth {
+    Pixels(u32),
+    Text(String),
+}
+
+impl MinWidth {
+    pub fn to_json(&self) -> Value {
+        return match self {
+            MinWidth::Pixels(pixels) => json!(pixels),
+            MinWidth::Text(text) => json!(text),
+        };
+    }
+}
+
+/// Shared resources handed to blocks when they're built
+#[derive(Clone)]
+pub struct Context {
+    pub bus: Bus,
+    /// Notified by blocks whose state changed outside of `render`
+    pub invalidate: Arc<Notify>,
+    /// Markup of the block being built, dynamic text has to be escaped with `text::escape_markup`
+    pub markup: Markup,
+    pub output: OutputMode,
+    pub icons: IconSet,
+}
+
+/// One indicator on the status line, rendering to any number of i3bar items.
+/// Items that don't set a `name` get the block's name so clicks on them are
+/// routed back to `handle_click`. A `short_text` is used by i3bar when the bar gets too
+/// full, items without one always show `full_text`. Items may also carry Waybar's `tooltip`
+/// and `class`, which are left out for i3bar.
+#[async_trait]
+pub trait Block: Send {
+    fn name(&self) -> &str;
+
+    async fn render(&mut self) -> Vec<Value>;
+
+    /// How long after a render the block wants to be rendered again, `None` when it only
+    /// changes through `Context::invalidate` or clicks
+    fn interval(&self) -> Option<Duration> {
+        return Some(DEFAULT_INTERVAL);
+    }
+
+    fn handle_click(&mut self, _event: &ClickEvent) {}
+
+    /// Scroll wheel over the block, `steps` is positive when scrolling up
+    fn handle_scroll(&mut self, _event: &ClickEvent, _steps: i32) {}
+
+    /// Tells apart blocks of the same type for toggling them, the name unless the config set one
+    fn id(&self) -> &str {
+        return self.name();
+    }
+
+    /// Blocks next to each other in the same group have no separators between them
+    fn group(&self) -> Option<&str> {
+        return None;
+    }
+
+    /// Update right away instead of when the interval passed, blocks polling in the
+    /// background poll now. Called on left clicks of blocks with `refresh_on_click`.
+    fn refresh(&mut self) {}
+}
+
+/// Kept by a block polling in the background, the task stops when it's dropped along with
+/// the block and `refresh` makes it poll early
+pub struct Poller {
+    wait: PollWait,
+}
+
+/// The background task's end of a `Poller`
+#[derive(Clone)]
+pub struct PollWait {
+    destruct: Arc<Notify>,
+    refresh: Arc<Notify>,
+}
+
+impl Poller {
+    pub fn new() -> (Self, PollWait) {
+        let wait = PollWait { destruct: Arc::new(Notify::new()), refresh: Arc::new(Notify::new()) };
+        return (Self { wait: wait.clone() }, wait);
+    }
+
+    pub fn refresh(&self) {
+        self.wait.refresh.notify_one();
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.wait.destruct.notify_one();
+    }
+}
+
+impl PollWait {
+    /// Finishes once the block is gone, for racing against a poll in progress
+    pub async fn stopped(&self) {
+        self.destruct.notified().await;
+    }
+
+    /// Sleeps for `interval` or until a refresh, false when the block went away instead
+    pub async fn sleep(&self, interval: Duration) -> bool {
+        return tokio::select! {
+            _ = self.destruct.notified() => false,
+            _ = tokio::time::sleep(interval) => true,
+            _ = self.refresh.notified() => true,
+        };
+    }
+}
+
+/// Shows `items` as one group: only the last one is separated from what comes after it.
+/// Items that pick their own `separator` keep it.
+pub fn join(items: &mut [Value]) {
+    let Some((_, joined)) = items.split_last_mut()
+    else { return };
+
+    for item in joined {
+        if let Value::Object(fields) = item {
+            fields.entry("separator").or_insert(Value::Bool(false));
+        }
+    }
+}
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Renders a single block, filling in its name
+pub async fn render_block(block: &mut dyn Block) -> Vec<Value> {
+    let name = block.name().to_string();
+    let mut output = Vec::new();
+    for mut item in block.render().await {
+        let Value::Object(fields) = &mut item
+        else { continue };
+
+        fields.entry("name").or_insert_with(|| Value::String(name.clone()));
+        output.push(item);
+    }
+    return output;
+}
+
+thread_local! {
+    /// Set while `catch_panic` runs, its caller reports the panic instead of the hook
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    /// Where the last caught panic happened, `file:line`
+    static CAUGHT_AT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Logs panics with `error!` instead of the default message and backtrace note. Panics caught
+/// by `catch_panic` are left to its caller, which knows which block it was.
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let location = info.location().map(|location| format!("{}:{}", location.file(), location.line()));
+        if CATCHING.get() {
+            CAUGHT_AT.replace(location);
+            return;
+        }
+        error!("Panicked at {}: {}", location.as_deref().unwrap_or("unknown location"), info.payload_as_str().unwrap_or("unknown panic"));
+    }));
+}
+
+/// Runs `f`, turning a panic into an error that the caller reports
+pub fn catch_panic<R>(f: impl FnOnce() -> R) -> thread::Result<R> {
+    let catching = CATCHING.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(catching);
+    return result;
+}
+
+/// Where the panic last caught by `catch_panic` happened
+pub fn caught_at() -> Option<String> {
+    return CAUGHT_AT.take();
+}
+
+/// Runs `future`, turning a panic while polling it into an error instead of unwinding further
+pub async fn catch_unwind<F: Future>(future: F) -> thread::Result<F::Output> {
+    let mut future = std::pin::pin!(future);
+    return std::future::poll_fn(|context| match catch_panic(|| future.as_mut().poll(context)) {
+        Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+        Ok(Poll::Pending) => Poll::Pending,
+        Err(payload) => Poll::Ready(Err(payload)),
+    }).await;
+}
+
+/// The message a panic was started with, `panic!` passes either a `&str` or a `String`
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message;
+    }
+    return payload.downcast_ref::<String>().map(String::as_str).unwrap_or("unknown panic");
+}
+
+/// Passes a click on to `block`, scrolling goes to `handle_scroll`
+pub fn dispatch_click(block: &mut dyn Block, event: &ClickEvent) {
+    match event.mouse_button() {
+        MouseButton::ScrollUp => block.handle_scroll(event, 1),
+        MouseButton::ScrollDown => block.handle_scroll(event, -1),
+        MouseButton::Unknown => {}
+        _ => block.handle_click(event),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders what it's given and records the clicks and scrolls it gets
+    #[derive(Default)]
+    struct Recorder {
+        items: Vec<Value>,
+        clicks: Vec<i32>,
+        scrolls: Vec<i32>,
+    }
+
+    #[async_trait]
+    impl Block for Recorder {
+        fn name(&self) -> &str {
+            return "recorder";
+        }
+
+        async fn render(&mut self) -> Vec<Value> {
+            return self.items.clone();
+        }
+
+        fn handle_click(&mut self, event: &ClickEvent) {
+            self.clicks.push(event.button);
+        }
+
+        fn handle_scroll(&mut self, _event: &ClickEvent, steps: i32) {
+            self.scrolls.push(steps);
+        }
+    }
+
+    fn click(button: i32) -> ClickEvent {
+        return ClickEvent { name: None, instance: None, x: 0, y: 0, button, relative_x: 0, relative_y: 0, width: 0, height: 0 };
+    }
+
+    #[tokio::test]
+    async fn items_get_the_block_name() {
+        let mut block = Recorder {
+            items: vec![json!({ "full_text": "a" }), json!({ "full_text": "b", "name": "custom" }), json!("not an item")],
+            ..Recorder::default()
+        };
+        assert_eq!(render_block(&mut block).await, vec![
+            json!({ "full_text": "a", "name": "recorder" }),
+            json!({ "full_text": "b", "name": "custom" }),
+        ]);
+    }
+
+    #[test]
+    fn joined_items_have_no_separators_between_them() {
+        let mut items = vec![json!({ "full_text": "a" }), json!({ "full_text": "b", "separator": true }), json!({ "full_text": "c" })];
+        join(&mut items);
+        assert_eq!(items, vec![
+            json!({ "full_text": "a", "separator": false }),
+            // Ones the block set stay
+            json!({ "full_text": "b", "separator": true }),
+            json!({ "full_text": "c" }),
+        ]);
+
+        let mut empty: Vec<Value> = Vec::new();
+        join(&mut empty);
+    }
+
+    #[test]
+    fn clicks_and_scrolls_are_dispatched() {
+        let mut block = Recorder::default();
+        for button in [1, 4, 3, 5, 9, 2] {
+            dispatch_click(&mut block, &click(button));
+        }
+        assert_eq!(block.clicks, [1, 3, 2]);
+        assert_eq!(block.scrolls, [1, -1]);
+    }
+
+    #[test]
+    fn click_lines_good_and_bad() {
+        let stdin = [
+            "[",
+            r#"{"name":"clock","instance":"utc","button":1,"x":1820,"y":5,"relative_x":20,"relative_y":5,"width":90,"height":22}"#,
+            r#",{"name":"volume","button":4,"x":1700,"y":5,"relative_x":3,"relative_y":5,"width":60,"height":22}"#,
+            ",",
+            r#",{"name":"clock","button":"#,
+            "not json at all",
+            r#",{"name":"mpris","button":3,"x":10,"y":5,"relative_x":10,"relative_y":5,"width":200,"height":22}"#,
+            "",
+        ];
+        let mut events = Vec::new();
+        let mut malformed = 0;
+        for line in stdin {
+            match ClickEvent::parse_line(line) {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => {}
+                Err(_) => malformed += 1,
+            }
+        }
+        assert_eq!(malformed, 2);
+
+        let clicks: Vec<_> = events.iter()
+            .map(|event| (event.name.as_deref(), event.instance.as_deref(), event.mouse_button()))
+            .collect();
+        assert_eq!(clicks, [
+            (Some("clock"), Some("utc"), MouseButton::Left),
+            (Some("volume"), None, MouseButton::ScrollUp),
+            (Some("mpris"), None, MouseButton::Right),
+        ]);
+    }
+
+    #[test]
+    fn first_event_on_the_opening_line() {
+        let line = r#"[{"name":"disk","button":9,"x":0,"y":0,"relative_x":0,"relative_y":0,"width":1,"height":1}"#;
+        let event = ClickEvent::parse_line(line).unwrap().unwrap();
+        assert_eq!(event.name.as_deref(), Some("disk"));
+        assert_eq!(event.mouse_button(), MouseButton::Unknown);
+    }
+}
@@ -0,0 +1,40 @@
+pub mod battery;
+pub mod binding_mode;
+pub mod bluetooth;
+pub mod calendar;
+pub mod clock;
+pub mod command;
+pub mod cpu;
+pub mod cpu_freq;
+pub mod ddc;
+pub mod disk;
+pub mod disk_io;
+pub mod dnd;
+pub mod fan;
+pub mod gpu;
+pub mod idle_inhibitor;
+pub mod keyboard_layout;
+pub mod load;
+pub mod lock_keys;
+pub mod mail;
+pub mod memory;
+pub mod memory_light;
+pub mod mpris;
+pub mod network;
+pub mod persistent_command;
+pub mod ping;
+pub mod pressure;
+pub mod price;
+pub mod process;
+pub mod public_ip;
+pub mod redshift;
+pub mod socket;
+pub mod temperature;
+pub mod timer;
+pub mod tmpfs;
+pub mod updates;
+pub mod volume;
+pub mod vpn;
+pub mod weather;
+pub mod wifi;
+pub mod window_title;
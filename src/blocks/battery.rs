@@ -0,0 +1,123 @@
+use std::{path::PathBuf, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::Block, icons::{Icon, IconSet}, sysfs};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct BatteryConfig {
+    /// Directory name under /sys/class/power_supply
+    pub device: String,
+    /// Capacity in percent below which a discharging battery is marked urgent
+    pub urgent_below: u8,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        return Self {
+            device: "BAT0".to_string(),
+            urgent_below: 15,
+        };
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Status {
+    Charging,
+    Discharging,
+    Full,
+    Unknown,
+}
+
+impl Status {
+    fn parse(status: &str) -> Self {
+        return match status {
+            "Charging" => Status::Charging,
+            "Discharging" => Status::Discharging,
+            "Full" | "Not charging" => Status::Full,
+            _ => Status::Unknown,
+        };
+    }
+}
+
+fn icon(status: &Status, capacity: u8) -> Icon {
+    return match status {
+        Status::Charging => Icon::Charging,
+        Status::Full => Icon::PluggedIn,
+        Status::Discharging | Status::Unknown => match capacity {
+            0..=12 => Icon::BatteryEmpty,
+            13..=37 => Icon::BatteryQuarter,
+            38..=62 => Icon::BatteryHalf,
+            63..=87 => Icon::BatteryThreeQuarters,
+            _ => Icon::BatteryFull,
+        },
+    };
+}
+
+/// Formats a duration as `2h13m`
+fn format_remaining(remaining: Duration) -> String {
+    let minutes = remaining.as_secs() / 60;
+    return format!("{}h{:02}m", minutes / 60, minutes % 60);
+}
+
+pub struct BatteryBlock {
+    path: PathBuf,
+    urgent_below: u8,
+    icons: IconSet,
+}
+
+impl BatteryBlock {
+    pub fn new(config: BatteryConfig, icons: IconSet) -> Self {
+        return Self {
+            path: PathBuf::from("/sys/class/power_supply").join(config.device),
+            urgent_below: config.urgent_below,
+            icons,
+        };
+    }
+
+    /// Time until empty when discharging or until full when charging
+    fn remaining(&self, status: &Status) -> Option<Duration> {
+        // Some batteries only report charge/current instead of energy/power,
+        // the ratio works out the same
+        let read = |energy: &str, charge: &str| sysfs::read_value::<u64>(self.path.join(energy))
+            .or_else(|| sysfs::read_value(self.path.join(charge)));
+
+        let now = read("energy_now", "charge_now")?;
+        let rate = read("power_now", "current_now").filter(|&rate| rate > 0)?;
+        let left = match status {
+            Status::Discharging => now,
+            Status::Charging => read("energy_full", "charge_full")?.saturating_sub(now),
+            _ => return None,
+        };
+        return Some(Duration::from_secs(left * 3600 / rate));
+    }
+}
+
+#[async_trait]
+impl Block for BatteryBlock {
+    fn name(&self) -> &str {
+        return "battery";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        // No battery, nothing to show
+        let Some(capacity) = sysfs::read_value::<u8>(self.path.join("capacity"))
+        else { return Vec::new() };
+
+        let status = Status::parse(&sysfs::read_string(self.path.join("status")).unwrap_or_default());
+
+        let mut full_text = format!("{} {}%", self.icons.get(icon(&status, capacity)), capacity);
+        if let Some(remaining) = self.remaining(&status) {
+            full_text.push(' ');
+            full_text.push_str(&format_remaining(remaining));
+        }
+
+        return vec![json!({
+            "full_text": full_text,
+            "urgent": status != Status::Charging && capacity < self.urgent_below,
+        })];
+    }
+}
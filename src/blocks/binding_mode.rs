@@ -0,0 +1,148 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{block::{Block, Markup}, i3ipc::{self, I3Ipc, Message}, log::{debug, warn}, text};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const DEFAULT_MODE: &str = "default";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct BindingModeConfig {
+    /// Show the mode without marking it urgent
+    pub no_urgent: bool,
+}
+
+/// The current i3 binding mode, hidden in the default mode
+pub struct BindingModeBlock {
+    /// None in the default mode or without i3
+    mode: Arc<Mutex<Option<String>>>,
+    urgent: bool,
+    markup: Markup,
+    destruct: Arc<Notify>,
+}
+
+impl Drop for BindingModeBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+/// `name` of a mode, None for the default one
+fn mode_of(name: Option<&str>) -> Option<String> {
+    return name.filter(|&name| name != DEFAULT_MODE).map(str::to_string);
+}
+
+impl BindingModeBlock {
+    async fn connect() -> std::io::Result<I3Ipc> {
+        let mut ipc = I3Ipc::connect().await?;
+        ipc.subscribe(&["mode"]).await?;
+        return Ok(ipc);
+    }
+
+    fn set(mode: &Mutex<Option<String>>, current: Option<String>, invalidate: &Notify) {
+        if *mode.lock().unwrap() != current {
+            *mode.lock().unwrap() = current;
+            invalidate.notify_one();
+        }
+    }
+
+    /// Follows mode changes until the connection breaks
+    async fn follow(mut ipc: I3Ipc, mode: &Mutex<Option<String>>, invalidate: &Notify) -> std::io::Error {
+        // Subscribing first so that a change right after asking isn't missed
+        match ipc.request(i3ipc::GET_BINDING_STATE, "").await {
+            Ok(state) => Self::set(mode, mode_of(state.get("name").and_then(Value::as_str)), invalidate),
+            Err(err) => return err,
+        }
+        loop {
+            match ipc.read().await {
+                Ok(Message::Event(i3ipc::MODE_EVENT, event)) => {
+                    Self::set(mode, mode_of(event.get("change").and_then(Value::as_str)), invalidate);
+                }
+                Ok(_) => {}
+                Err(err) => return err,
+            }
+        }
+    }
+
+    fn create_watcher(mode: Arc<Mutex<Option<String>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let mut backoff = MIN_BACKOFF;
+            // Without i3 there's nothing to show, retrying shouldn't fill the log
+            let mut warned = false;
+            loop {
+                let connected = tokio::select! {
+                    _ = destruct.notified() => return,
+                    connected = Self::connect() => connected,
+                };
+                match connected {
+                    Ok(ipc) => {
+                        backoff = MIN_BACKOFF;
+                        warned = false;
+                        let err = tokio::select! {
+                            _ = destruct.notified() => return,
+                            err = Self::follow(ipc, &mode, &invalidate) => err,
+                        };
+                        warn!("Lost i3 IPC connection: {}", err);
+                        Self::set(&mode, None, &invalidate);
+                    }
+                    Err(err) if warned => debug!("Failed to connect to i3 IPC: {}", err),
+                    Err(err) => {
+                        warn!("Failed to connect to i3 IPC, the binding mode stays hidden until it's available: {}", err);
+                        warned = true;
+                    }
+                }
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(backoff) => {}
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+    }
+
+    pub fn new(config: BindingModeConfig, invalidate: Arc<Notify>, markup: Markup) -> Self {
+        let mode = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_watcher(mode.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            mode,
+            urgent: !config.no_urgent,
+            markup,
+            destruct,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for BindingModeBlock {
+    fn name(&self) -> &str {
+        return "binding_mode";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(mode) = self.mode.lock().unwrap().clone()
+        else { return Vec::new() };
+
+        // Mode names come from the i3 config, which may have markup of its own
+        let mode = if self.markup == Markup::Pango { text::escape_markup(&mode) } else { mode };
+        let mut item = json!({ "full_text": mode });
+        if self.urgent {
+            item["urgent"] = json!(true);
+        }
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The watcher invalidates on mode changes
+        return None;
+    }
+}
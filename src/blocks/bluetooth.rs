@@ -0,0 +1,204 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use dbus::{arg::{self, PropMap}, message::{MatchRule, MessageType}, nonblock::{MsgMatch, Proxy, SyncConnection}};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use crate::{block::Block, bus::Bus, color, icons::{Icon, IconSet}, log::warn};
+
+const BLUEZ: &str = "org.bluez";
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct BluetoothConfig {
+    /// Append the battery level of a connected device that reports one
+    pub show_battery: bool,
+}
+
+impl Default for BluetoothConfig {
+    fn default() -> Self {
+        return Self {
+            show_battery: true,
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct BluetoothState {
+    powered: bool,
+    connected: usize,
+    battery: Option<u8>,
+}
+
+type ManagedObjects = HashMap<dbus::Path<'static>, HashMap<String, PropMap>>;
+
+/// Sums up the adapters and devices BlueZ knows about, None without an adapter
+fn summarize(objects: &ManagedObjects) -> Option<BluetoothState> {
+    let mut adapters = 0;
+    let mut state = BluetoothState { powered: false, connected: 0, battery: None };
+    for interfaces in objects.values() {
+        if let Some(adapter) = interfaces.get("org.bluez.Adapter1") {
+            adapters += 1;
+            state.powered |= arg::prop_cast::<bool>(adapter, "Powered").copied().unwrap_or(false);
+        }
+
+        let Some(device) = interfaces.get("org.bluez.Device1")
+        else { continue };
+
+        if !arg::prop_cast::<bool>(device, "Connected").copied().unwrap_or(false) {
+            continue;
+        }
+        state.connected += 1;
+        if state.battery.is_none() {
+            state.battery = interfaces.get("org.bluez.Battery1")
+                .and_then(|battery| arg::prop_cast::<u8>(battery, "Percentage").copied());
+        }
+    }
+    return (adapters > 0).then_some(state);
+}
+
+/// Adapter power and connected devices from BlueZ on the system bus
+pub struct BluetoothBlock {
+    show_battery: bool,
+    state: Arc<Mutex<Option<BluetoothState>>>,
+    destruct: Arc<Notify>,
+    icons: IconSet,
+}
+
+impl Drop for BluetoothBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl BluetoothBlock {
+    async fn fetch(conn: Arc<SyncConnection>) -> Option<BluetoothState> {
+        let proxy = Proxy::new(BLUEZ, "/", TIMEOUT, conn);
+        return match proxy.method_call("org.freedesktop.DBus.ObjectManager", "GetManagedObjects", ()).await {
+            Ok((objects,)) => summarize(&objects),
+            // Not running, same as having no adapter
+            Err(_) => None,
+        };
+    }
+
+    /// Anything BlueZ signals (InterfacesAdded/Removed, PropertiesChanged) or it (dis)appearing
+    /// notifies `changed`
+    async fn watch(conn: Arc<SyncConnection>, changed: Arc<Notify>) -> Result<[MsgMatch; 2], dbus::Error> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender(BLUEZ);
+        let notify = changed.clone();
+        let signals = conn.add_match(rule).await?
+            .msg_cb(move |_| {
+                notify.notify_one();
+                true
+            });
+
+        let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+            .with_sender("org.freedesktop.DBus");
+        let owner = match conn.add_match(rule).await {
+            Ok(owner) => owner,
+            Err(err) => {
+                let _ = conn.remove_match(signals.token()).await;
+                return Err(err);
+            }
+        };
+        let owner = owner.cb(move |_, (name, _old_owner, _new_owner): (String, String, String)| {
+            if name == BLUEZ {
+                changed.notify_one();
+            }
+            true
+        });
+
+        return Ok([signals, owner]);
+    }
+
+    fn create_watcher(mut bus: Bus, state: Arc<Mutex<Option<BluetoothState>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let set_state = |current: Option<BluetoothState>| {
+                if *state.lock().unwrap() != current {
+                    *state.lock().unwrap() = current;
+                    invalidate.notify_one();
+                }
+            };
+
+            loop {
+                let changed = Arc::new(Notify::new());
+                let watching = match bus.current_and_update() {
+                    Some(conn) => match Self::watch(conn.clone(), changed.clone()).await {
+                        Ok(matches) => Some((conn, matches)),
+                        Err(err) => {
+                            warn!("Failed to AddMatch on BlueZ signals: {}", err);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                loop {
+                    match &watching {
+                        Some((conn, _)) => set_state(Self::fetch(conn.clone()).await),
+                        None => set_state(None),
+                    }
+
+                    tokio::select! {
+                        _ = destruct.notified() => {
+                            if let Some((conn, matches)) = watching {
+                                for r#match in matches {
+                                    let _ = conn.remove_match(r#match.token()).await;
+                                }
+                            }
+                            return;
+                        }
+                        _ = bus.changed() => break,
+                        _ = changed.notified() => {}
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn new(config: BluetoothConfig, bus: Bus, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let state = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_watcher(bus, state.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            show_battery: config.show_battery,
+            state,
+            destruct,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for BluetoothBlock {
+    fn name(&self) -> &str {
+        return "bluetooth";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(state) = *self.state.lock().unwrap()
+        else { return Vec::new() };
+
+        if !state.powered {
+            return vec![json!({ "full_text": format!("BT {}", self.icons.get(Icon::PowerOff)), "color": color::MUTED })];
+        }
+
+        let mut text = format!("BT {}", state.connected);
+        if let (true, Some(battery)) = (self.show_battery, state.battery) {
+            text += &format!(" {}%", battery);
+        }
+        return vec![json!({ "full_text": text })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The watcher invalidates on changes
+        return None;
+    }
+}
@@ -0,0 +1,209 @@
+use std::{fs, path::PathBuf, time::{Duration, Instant}};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::Block, icons::{Icon, IconSet}, log::warn, text::sanitize_text};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct CalendarConfig {
+    /// iCalendar file with the events, e.g. one synced by vdirsyncer
+    pub path: PathBuf,
+    /// Seconds between reading the file again
+    pub interval: u64,
+    /// Only events starting within this many minutes are shown
+    pub window: u64,
+    /// Mark the block urgent this many minutes before an event starts
+    pub lead_time: u64,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        return Self {
+            path: PathBuf::new(),
+            interval: 300,
+            window: 24 * 60,
+            lead_time: 5,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Event {
+    start: DateTime<Utc>,
+    summary: String,
+}
+
+/// Joins folded lines, continuations start with a space or a tab
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    return lines;
+}
+
+/// Undoes the escaping of text values
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push(' '),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    return unescaped;
+}
+
+/// A `DTSTART` value: UTC with a trailing `Z`, in `TZID` when given, otherwise local time.
+/// All day events start at local midnight.
+fn parse_start(params: &str, value: &str) -> Option<DateTime<Utc>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()
+            .map(|start| start.and_utc());
+    }
+
+    let start = match NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        Ok(start) => start,
+        Err(_) => NaiveDate::parse_from_str(value, "%Y%m%d").ok()?.and_hms_opt(0, 0, 0)?,
+    };
+    let timezone = params.split(';')
+        .find_map(|param| param.strip_prefix("TZID="))
+        .and_then(|timezone| timezone.trim_matches('"').parse::<Tz>().ok());
+    return match timezone {
+        Some(timezone) => timezone.from_local_datetime(&start).earliest().map(|start| start.with_timezone(&Utc)),
+        None => Local.from_local_datetime(&start).earliest().map(|start| start.with_timezone(&Utc)),
+    };
+}
+
+/// The `VEVENT`s with a start time. Recurring events only count with their first occurrence.
+fn parse_events(ics: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut start = None;
+    let mut summary = None;
+    let mut in_event = false;
+    for line in unfold(ics) {
+        let Some((name, value)) = line.split_once(':')
+        else { continue };
+
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match (name, value) {
+            ("BEGIN", "VEVENT") => {
+                in_event = true;
+                start = None;
+                summary = None;
+            }
+            ("END", "VEVENT") => {
+                in_event = false;
+                if let Some(start) = start.take() {
+                    events.push(Event { start, summary: summary.take().unwrap_or_default() });
+                }
+            }
+            ("DTSTART", value) if in_event => start = parse_start(params, value),
+            ("SUMMARY", value) if in_event => summary = Some(sanitize_text(&unescape(value))),
+            _ => {}
+        }
+    }
+    return events;
+}
+
+/// The first event starting after `now` but within `window`
+fn next_event(events: &[Event], now: DateTime<Utc>, window: Duration) -> Option<&Event> {
+    let until = now + chrono::Duration::from_std(window).ok()?;
+    return events.iter()
+        .filter(|event| event.start > now && event.start <= until)
+        .min_by_key(|event| event.start);
+}
+
+/// Time until an event as `15m`, `2h 5m` or `1d 3h`, rounded up to whole minutes
+fn format_until(until: Duration) -> String {
+    let minutes = until.as_secs().div_ceil(60);
+    if minutes < 60 {
+        return format!("{}m", minutes);
+    }
+    let (value, unit, rest, rest_unit) = if minutes < 24 * 60 {
+        (minutes / 60, "h", minutes % 60, "m")
+    } else {
+        (minutes / (24 * 60), "d", minutes / 60 % 24, "h")
+    };
+    return match rest {
+        0 => format!("{}{}", value, unit),
+        rest => format!("{}{} {}{}", value, unit, rest, rest_unit),
+    };
+}
+
+/// The next event from a local iCalendar file, hidden when there's none coming up
+pub struct CalendarBlock {
+    path: PathBuf,
+    interval: Duration,
+    window: Duration,
+    lead_time: Duration,
+    events: Vec<Event>,
+    last_read: Option<Instant>,
+    icons: IconSet,
+}
+
+impl CalendarBlock {
+    pub fn new(config: CalendarConfig, icons: IconSet) -> Self {
+        return Self {
+            path: config.path,
+            interval: Duration::from_secs(config.interval),
+            window: Duration::from_secs(config.window * 60),
+            lead_time: Duration::from_secs(config.lead_time * 60),
+            events: Vec::new(),
+            last_read: None,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for CalendarBlock {
+    fn name(&self) -> &str {
+        return "calendar";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        if self.last_read.map_or(true, |last_read| last_read.elapsed() >= self.interval) {
+            self.last_read = Some(Instant::now());
+            match fs::read_to_string(&self.path) {
+                Ok(ics) => self.events = parse_events(&ics),
+                Err(err) => {
+                    warn!("Failed to read {}: {}", self.path.display(), err);
+                    self.events.clear();
+                }
+            }
+        }
+
+        let now = Utc::now();
+        let Some(event) = next_event(&self.events, now, self.window)
+        else { return Vec::new() };
+
+        let until = (event.start - now).to_std().unwrap_or_default();
+        let mut item = json!({
+            "full_text": format!("{} {} in {}", self.icons.get(Icon::Calendar), event.summary, format_until(until)),
+        });
+        if until <= self.lead_time {
+            item["urgent"] = json!(true);
+        }
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The countdown is in minutes
+        return Some(Duration::from_secs(60));
+    }
+}
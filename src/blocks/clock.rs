@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{format::{Item, Numeric, StrftimeItems}, DateTime, Local, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::block::Block;
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// chrono strftime format
+    pub format: String,
+    /// Shown instead of `format` when the bar runs out of space, empty always shows `format`
+    pub short_format: Option<String>,
+    /// IANA timezone like `Europe/Prague`, defaults to the local one
+    pub timezone: Option<Tz>,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        return Self {
+            format: "%a %d.%m.%Y %H:%M".to_string(),
+            short_format: Some("%H:%M".to_string()),
+            timezone: None,
+        };
+    }
+}
+
+/// Whether `format` changes every second, in which case minute aligned updates aren't enough
+fn shows_seconds(format: &str) -> bool {
+    // Composite specifiers like `%T` or `%c` come out as their parts
+    return StrftimeItems::new(format)
+        .any(|item| matches!(item, Item::Numeric(Numeric::Second | Numeric::Timestamp, _)));
+}
+
+/// `instant` formatted in `timezone`, or the local timezone when `None`
+fn format_at(instant: DateTime<Utc>, timezone: Option<Tz>, format: &str) -> String {
+    return match timezone {
+        Some(timezone) => instant.with_timezone(&timezone).format(format).to_string(),
+        None => instant.with_timezone(&Local).format(format).to_string(),
+    };
+}
+
+pub struct ClockBlock {
+    format: String,
+    short_format: Option<String>,
+    timezone: Option<Tz>,
+    seconds: bool,
+}
+
+impl ClockBlock {
+    pub fn new(config: ClockConfig) -> Self {
+        let short_format = config.short_format.filter(|format| !format.is_empty());
+        return Self {
+            seconds: shows_seconds(&config.format) || short_format.as_deref().is_some_and(shows_seconds),
+            format: config.format,
+            short_format,
+            timezone: config.timezone,
+        };
+    }
+
+    /// The items for `now`, `render` passes the current time
+    pub fn render_at(&self, now: DateTime<Utc>) -> Vec<Value> {
+        let mut item = json!({
+            "full_text": format_at(now, self.timezone, &self.format),
+        });
+        if let Some(short_format) = &self.short_format {
+            item["short_text"] = json!(format_at(now, self.timezone, short_format));
+        }
+        return vec![item];
+    }
+}
+
+#[async_trait]
+impl Block for ClockBlock {
+    fn name(&self) -> &str {
+        return "clock";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        return self.render_at(Utc::now());
+    }
+
+    /// Wakes up right when the minute, or second if shown, changes
+    fn interval(&self) -> Option<Duration> {
+        // Timezones are offset by whole minutes so any of them works for finding the next one
+        let now = Utc::now();
+        if self.seconds {
+            return Some(Duration::from_millis((1000 - now.timestamp_subsec_millis().min(999)).into()));
+        }
+        let elapsed_ms = 1000 * now.second() + now.timestamp_subsec_millis();
+        return Some(Duration::from_millis((60000 - elapsed_ms.min(59999)).into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn clock(timezone: &str, format: &str) -> ClockBlock {
+        return ClockBlock::new(ClockConfig {
+            format: format.to_string(),
+            short_format: None,
+            timezone: Some(timezone.parse().unwrap()),
+        });
+    }
+
+    #[test]
+    fn one_instant_in_two_zones() {
+        let instant = Utc.with_ymd_and_hms(2024, 1, 15, 23, 30, 0).unwrap();
+        assert_eq!(clock("UTC", "%a %H:%M").render_at(instant)[0]["full_text"], "Mon 23:30");
+        assert_eq!(clock("Asia/Tokyo", "%a %H:%M").render_at(instant)[0]["full_text"], "Tue 08:30");
+    }
+
+    #[test]
+    fn zones_follow_daylight_saving() {
+        let winter = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2024, 7, 15, 12, 0, 0).unwrap();
+        let prague = clock("Europe/Prague", "%H:%M %Z");
+        assert_eq!(prague.render_at(winter)[0]["full_text"], "13:00 CET");
+        assert_eq!(prague.render_at(summer)[0]["full_text"], "14:00 CEST");
+    }
+
+    #[test]
+    fn short_format_is_optional() {
+        let instant = Utc.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap();
+        let config = ClockConfig { timezone: Some(Tz::UTC), ..ClockConfig::default() };
+        assert_eq!(ClockBlock::new(config).render_at(instant)[0]["short_text"], "14:05");
+
+        let config = ClockConfig { short_format: Some("".to_string()), timezone: Some(Tz::UTC), ..ClockConfig::default() };
+        assert_eq!(ClockBlock::new(config).render_at(instant)[0].get("short_text"), None);
+    }
+
+    #[test]
+    fn unknown_timezone_fails_loading() {
+        let err = toml::from_str::<ClockConfig>("timezone = \"Mars/Olympus_Mons\"").unwrap_err();
+        assert!(err.to_string().contains("Mars/Olympus_Mons"), "{}", err);
+        assert!(toml::from_str::<ClockConfig>("timezone = \"America/New_York\"").is_ok());
+    }
+
+    #[test]
+    fn seconds_in_the_format() {
+        assert!(shows_seconds("%H:%M:%S"));
+        assert!(shows_seconds("%T"));
+        assert!(shows_seconds("%s"));
+        assert!(!shows_seconds("%a %d.%m.%Y %H:%M"));
+    }
+}
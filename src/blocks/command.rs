@@ -0,0 +1,123 @@
+use std::{io, process::Stdio, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, sync::Notify, time::{sleep, timeout}};
+
+use crate::{block::Block, color, log::warn};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct CommandConfig {
+    /// Run with `sh -c`
+    pub command: String,
+    /// Seconds between runs
+    pub interval: u64,
+    /// Seconds before a run is killed and counts as failed
+    pub timeout: u64,
+}
+
+impl Default for CommandConfig {
+    fn default() -> Self {
+        return Self {
+            command: "".to_string(),
+            interval: 10,
+            timeout: 5,
+        };
+    }
+}
+
+/// The item for a line of output, either a JSON object with i3bar fields or plain text
+pub fn parse_output(line: &str) -> Value {
+    return match serde_json::from_str::<Value>(line) {
+        Ok(item @ Value::Object(_)) => item,
+        _ => json!({ "full_text": line }),
+    };
+}
+
+/// First line `command` prints, failing if it takes longer than `limit` or exits unsuccessfully
+pub async fn run_line(command: &str, limit: Duration) -> io::Result<String> {
+    let child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let output = timeout(limit, child.wait_with_output()).await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out"))??;
+    if !output.status.success() {
+        return Err(io::Error::other(output.status.to_string()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    return Ok(stdout.lines().next().unwrap_or("").to_string());
+}
+
+/// Output of a shell command that is run periodically
+pub struct CommandBlock {
+    output: Arc<Mutex<Option<Value>>>,
+    destruct: Arc<Notify>,
+}
+
+impl Drop for CommandBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl CommandBlock {
+    fn create_runner(config: CommandConfig, output: Arc<Mutex<Option<Value>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let limit = Duration::from_secs(config.timeout);
+            loop {
+                let item = match run_line(&config.command, limit).await {
+                    Ok(line) => parse_output(&line),
+                    Err(err) => {
+                        warn!("Command {:?} failed: {}", config.command, err);
+                        json!({ "full_text": "ERROR", "color": color::CRITICAL })
+                    }
+                };
+                *output.lock().unwrap() = Some(item);
+                invalidate.notify_one();
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(Duration::from_secs(config.interval)) => {}
+                }
+            }
+        });
+    }
+
+    pub fn new(config: CommandConfig, invalidate: Arc<Notify>) -> Self {
+        let output = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_runner(config, output.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            output,
+            destruct,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for CommandBlock {
+    fn name(&self) -> &str {
+        return "command";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        return self.output.lock().unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The runner invalidates after every run
+        return None;
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sysinfo::{CpuExt, CpuRefreshKind, System, SystemExt};
+
+use crate::{block::{Align, Block, ClickEvent, MouseButton}, sparkline::{self, History}};
+
+/// Widest total usage, the block stays this wide
+const MIN_WIDTH: &str = "CPU 100%";
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct CpuConfig {
+    /// Left click switches between the global usage and a per core breakdown
+    pub per_core_on_click: bool,
+    /// Show each core as a bar like `▂▅█▃` instead of its percentage
+    pub per_core_bars: bool,
+    /// Renders of total usage to show as a sparkline behind it, 0 shows none
+    pub history: usize,
+}
+
+impl Default for CpuConfig {
+    fn default() -> Self {
+        return Self {
+            per_core_on_click: true,
+            per_core_bars: false,
+            history: 0,
+        };
+    }
+}
+
+pub struct CpuBlock {
+    sys: System,
+    last_refresh: Instant,
+    per_core_on_click: bool,
+    per_core_bars: bool,
+    show_per_core: bool,
+    history: History,
+}
+
+impl CpuBlock {
+    pub fn new(config: CpuConfig) -> Self {
+        let mut sys = System::new();
+        // Usage is calculated between two refreshes, take the first sample right away
+        sys.refresh_cpu_specifics(CpuRefreshKind::new().with_cpu_usage());
+        return Self {
+            sys,
+            last_refresh: Instant::now(),
+            per_core_on_click: config.per_core_on_click,
+            per_core_bars: config.per_core_bars,
+            history: History::new(config.history),
+            show_per_core: false,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for CpuBlock {
+    fn name(&self) -> &str {
+        return "cpu";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        // Only matters for the first render right after startup
+        let since_refresh = self.last_refresh.elapsed();
+        if since_refresh < System::MINIMUM_CPU_UPDATE_INTERVAL {
+            tokio::time::sleep(System::MINIMUM_CPU_UPDATE_INTERVAL - since_refresh).await;
+        }
+        self.sys.refresh_cpu_specifics(CpuRefreshKind::new().with_cpu_usage());
+        self.last_refresh = Instant::now();
+
+        let full_text = if self.show_per_core && self.per_core_bars {
+            let usages: Vec<f64> = self.sys.cpus().iter()
+                .map(|cpu| cpu.cpu_usage() as f64)
+                .collect();
+            format!("CPU {}", sparkline::sparkline(&usages, 100.0))
+        } else if self.show_per_core {
+            let cores: Vec<String> = self.sys.cpus().iter()
+                .map(|cpu| format!("{:.0}%", cpu.cpu_usage()))
+                .collect();
+            format!("CPU {}", cores.join(" "))
+        } else {
+            format!("CPU {:.0}%", self.sys.global_cpu_info().cpu_usage())
+        };
+        // Sampled even while showing cores so the trend has no gaps
+        self.history.push(self.sys.global_cpu_info().cpu_usage() as f64);
+        let trend = self.history.sparkline(100.0);
+        let full_text = if trend.is_empty() { full_text } else { format!("{} {}", full_text, trend) };
+
+        let mut item = json!({ "full_text": full_text });
+        // Keeps the bar from shifting as the usage gains and loses digits
+        if !self.show_per_core {
+            item["min_width"] = json!(MIN_WIDTH);
+            item["align"] = json!(Align::Right.as_str());
+        }
+        return vec![item];
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        if self.per_core_on_click && event.mouse_button() == MouseButton::Left {
+            self.show_per_core = !self.show_per_core;
+        }
+    }
+}
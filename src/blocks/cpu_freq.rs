@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::Block, glob, sysfs};
+
+const SCALING_CUR_FREQ: &str = "/sys/devices/system/cpu/cpu*/cpufreq/scaling_cur_freq";
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrequencySummary {
+    #[default]
+    Average,
+    /// The fastest core
+    Max,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct CpuFreqConfig {
+    pub summary: FrequencySummary,
+}
+
+/// `2.40GHz` from kHz, which is what cpufreq reports, slower than 1GHz shows as `800MHz`
+fn format_frequency(khz: u64) -> String {
+    if khz < 1_000_000 {
+        return format!("{}MHz", khz / 1000);
+    }
+    return format!("{:.2}GHz", khz as f64 / 1_000_000.0);
+}
+
+/// Current clock of the CPU cores, averaged or the highest one
+pub struct CpuFreqBlock {
+    summary: FrequencySummary,
+}
+
+impl CpuFreqBlock {
+    pub fn new(config: CpuFreqConfig) -> Self {
+        return Self {
+            summary: config.summary,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for CpuFreqBlock {
+    fn name(&self) -> &str {
+        return "cpu_freq";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let frequencies: Vec<u64> = glob::resolve(SCALING_CUR_FREQ).into_iter()
+            .filter_map(sysfs::read_value)
+            .collect();
+        // No cpufreq driver, like in most VMs
+        if frequencies.is_empty() {
+            return Vec::new();
+        }
+
+        let khz = match self.summary {
+            FrequencySummary::Average => frequencies.iter().sum::<u64>() / frequencies.len() as u64,
+            FrequencySummary::Max => frequencies.iter().copied().max().unwrap_or(0),
+        };
+        return vec![json!({ "full_text": format_frequency(khz) })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return Some(Duration::from_secs(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gigahertz_with_two_decimals() {
+        assert_eq!(format_frequency(2_400_000), "2.40GHz");
+        assert_eq!(format_frequency(3_792_451), "3.79GHz");
+        assert_eq!(format_frequency(1_000_000), "1.00GHz");
+    }
+
+    #[test]
+    fn megahertz_below_one_gigahertz() {
+        assert_eq!(format_frequency(800_000), "800MHz");
+        // Leftover kHz are dropped rather than rounded up to 1000MHz
+        assert_eq!(format_frequency(999_999), "999MHz");
+        assert_eq!(format_frequency(0), "0MHz");
+    }
+}
@@ -0,0 +1,252 @@
+use std::{cmp::min, sync::{Arc, Mutex, MutexGuard}, time::Duration, io};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, sync::Notify, time::{timeout, sleep}};
+
+use crate::{block::{Block, ClickEvent}, icons::{Icon, IconSet}, log::{debug, warn}};
+
+// VCP feature code for luminance
+const VCP_BRIGHTNESS: &str = "10";
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+// Scroll events coalesce until none arrived for this long
+const SCROLL_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DdcTarget {
+    #[default]
+    Auto,
+    Display(u32),
+    Bus(u32),
+}
+
+impl DdcTarget {
+    fn args(&self) -> Vec<String> {
+        return match self {
+            DdcTarget::Auto => Vec::new(),
+            DdcTarget::Display(n) => vec!["--display".to_string(), n.to_string()],
+            DdcTarget::Bus(n) => vec!["--bus".to_string(), n.to_string()],
+        };
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct DdcConfig {
+    pub target: DdcTarget,
+    /// Brightness change per scroll step, in the monitor's own units
+    pub step: u32,
+}
+
+impl Default for DdcConfig {
+    fn default() -> Self {
+        return Self {
+            target: DdcTarget::Auto,
+            step: 5,
+        };
+    }
+}
+
+pub struct DdcState {
+    invalidate: Arc<Notify>,
+    pub available: bool,
+    pub brightness: u32,
+    pub max: u32,
+    // Set while a scroll is waiting to be written, so polls don't clobber it
+    dirty: bool,
+}
+
+impl DdcState {
+    fn new(invalidate: Arc<Notify>) -> Self {
+        return Self {
+            invalidate,
+            available: false,
+            brightness: 0,
+            max: 100,
+            dirty: false,
+        };
+    }
+
+    fn update(&mut self, reading: Option<(u32, u32)>) {
+        if self.dirty {
+            return;
+        }
+        match reading {
+            Some((brightness, max)) => {
+                self.available = true;
+                self.brightness = brightness;
+                self.max = max;
+            }
+            None => self.available = false,
+        }
+        self.invalidate.notify_one();
+    }
+
+    pub fn percent(&self) -> u32 {
+        if self.max == 0 {
+            return 0;
+        }
+        return self.brightness * 100 / self.max;
+    }
+}
+
+pub struct Ddc {
+    step: u32,
+    scrolled: Arc<Notify>,
+    destruct: Arc<Notify>,
+    state: Arc<Mutex<DdcState>>,
+    icons: IconSet,
+}
+
+impl Drop for Ddc {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+/// Parses the output of `ddcutil getvcp 10 --brief`, e.g. `VCP 10 C 50 100`
+fn parse_getvcp_brief(output: &str) -> Option<(u32, u32)> {
+    let mut fields = output.split_whitespace();
+    if fields.next()? != "VCP" {
+        return None;
+    }
+    fields.next()?;
+    if fields.next()? != "C" {
+        return None;
+    }
+    let current = fields.next()?.parse().ok()?;
+    let max = fields.next()?.parse().ok()?;
+    return Some((current, max));
+}
+
+async fn run_ddcutil(target: &DdcTarget, args: &[&str]) -> io::Result<String> {
+    let command = Command::new("ddcutil")
+        .args(target.args())
+        .args(args)
+        .kill_on_drop(true)
+        .output();
+
+    let output = match timeout(COMMAND_TIMEOUT, command).await {
+        Ok(output) => output?,
+        Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "ddcutil timed out")),
+    };
+
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+}
+
+impl Ddc {
+    fn create_watcher(target: DdcTarget, scrolled: Arc<Notify>, destruct: Arc<Notify>, state: Arc<Mutex<DdcState>>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            // Monitors without DDC fail every poll, only the first failure in a row is a warning
+            let mut warned = false;
+            loop {
+                match run_ddcutil(&target, &["getvcp", VCP_BRIGHTNESS, "--brief"]).await {
+                    Ok(output) => {
+                        warned = false;
+                        state.lock().unwrap().update(parse_getvcp_brief(&output));
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        warn!("ddcutil not found, disabling DDC block");
+                        state.lock().unwrap().update(None);
+                        return;
+                    }
+                    Err(err) if warned => {
+                        debug!("Failed to get DDC brightness: {}", err);
+                        state.lock().unwrap().update(None);
+                    }
+                    Err(err) => {
+                        warn!("Failed to get DDC brightness: {}", err);
+                        warned = true;
+                        state.lock().unwrap().update(None);
+                    }
+                }
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(POLL_INTERVAL) => continue,
+                    _ = scrolled.notified() => {}
+                }
+
+                while timeout(SCROLL_DEBOUNCE, scrolled.notified()).await.is_ok() {}
+
+                let brightness = {
+                    let mut state = state.lock().unwrap();
+                    state.dirty = false;
+                    state.brightness.to_string()
+                };
+                if let Err(err) = run_ddcutil(&target, &["setvcp", VCP_BRIGHTNESS, &brightness]).await {
+                    warn!("Failed to set DDC brightness: {}", err);
+                }
+            }
+        });
+    }
+
+    pub fn new(config: DdcConfig, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let scrolled = Arc::new(Notify::new());
+        let destruct = Arc::new(Notify::new());
+        let state = Arc::new(Mutex::new(DdcState::new(invalidate)));
+
+        Self::create_watcher(config.target, scrolled.clone(), destruct.clone(), state.clone());
+
+        return Self {
+            step: config.step,
+            scrolled,
+            destruct,
+            state,
+            icons,
+        };
+    }
+
+    /// Adjusts the cached brightness by `steps` and schedules a write to the monitor
+    pub fn scroll(&self, steps: i32) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.available {
+                return;
+            }
+            let delta = steps.unsigned_abs() * self.step;
+            state.brightness = if steps < 0 {
+                state.brightness.saturating_sub(delta)
+            } else {
+                min(state.brightness + delta, state.max)
+            };
+            state.dirty = true;
+            state.invalidate.notify_one();
+        }
+        self.scrolled.notify_one();
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, DdcState> {
+        return self.state.lock().unwrap();
+    }
+}
+
+#[async_trait]
+impl Block for Ddc {
+    fn name(&self) -> &str {
+        return "ddc";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let state = self.state();
+        return vec![json!({
+            "full_text": if state.available { format!("{} {}%", self.icons.get(Icon::Brightness), state.percent()) } else { "".to_string() },
+        })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The watcher invalidates whenever the brightness changes
+        return None;
+    }
+
+    fn handle_scroll(&mut self, _event: &ClickEvent, steps: i32) {
+        self.scroll(steps);
+    }
+}
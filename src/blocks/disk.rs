@@ -0,0 +1,262 @@
+use std::{path::PathBuf, time::{Duration, Instant}};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sysinfo::{Disk, DiskExt, System, SystemExt};
+
+use crate::{block::Block, byte_count::ByteCount, color};
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskDisplay {
+    /// `/ 78.11GiB`
+    #[default]
+    Free,
+    /// `/ 45%`
+    Percent,
+    /// `/ 45% (120.3GiB/256.0GiB used)`
+    Usage,
+}
+
+/// Where the label comes from when the config doesn't set one
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelSource {
+    #[default]
+    MountPoint,
+    /// `sda1` for `/dev/sda1`
+    Device,
+    /// `ext4`, `btrfs`...
+    FileSystem,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DiskConfig {
+    pub mount_point: PathBuf,
+    /// Shown in front of the free space, defaults to the one picked by `label_from`
+    pub label: Option<String>,
+    #[serde(default)]
+    pub label_from: LabelSource,
+    /// Seconds between rescans of the mounted disks, free space is updated on every render
+    #[serde(default = "default_rescan_interval")]
+    pub rescan_interval: u64,
+    /// Free space below which the block turns yellow
+    #[serde(default = "default_warning")]
+    pub warning: Option<ByteCount>,
+    /// Free space below which the block turns red
+    #[serde(default = "default_critical")]
+    pub critical: Option<ByteCount>,
+    /// Free space, used percentage or both used and total space
+    #[serde(default)]
+    pub display: DiskDisplay,
+}
+
+fn default_rescan_interval() -> u64 {
+    return 60;
+}
+
+fn default_warning() -> Option<ByteCount> {
+    return Some(ByteCount::from(10 << 30));
+}
+
+fn default_critical() -> Option<ByteCount> {
+    return Some(ByteCount::from(2 << 30));
+}
+
+impl DiskConfig {
+    pub fn new(mount_point: PathBuf, label: Option<String>) -> Self {
+        return Self {
+            mount_point,
+            label,
+            label_from: LabelSource::default(),
+            rescan_interval: default_rescan_interval(),
+            warning: default_warning(),
+            critical: default_critical(),
+            display: DiskDisplay::default(),
+        };
+    }
+}
+
+/// Color for `available` bytes of free space, `None` while above both thresholds
+fn color_for(available: u64, warning: Option<ByteCount>, critical: Option<ByteCount>) -> Option<&'static str> {
+    if critical.is_some_and(|critical| available < critical.bytes()) {
+        return Some(color::CRITICAL);
+    }
+    if warning.is_some_and(|warning| available < warning.bytes()) {
+        return Some(color::WARNING);
+    }
+    return None;
+}
+
+/// The label of `disk` taken from `source`, the mount point when that's empty
+fn label_of(disk: &Disk, source: LabelSource) -> String {
+    let label = match source {
+        LabelSource::MountPoint => "".to_string(),
+        LabelSource::Device => {
+            let name = disk.name().to_string_lossy();
+            name.strip_prefix("/dev/").unwrap_or(&name).to_string()
+        }
+        LabelSource::FileSystem => String::from_utf8_lossy(disk.file_system()).into_owned(),
+    };
+    if label.is_empty() {
+        return disk.mount_point().display().to_string();
+    }
+    return label;
+}
+
+/// Space that isn't available, as a percentage of `total`
+fn used_percent(total: u64, available: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    return total.saturating_sub(available) as f64 / total as f64 * 100.0;
+}
+
+/// `/home: 120.3GiB of 250.0GiB used (48%)`
+fn usage(disk: &Disk) -> String {
+    let total = disk.total_space();
+    let used = total.saturating_sub(disk.available_space());
+    let percent = used_percent(total, disk.available_space());
+    return format!("{}: {:.1} of {:.1} used ({:.0}%)", disk.mount_point().display(), ByteCount::from(used), ByteCount::from(total), percent);
+}
+
+/// Free space on a single mount point
+pub struct DiskBlock {
+    sys: System,
+    mount_point: PathBuf,
+    label: Option<String>,
+    label_from: LabelSource,
+    rescan_interval: Duration,
+    last_rescan: Option<Instant>,
+    warning: Option<ByteCount>,
+    critical: Option<ByteCount>,
+    display: DiskDisplay,
+}
+
+impl DiskBlock {
+    pub fn new(config: DiskConfig) -> Self {
+        return Self {
+            sys: System::new(),
+            mount_point: config.mount_point,
+            label: config.label,
+            label_from: config.label_from,
+            rescan_interval: Duration::from_secs(config.rescan_interval),
+            last_rescan: None,
+            warning: config.warning,
+            critical: config.critical,
+            display: config.display,
+        };
+    }
+
+    /// The item for a disk with `available` of `total` bytes left, `tooltip` lists every disk
+    pub fn render_space(&self, label: &str, available: u64, total: u64, tooltip: &str) -> Vec<Value> {
+        let percent = format!("{:.0}%", used_percent(total, available));
+        // Usage is shortened to just the percentage
+        let (text, short_text) = match self.display {
+            DiskDisplay::Free => {
+                let free = format!("{:.2}", ByteCount::from(available));
+                (free.clone(), free)
+            }
+            DiskDisplay::Percent => (percent.clone(), percent),
+            DiskDisplay::Usage => {
+                let text = format!("{} ({:.1}/{:.1} used)", percent, ByteCount::from(total.saturating_sub(available)), ByteCount::from(total));
+                (text, percent)
+            }
+        };
+        let mut item = json!({
+            "full_text": format!("{} {}", label, text),
+            "short_text": short_text,
+            "tooltip": tooltip,
+        });
+        if let Some(color) = color_for(available, self.warning, self.critical) {
+            item["color"] = json!(color);
+        }
+        return vec![item];
+    }
+}
+
+#[async_trait]
+impl Block for DiskBlock {
+    fn name(&self) -> &str {
+        return "disk";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        // Listing the mounts is expensive and they rarely change
+        if self.last_rescan.map_or(true, |last_rescan| last_rescan.elapsed() >= self.rescan_interval) {
+            self.sys.refresh_disks_list();
+            self.last_rescan = Some(Instant::now());
+        } else {
+            self.sys.refresh_disks();
+        }
+
+        let disk = self.sys.disks().iter()
+            .find(|&val| val.mount_point() == self.mount_point);
+
+        let Some(disk) = disk
+        else {
+            let label = self.label.clone().unwrap_or_else(|| self.mount_point.display().to_string());
+            return vec![json!({ "full_text": format!("{} ERROR", label) })];
+        };
+
+        let label = self.label.clone().unwrap_or_else(|| label_of(disk, self.label_from));
+        let tooltip: Vec<String> = self.sys.disks().iter().map(usage).collect();
+        return self.render_space(&label, disk.available_space(), disk.total_space(), &tooltip.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn colors_from_the_default_thresholds() {
+        let (warning, critical) = (default_warning(), default_critical());
+        assert_eq!(color_for(50 * GIB, warning, critical), None);
+        assert_eq!(color_for(10 * GIB, warning, critical), None);
+        assert_eq!(color_for(10 * GIB - 1, warning, critical), Some(color::WARNING));
+        assert_eq!(color_for(2 * GIB, warning, critical), Some(color::WARNING));
+        assert_eq!(color_for(GIB, warning, critical), Some(color::CRITICAL));
+        assert_eq!(color_for(0, warning, critical), Some(color::CRITICAL));
+    }
+
+    #[test]
+    fn thresholds_can_be_turned_off() {
+        let critical = Some("500MiB".parse().unwrap());
+        assert_eq!(color_for(GIB, None, critical), None);
+        assert_eq!(color_for(100 << 20, None, critical), Some(color::CRITICAL));
+        assert_eq!(color_for(0, None, None), None);
+    }
+
+    #[test]
+    fn item_color_follows_the_free_space() {
+        let block = DiskBlock::new(DiskConfig::new(PathBuf::from("/"), None));
+        assert_eq!(block.render_space("/", 5 * GIB, 100 * GIB, "")[0]["color"], color::WARNING);
+        assert_eq!(block.render_space("/", 50 * GIB, 100 * GIB, "")[0].get("color"), None);
+    }
+
+    #[test]
+    fn used_percent_from_total_and_available() {
+        assert_eq!(used_percent(256 * GIB, 141 * GIB), 115.0 / 256.0 * 100.0);
+        assert_eq!(used_percent(100 * GIB, 100 * GIB), 0.0);
+        assert_eq!(used_percent(100 * GIB, 0), 100.0);
+        // Reserved blocks can make available more than total on some filesystems
+        assert_eq!(used_percent(GIB, 2 * GIB), 0.0);
+        assert_eq!(used_percent(0, 0), 0.0);
+    }
+
+    #[test]
+    fn display_modes() {
+        let render = |display| {
+            let block = DiskBlock::new(DiskConfig { display, ..DiskConfig::new(PathBuf::from("/"), None) });
+            let item = block.render_space("/", 136 * GIB, 256 * GIB, "").remove(0);
+            return (item["full_text"].as_str().unwrap().to_string(), item["short_text"].as_str().unwrap().to_string());
+        };
+        assert_eq!(render(DiskDisplay::Free), ("/ 136.00GiB".to_string(), "136.00GiB".to_string()));
+        assert_eq!(render(DiskDisplay::Percent), ("/ 47%".to_string(), "47%".to_string()));
+        assert_eq!(render(DiskDisplay::Usage), ("/ 47% (120.0GiB/256.0GiB used)".to_string(), "47%".to_string()));
+    }
+}
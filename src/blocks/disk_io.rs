@@ -0,0 +1,188 @@
+use std::{fs, path::Path, time::{Duration, Instant}};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::{Align, Block}, byte_count::ByteRate};
+
+/// /proc/diskstats counts in 512 byte sectors regardless of the device's sector size
+const SECTOR_SIZE: u64 = 512;
+/// Widest rates, each unit goes up to 1023.9 before switching to the next
+const MIN_WIDTH: &str = "DISK R 1023.9MiB/s W 1023.9MiB/s";
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct DiskIoConfig {
+    /// Device name as in /proc/diskstats (`sda`, `nvme0n1p2`, ...) or `auto` for the one `/` is on
+    pub device: String,
+}
+
+impl Default for DiskIoConfig {
+    fn default() -> Self {
+        return Self {
+            device: "auto".to_string(),
+        };
+    }
+}
+
+/// Sectors read and written by `device` according to the contents of /proc/diskstats
+fn parse_diskstats(diskstats: &str, device: &str) -> Option<(u64, u64)> {
+    for line in diskstats.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // major minor name reads merged sectors_read ms writes merged sectors_written ...
+        if fields.len() < 10 || fields[2] != device {
+            continue;
+        }
+        return Some((fields[5].parse().ok()?, fields[9].parse().ok()?));
+    }
+    return None;
+}
+
+/// Name of the block device mounted on `/`, following /dev/mapper and friends to the kernel name
+fn root_device() -> Option<String> {
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+    let source = mountinfo.lines()
+        .find(|line| line.split(' ').nth(4) == Some("/"))?
+        // The optional fields end with a lone `-`, followed by the fs type and source
+        .split(" - ").nth(1)?
+        .split(' ').nth(1)?
+        .to_string();
+    let source = fs::canonicalize(source).ok()?;
+    return source.file_name()?.to_str().map(str::to_string);
+}
+
+struct Snapshot {
+    device: String,
+    sectors_read: u64,
+    sectors_written: u64,
+    taken: Instant,
+}
+
+/// Read and write rates between two snapshots, zero when they're of different devices
+fn rates(previous: Option<&Snapshot>, current: &Snapshot) -> (ByteRate, ByteRate) {
+    let Some(previous) = previous.filter(|previous| previous.device == current.device)
+    else { return (ByteRate::new(0, Duration::ZERO), ByteRate::new(0, Duration::ZERO)) };
+
+    let elapsed = current.taken - previous.taken;
+    let read = current.sectors_read.saturating_sub(previous.sectors_read) * SECTOR_SIZE;
+    let written = current.sectors_written.saturating_sub(previous.sectors_written) * SECTOR_SIZE;
+    return (ByteRate::new(read, elapsed), ByteRate::new(written, elapsed));
+}
+
+/// Read and write throughput of a block device
+pub struct DiskIoBlock {
+    device: String,
+    previous: Option<Snapshot>,
+}
+
+impl DiskIoBlock {
+    pub fn new(config: DiskIoConfig) -> Self {
+        return Self {
+            device: config.device,
+            previous: None,
+        };
+    }
+
+    fn snapshot(&self) -> Option<Snapshot> {
+        let device = if self.device == "auto" { root_device()? } else { self.device.clone() };
+        let diskstats = fs::read_to_string(Path::new("/proc/diskstats")).ok()?;
+        let (sectors_read, sectors_written) = parse_diskstats(&diskstats, &device)?;
+        return Some(Snapshot {
+            device,
+            sectors_read,
+            sectors_written,
+            taken: Instant::now(),
+        });
+    }
+}
+
+#[async_trait]
+impl Block for DiskIoBlock {
+    fn name(&self) -> &str {
+        return "disk_io";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(current) = self.snapshot()
+        else {
+            self.previous = None;
+            return vec![json!({ "full_text": "DISK ERROR" })];
+        };
+
+        let (read, written) = rates(self.previous.as_ref(), &current);
+        self.previous = Some(current);
+
+        return vec![json!({
+            "full_text": format!("DISK R {:.1} W {:.1}", read, written),
+            "min_width": MIN_WIDTH,
+            "align": Align::Right.as_str(),
+        })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return Some(Duration::from_secs(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEFORE: &str = "\
+   8       0 sda 51234 1201 4096000 30120 80011 5120 2048000 91230 0 61200 121350 0 0 0 0
+   8       1 sda1 51000 1201 4090000 30000 80000 5120 2047000 91200 0 61000 121200 0 0 0 0
+ 259       0 nvme0n1 120 0 9600 40 12 0 96 8 0 60 48 0 0 0 0
+";
+    const AFTER: &str = "\
+   8       0 sda 51300 1201 4104192 30140 80100 5122 2048240 91280 0 61260 121420 0 0 0 0
+   8       1 sda1 51066 1201 4098192 30020 80089 5122 2047240 91250 0 61060 121270 0 0 0 0
+ 259       0 nvme0n1 130 0 9680 42 12 0 96 8 0 62 50 0 0 0 0
+";
+
+    fn snapshot(diskstats: &str, device: &str, taken: Instant) -> Snapshot {
+        let (sectors_read, sectors_written) = parse_diskstats(diskstats, device).unwrap();
+        return Snapshot { device: device.to_string(), sectors_read, sectors_written, taken };
+    }
+
+    #[test]
+    fn finds_the_device_by_its_exact_name() {
+        assert_eq!(parse_diskstats(BEFORE, "sda"), Some((4096000, 2048000)));
+        assert_eq!(parse_diskstats(BEFORE, "sda1"), Some((4090000, 2047000)));
+        assert_eq!(parse_diskstats(BEFORE, "sdb"), None);
+        assert_eq!(parse_diskstats("   8       0 sda 1 2 3\n", "sda"), None);
+    }
+
+    #[test]
+    fn sectors_become_bytes_per_second() {
+        let start = Instant::now();
+        let previous = snapshot(BEFORE, "sda", start);
+        let current = snapshot(AFTER, "sda", start + Duration::from_secs(2));
+        let (read, written) = rates(Some(&previous), &current);
+        // 8192 sectors read and 240 written over 2 seconds
+        assert_eq!(read.bytes_per_second(), 8192 * 512 / 2);
+        assert_eq!(written.bytes_per_second(), 240 * 512 / 2);
+        assert_eq!(format!("DISK R {:.1} W {:.1}", read, written), "DISK R 2.0MiB/s W 60.0KiB/s");
+    }
+
+    #[test]
+    fn nothing_to_compare_against() {
+        let start = Instant::now();
+        let current = snapshot(AFTER, "nvme0n1", start);
+        assert_eq!(rates(None, &current).0.bytes_per_second(), 0);
+
+        // Switching devices doesn't count the difference between them
+        let previous = snapshot(BEFORE, "sda", start);
+        let current = snapshot(AFTER, "nvme0n1", start + Duration::from_secs(1));
+        let (read, written) = rates(Some(&previous), &current);
+        assert_eq!((read.bytes_per_second(), written.bytes_per_second()), (0, 0));
+    }
+
+    #[test]
+    fn counters_going_back_are_zero() {
+        let start = Instant::now();
+        let previous = snapshot(AFTER, "sda", start);
+        let current = snapshot(BEFORE, "sda", start + Duration::from_secs(1));
+        assert_eq!(rates(Some(&previous), &current).0.bytes_per_second(), 0);
+    }
+}
@@ -0,0 +1,229 @@
+use std::{process::Stdio, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use dbus::{arg, message::MatchRule, nonblock::{MsgMatch, Proxy, SyncConnection}};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, sync::Notify, time::{sleep, timeout}};
+
+use crate::{block::{Block, ClickEvent, MouseButton}, bus::Bus, color, icons::{Icon, IconSet}, log::warn};
+
+const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+const DUNST_INTERFACE: &str = "org.dunstproject.cmd0";
+const MAKO_MODE: &str = "do-not-disturb";
+const TIMEOUT: Duration = Duration::from_secs(2);
+// For daemons or versions that don't signal changes
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Daemon {
+    /// The `paused` property over DBus
+    #[default]
+    Dunst,
+    /// The `do-not-disturb` mode through `makoctl`
+    Mako,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct DndConfig {
+    pub daemon: Daemon,
+}
+
+async fn makoctl(args: &[&str]) -> Option<String> {
+    let output = Command::new("makoctl")
+        .args(args)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = timeout(TIMEOUT, output).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    return Some(String::from_utf8_lossy(&output.stdout).into_owned());
+}
+
+/// Whether notifications are paused, None when the daemon isn't running
+async fn query(daemon: Daemon, conn: Option<Arc<SyncConnection>>) -> Option<bool> {
+    return match daemon {
+        Daemon::Dunst => {
+            let proxy = Proxy::new(NOTIFICATIONS, OBJECT_PATH, TIMEOUT, conn?);
+            let (paused,): (arg::Variant<bool>,) = proxy
+                .method_call("org.freedesktop.DBus.Properties", "Get", (DUNST_INTERFACE, "paused")).await
+                .ok()?;
+            Some(paused.0)
+        }
+        Daemon::Mako => Some(makoctl(&["mode"]).await?.lines().any(|mode| mode.trim() == MAKO_MODE)),
+    };
+}
+
+async fn set_paused(daemon: Daemon, conn: Option<Arc<SyncConnection>>, paused: bool) {
+    match daemon {
+        Daemon::Dunst => {
+            let Some(conn) = conn
+            else { return };
+
+            let proxy = Proxy::new(NOTIFICATIONS, OBJECT_PATH, TIMEOUT, conn);
+            let reply: Result<(), _> = proxy
+                .method_call("org.freedesktop.DBus.Properties", "Set", (DUNST_INTERFACE, "paused", arg::Variant(paused))).await;
+            if let Err(err) = reply {
+                warn!("Failed to set do not disturb: {}", err);
+            }
+        }
+        Daemon::Mako => {
+            let mode = if paused { "-a" } else { "-r" };
+            if makoctl(&["mode", mode, MAKO_MODE]).await.is_none() {
+                warn!("Failed to set do not disturb with makoctl");
+            }
+        }
+    }
+}
+
+/// Notifies `changed` when dunst signals a property change or (dis)appears
+async fn watch(conn: Arc<SyncConnection>, changed: Arc<Notify>) -> Result<[MsgMatch; 2], dbus::Error> {
+    let rule = MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+        .with_sender(NOTIFICATIONS)
+        .with_path(OBJECT_PATH);
+    let notify = changed.clone();
+    let properties = conn.add_match(rule).await?
+        .msg_cb(move |_| {
+            notify.notify_one();
+            true
+        });
+
+    let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+        .with_sender("org.freedesktop.DBus");
+    let owner = match conn.add_match(rule).await {
+        Ok(owner) => owner,
+        Err(err) => {
+            let _ = conn.remove_match(properties.token()).await;
+            return Err(err);
+        }
+    };
+    let owner = owner.cb(move |_, (name, _old_owner, _new_owner): (String, String, String)| {
+        if name == NOTIFICATIONS {
+            changed.notify_one();
+        }
+        true
+    });
+
+    return Ok([properties, owner]);
+}
+
+/// Do not disturb state of the notification daemon, toggled by clicking
+pub struct DndBlock {
+    daemon: Daemon,
+    bus: Bus,
+    /// None while the daemon isn't running
+    paused: Arc<Mutex<Option<bool>>>,
+    /// Makes the watcher check again after a toggle
+    toggled: Arc<Notify>,
+    destruct: Arc<Notify>,
+    icons: IconSet,
+}
+
+impl Drop for DndBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl DndBlock {
+    fn create_watcher(daemon: Daemon, mut bus: Bus, paused: Arc<Mutex<Option<bool>>>, toggled: Arc<Notify>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            loop {
+                let conn = bus.current_and_update();
+                let changed = Arc::new(Notify::new());
+                let watching = match (daemon, &conn) {
+                    (Daemon::Dunst, Some(conn)) => match watch(conn.clone(), changed.clone()).await {
+                        Ok(matches) => Some(matches),
+                        Err(err) => {
+                            warn!("Failed to AddMatch on notification daemon signals: {}", err);
+                            None
+                        }
+                    },
+                    _ => None,
+                };
+
+                loop {
+                    let current = query(daemon, conn.clone()).await;
+                    if *paused.lock().unwrap() != current {
+                        *paused.lock().unwrap() = current;
+                        invalidate.notify_one();
+                    }
+
+                    tokio::select! {
+                        _ = destruct.notified() => {
+                            if let (Some(conn), Some(matches)) = (&conn, watching) {
+                                for r#match in matches {
+                                    let _ = conn.remove_match(r#match.token()).await;
+                                }
+                            }
+                            return;
+                        }
+                        _ = bus.changed() => break,
+                        _ = changed.notified() => {}
+                        _ = toggled.notified() => {}
+                        _ = sleep(POLL_INTERVAL) => {}
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn new(config: DndConfig, bus: Bus, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let paused = Arc::new(Mutex::new(None));
+        let toggled = Arc::new(Notify::new());
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_watcher(config.daemon, bus.clone(), paused.clone(), toggled.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            daemon: config.daemon,
+            bus,
+            paused,
+            toggled,
+            destruct,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for DndBlock {
+    fn name(&self) -> &str {
+        return "dnd";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let item = match *self.paused.lock().unwrap() {
+            None => return Vec::new(),
+            Some(true) => json!({ "full_text": self.icons.get(Icon::NotificationsOff), "color": color::MUTED }),
+            Some(false) => json!({ "full_text": self.icons.get(Icon::NotificationsOn) }),
+        };
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The watcher invalidates on changes
+        return None;
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        if event.mouse_button() != MouseButton::Left {
+            return;
+        }
+        let Some(paused) = *self.paused.lock().unwrap()
+        else { return };
+
+        let daemon = self.daemon;
+        let conn = self.bus.current();
+        let toggled = self.toggled.clone();
+        tokio::spawn(async move {
+            set_paused(daemon, conn, !paused).await;
+            toggled.notify_one();
+        });
+    }
+}
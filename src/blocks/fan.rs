@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::Block, blocks::temperature::{read_celsius, TemperatureConfig}, color, glob, sysfs};
+
+const ALL_FANS: &str = "/sys/class/hwmon/hwmon*/fan*_input";
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct FanConfig {
+    /// Fan inputs reading RPM, wildcards match several. `all` is every fan hwmon knows.
+    pub fans: Vec<String>,
+    /// Sensor input in millidegrees, a stopped fan while it's hotter than `critical` may
+    /// have failed
+    pub temperature_path: String,
+    pub critical: f64,
+}
+
+impl Default for FanConfig {
+    fn default() -> Self {
+        return Self {
+            fans: vec!["all".to_string()],
+            temperature_path: TemperatureConfig::default().path,
+            critical: 70.0,
+        };
+    }
+}
+
+/// Speed of one or more fans like `FAN 1800rpm 950rpm`
+pub struct FanBlock {
+    fans: Vec<String>,
+    temperature_path: String,
+    critical: f64,
+}
+
+impl FanBlock {
+    pub fn new(config: FanConfig) -> Self {
+        let fans = config.fans.into_iter()
+            .map(|fan| if fan == "all" { ALL_FANS.to_string() } else { fan })
+            .collect();
+        return Self {
+            fans,
+            temperature_path: config.temperature_path,
+            critical: config.critical,
+        };
+    }
+
+    /// Inputs of the configured fans, fans come and go with their drivers so this is resolved
+    /// on every render
+    fn inputs(&self) -> Vec<PathBuf> {
+        let mut inputs = Vec::new();
+        for input in self.fans.iter().flat_map(|fan| glob::resolve(fan)) {
+            // A fan matched by several patterns is shown once, where it first matched
+            if !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+        return inputs;
+    }
+}
+
+#[async_trait]
+impl Block for FanBlock {
+    fn name(&self) -> &str {
+        return "fan";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let speeds: Vec<u64> = self.inputs().into_iter()
+            .filter_map(sysfs::read_value)
+            .collect();
+        if speeds.is_empty() {
+            return Vec::new();
+        }
+
+        let text: Vec<String> = speeds.iter().map(|rpm| format!("{}rpm", rpm)).collect();
+        let mut item = json!({ "full_text": format!("FAN {}", text.join(" ")) });
+        let hot = read_celsius(&self.temperature_path).is_some_and(|celsius| celsius >= self.critical);
+        if hot && speeds.contains(&0) {
+            item["color"] = json!(color::CRITICAL);
+        }
+        return vec![item];
+    }
+}
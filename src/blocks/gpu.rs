@@ -0,0 +1,173 @@
+use std::{path::Path, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{block::Block, blocks::command::run_line, glob, log::warn, sysfs};
+
+const NVIDIA_SMI: &str = "nvidia-smi --query-gpu=utilization.gpu,temperature.gpu --format=csv,noheader,nounits";
+const AMD_BUSY: &str = "/sys/class/drm/card*/device/gpu_busy_percent";
+/// nvidia-smi takes a while to start up, slower than this means something's wrong
+const NVIDIA_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuBackend {
+    /// AMD when its sysfs files exist, NVIDIA otherwise
+    #[default]
+    Auto,
+    Nvidia,
+    Amd,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct GpuConfig {
+    pub backend: GpuBackend,
+    /// Seconds between updates
+    pub interval: u64,
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        return Self {
+            backend: GpuBackend::default(),
+            interval: 5,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GpuStats {
+    usage: u32,
+    /// Degrees celsius
+    temperature: Option<f64>,
+}
+
+/// Stats of the first GPU from nvidia-smi's `usage, temperature` lines
+fn parse_nvidia_smi(line: &str) -> Option<GpuStats> {
+    let mut fields = line.split(',').map(str::trim);
+    let usage = fields.next()?.parse().ok()?;
+    let temperature = fields.next().and_then(|temperature| temperature.parse().ok());
+    return Some(GpuStats { usage, temperature });
+}
+
+/// Stats of the first AMD card, its temperature comes from the card's own hwmon
+fn read_amd(busy_path: &Path) -> Option<GpuStats> {
+    let usage = sysfs::read_value(busy_path)?;
+    let device = busy_path.parent()?;
+    let temperature = glob::resolve(&format!("{}/hwmon/hwmon*/temp1_input", device.display()))
+        .into_iter()
+        .next()
+        .and_then(sysfs::read_value::<i64>)
+        .map(|millidegrees| millidegrees as f64 / 1000.0);
+    return Some(GpuStats { usage, temperature });
+}
+
+/// Whether `program` is in one of the `PATH` directories
+fn in_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH")
+    else { return false };
+
+    return std::env::split_paths(&path).any(|directory| directory.join(program).is_file());
+}
+
+/// The backend `Auto` stands for, `None` when there's no supported GPU
+fn detect() -> Option<GpuBackend> {
+    if !glob::resolve(AMD_BUSY).is_empty() {
+        return Some(GpuBackend::Amd);
+    }
+    if in_path("nvidia-smi") {
+        return Some(GpuBackend::Nvidia);
+    }
+    return None;
+}
+
+async fn read(backend: GpuBackend) -> Option<GpuStats> {
+    return match backend {
+        GpuBackend::Amd => read_amd(glob::resolve(AMD_BUSY).first()?),
+        GpuBackend::Nvidia => match run_line(NVIDIA_SMI, NVIDIA_TIMEOUT).await {
+            Ok(line) => parse_nvidia_smi(&line),
+            Err(err) => {
+                warn!("Failed to run nvidia-smi: {}", err);
+                None
+            }
+        },
+        GpuBackend::Auto => None,
+    };
+}
+
+/// GPU usage and temperature, hidden without a supported GPU
+pub struct GpuBlock {
+    stats: Arc<Mutex<Option<GpuStats>>>,
+    destruct: Arc<Notify>,
+}
+
+impl Drop for GpuBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl GpuBlock {
+    fn create_poller(backend: GpuBackend, interval: Duration, stats: Arc<Mutex<Option<GpuStats>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let backend = match backend {
+                GpuBackend::Auto => match detect() {
+                    Some(backend) => backend,
+                    None => return,
+                },
+                backend => backend,
+            };
+            loop {
+                let current = tokio::select! {
+                    _ = destruct.notified() => return,
+                    current = read(backend) => current,
+                };
+                if *stats.lock().unwrap() != current {
+                    *stats.lock().unwrap() = current;
+                    invalidate.notify_one();
+                }
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(interval) => {}
+                }
+            }
+        });
+    }
+
+    pub fn new(config: GpuConfig, invalidate: Arc<Notify>) -> Self {
+        let stats = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_poller(config.backend, Duration::from_secs(config.interval), stats.clone(), destruct.clone(), invalidate);
+
+        return Self { stats, destruct };
+    }
+}
+
+#[async_trait]
+impl Block for GpuBlock {
+    fn name(&self) -> &str {
+        return "gpu";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(stats) = *self.stats.lock().unwrap()
+        else { return Vec::new() };
+
+        let mut text = format!("GPU {}%", stats.usage);
+        if let Some(temperature) = stats.temperature {
+            text += &format!(" {:.0}°C", temperature);
+        }
+        return vec![json!({ "full_text": text })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The poller invalidates on changes
+        return None;
+    }
+}
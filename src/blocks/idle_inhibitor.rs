@@ -0,0 +1,167 @@
+use std::{process::Stdio, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use dbus::{arg::OwnedFd, nonblock::Proxy};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, sync::{oneshot, Notify}};
+
+use crate::{block::{Block, ClickEvent, MouseButton}, bus::Bus, color, icons::{Icon, IconSet}, log::warn};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct IdleInhibitorConfig {
+    /// Inhibits idle for as long as it runs, like `wayland-idle-inhibitor`. Without one
+    /// an inhibitor lock is taken from systemd-logind.
+    pub command: Option<String>,
+}
+
+/// Keeps idle inhibited until dropped, it's only held and never read
+#[allow(dead_code)]
+enum Inhibitor {
+    /// logind releases the lock when its file descriptor is closed
+    Logind(OwnedFd),
+    /// The task running the command kills it when this is dropped
+    Command(oneshot::Sender<()>),
+}
+
+enum State {
+    Off,
+    /// Waiting for logind to hand out the lock
+    Pending,
+    /// Held for as long as idle is inhibited
+    On(#[allow(dead_code)] Inhibitor),
+}
+
+/// Toggles idle inhibition on click
+pub struct IdleInhibitorBlock {
+    command: Option<String>,
+    /// Only connected when logind is used
+    bus: Option<Bus>,
+    state: Arc<Mutex<State>>,
+    invalidate: Arc<Notify>,
+    icons: IconSet,
+}
+
+impl IdleInhibitorBlock {
+    pub fn new(config: IdleInhibitorConfig, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        return Self {
+            bus: config.command.is_none().then(Bus::connect_system),
+            command: config.command,
+            state: Arc::new(Mutex::new(State::Off)),
+            invalidate,
+            icons,
+        };
+    }
+
+    fn inhibit(&self) {
+        if let Some(command) = &self.command {
+            // exec so that killing the shell stops the command instead of orphaning it
+            let child = Command::new("sh")
+                .args(["-c", &format!("exec {}", command)])
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .spawn();
+            let mut child = match child {
+                Ok(child) => child,
+                Err(err) => {
+                    warn!("Failed to run {:?}: {}", command, err);
+                    return;
+                }
+            };
+
+            let (stop, mut stopped) = oneshot::channel();
+            *self.state.lock().unwrap() = State::On(Inhibitor::Command(stop));
+            let state = self.state.clone();
+            let invalidate = self.invalidate.clone();
+            let command = command.clone();
+            tokio::spawn(async move {
+                let status = tokio::select! {
+                    // Toggled off, dropping the child kills it
+                    _ = &mut stopped => return,
+                    status = child.wait() => status,
+                };
+
+                let mut state = state.lock().unwrap();
+                // The sender only lives in the state, so while it's there this is still the
+                // command being shown as inhibiting
+                if stopped.try_recv() != Err(oneshot::error::TryRecvError::Empty) {
+                    return;
+                }
+                match status {
+                    Ok(status) => warn!("{:?} exited with {}, idle isn't inhibited anymore", command, status),
+                    Err(err) => warn!("Failed to wait for {:?}, idle isn't inhibited anymore: {}", command, err),
+                }
+                *state = State::Off;
+                invalidate.notify_one();
+            });
+            return;
+        }
+
+        let Some(conn) = self.bus.as_ref().and_then(Bus::current)
+        else {
+            warn!("Can't inhibit idle without a connection to the system bus");
+            return;
+        };
+
+        *self.state.lock().unwrap() = State::Pending;
+        let state = self.state.clone();
+        let invalidate = self.invalidate.clone();
+        tokio::spawn(async move {
+            let proxy = Proxy::new("org.freedesktop.login1", "/org/freedesktop/login1", TIMEOUT, conn);
+            let reply: Result<(OwnedFd,), _> = proxy.method_call(
+                "org.freedesktop.login1.Manager",
+                "Inhibit",
+                ("idle", "i3-status-rs", "Idle inhibitor block", "block"),
+            ).await;
+
+            let mut state = state.lock().unwrap();
+            // Toggled off again while waiting, dropping the lock releases it right away
+            if !matches!(*state, State::Pending) {
+                return;
+            }
+            *state = match reply {
+                Ok((fd,)) => State::On(Inhibitor::Logind(fd)),
+                Err(err) => {
+                    warn!("Failed to take an idle inhibitor lock: {}", err);
+                    State::Off
+                }
+            };
+            invalidate.notify_one();
+        });
+    }
+}
+
+#[async_trait]
+impl Block for IdleInhibitorBlock {
+    fn name(&self) -> &str {
+        return "idle_inhibitor";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let item = match *self.state.lock().unwrap() {
+            State::On(_) => json!({ "full_text": self.icons.get(Icon::IdleInhibited) }),
+            State::Pending | State::Off => json!({ "full_text": self.icons.get(Icon::IdleAllowed), "color": color::MUTED }),
+        };
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // Only changes by clicking
+        return None;
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        if event.mouse_button() != MouseButton::Left {
+            return;
+        }
+        let off = matches!(*self.state.lock().unwrap(), State::Off);
+        if off {
+            self.inhibit();
+        } else {
+            *self.state.lock().unwrap() = State::Off;
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, sync::Notify, time::sleep};
+
+use crate::{block::{Block, ClickEvent, MouseButton}, blocks::command::run_line, log::warn};
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct KeyboardLayoutConfig {
+    /// Prints the active layout, on sway something like
+    /// `swaymsg -t get_inputs | jq -r 'map(.xkb_active_layout_name | values) | first'`
+    pub command: String,
+    /// Switches to the next layout when the block is clicked
+    pub next_command: Option<String>,
+    /// Seconds between checks of the active layout
+    pub interval: u64,
+}
+
+impl Default for KeyboardLayoutConfig {
+    fn default() -> Self {
+        return Self {
+            command: "xkb-switch -p".to_string(),
+            next_command: Some("xkb-switch -n".to_string()),
+            interval: 1,
+        };
+    }
+}
+
+/// The active keyboard layout as reported by a command
+pub struct KeyboardLayoutBlock {
+    layout: Arc<Mutex<Option<String>>>,
+    next_command: Option<String>,
+    /// Wakes the poller so a switch shows up right away
+    switched: Arc<Notify>,
+    destruct: Arc<Notify>,
+}
+
+impl Drop for KeyboardLayoutBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl KeyboardLayoutBlock {
+    fn create_poller(command: String, interval: Duration, layout: Arc<Mutex<Option<String>>>, switched: Arc<Notify>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            loop {
+                let current = match run_line(&command, COMMAND_TIMEOUT).await {
+                    Ok(current) => Some(current),
+                    Err(err) => {
+                        warn!("Failed to get keyboard layout with {:?}: {}", command, err);
+                        None
+                    }
+                };
+
+                // Polled often, only redraw when the layout actually changed
+                if *layout.lock().unwrap() != current {
+                    *layout.lock().unwrap() = current;
+                    invalidate.notify_one();
+                }
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = switched.notified() => {}
+                    _ = sleep(interval) => {}
+                }
+            }
+        });
+    }
+
+    pub fn new(config: KeyboardLayoutConfig, invalidate: Arc<Notify>) -> Self {
+        let layout = Arc::new(Mutex::new(None));
+        let switched = Arc::new(Notify::new());
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_poller(config.command, Duration::from_secs(config.interval), layout.clone(), switched.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            layout,
+            next_command: config.next_command,
+            switched,
+            destruct,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for KeyboardLayoutBlock {
+    fn name(&self) -> &str {
+        return "keyboard_layout";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(layout) = self.layout.lock().unwrap().clone()
+        else { return Vec::new() };
+
+        return vec![json!({ "full_text": layout })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The poller invalidates on changes
+        return None;
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        if event.mouse_button() != MouseButton::Left {
+            return;
+        }
+        let Some(next_command) = self.next_command.clone()
+        else { return };
+
+        let switched = self.switched.clone();
+        tokio::spawn(async move {
+            match Command::new("sh").args(["-c", &next_command]).status().await {
+                Ok(status) if !status.success() => warn!("{:?} failed: {}", next_command, status),
+                Ok(_) => {}
+                Err(err) => warn!("Failed to run {:?}: {}", next_command, err),
+            }
+            switched.notify_one();
+        });
+    }
+}
@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sysinfo::{LoadAvg, System, SystemExt};
+
+use crate::{block::Block, color};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct LoadConfig {
+    /// How many of the 1, 5 and 15 minute averages to show
+    pub averages: usize,
+    /// Turn yellow when the 1 minute average is above the number of CPUs
+    pub warn_above_cpus: bool,
+}
+
+impl Default for LoadConfig {
+    fn default() -> Self {
+        return Self {
+            averages: 3,
+            warn_above_cpus: true,
+        };
+    }
+}
+
+/// `L 0.52 0.61 0.70` with the first `averages` of the three
+fn format_load(load: &LoadAvg, averages: usize) -> String {
+    let mut text = "L".to_string();
+    for average in [load.one, load.five, load.fifteen].iter().take(averages.clamp(1, 3)) {
+        text += &format!(" {:.2}", average);
+    }
+    return text;
+}
+
+/// System load averages
+pub struct LoadBlock {
+    sys: System,
+    averages: usize,
+    warn_above_cpus: bool,
+    cpus: usize,
+}
+
+impl LoadBlock {
+    pub fn new(config: LoadConfig) -> Self {
+        return Self {
+            sys: System::new(),
+            averages: config.averages,
+            warn_above_cpus: config.warn_above_cpus,
+            cpus: std::thread::available_parallelism().map(|cpus| cpus.get()).unwrap_or(1),
+        };
+    }
+}
+
+#[async_trait]
+impl Block for LoadBlock {
+    fn name(&self) -> &str {
+        return "load";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let load = self.sys.load_average();
+        let mut item = json!({ "full_text": format_load(&load, self.averages) });
+        if self.warn_above_cpus && load.one > self.cpus as f64 {
+            item["color"] = json!(color::WARNING);
+        }
+        return vec![item];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUSY: LoadAvg = LoadAvg { one: 0.52, five: 0.61, fifteen: 0.7 };
+
+    #[test]
+    fn all_three_averages() {
+        assert_eq!(format_load(&BUSY, 3), "L 0.52 0.61 0.70");
+    }
+
+    #[test]
+    fn only_the_first_minute() {
+        assert_eq!(format_load(&BUSY, 1), "L 0.52");
+        assert_eq!(format_load(&BUSY, 2), "L 0.52 0.61");
+    }
+
+    #[test]
+    fn out_of_range_counts_are_clamped() {
+        assert_eq!(format_load(&BUSY, 0), "L 0.52");
+        assert_eq!(format_load(&BUSY, 10), "L 0.52 0.61 0.70");
+    }
+
+    #[test]
+    fn two_decimals_however_loaded() {
+        let load = LoadAvg { one: 12.345, five: 0.0, fifteen: 100.0 };
+        assert_eq!(format_load(&load, 3), "L 12.35 0.00 100.00");
+    }
+}
@@ -0,0 +1,133 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{block::Block, color, glob, sysfs};
+
+// Reading a handful of sysfs files is cheap enough to do this often
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct LockKeysConfig {
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    /// Mark active locks urgent instead of only coloring them
+    pub urgent: bool,
+}
+
+impl Default for LockKeysConfig {
+    fn default() -> Self {
+        return Self {
+            caps_lock: true,
+            num_lock: true,
+            urgent: false,
+        };
+    }
+}
+
+/// Whether any keyboard has the `led` (`capslock`, `numlock`) lit, None without such an LED
+fn led_state(led: &str) -> Option<bool> {
+    let mut state = None;
+    for path in glob::resolve(&format!("/sys/class/leds/input*::{}/brightness", led)) {
+        let Some(brightness) = sysfs::read_value::<u32>(path)
+        else { continue };
+
+        state = Some(state.unwrap_or(false) || brightness > 0);
+    }
+    return state;
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+struct LockState {
+    caps_lock: Option<bool>,
+    num_lock: Option<bool>,
+}
+
+/// Caps Lock and Num Lock indicators from the keyboard LEDs
+pub struct LockKeysBlock {
+    config: LockKeysConfig,
+    state: Arc<Mutex<LockState>>,
+    destruct: Arc<Notify>,
+}
+
+impl Drop for LockKeysBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl LockKeysBlock {
+    fn create_poller(state: Arc<Mutex<LockState>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            loop {
+                let current = LockState {
+                    caps_lock: led_state("capslock"),
+                    num_lock: led_state("numlock"),
+                };
+                if *state.lock().unwrap() != current {
+                    *state.lock().unwrap() = current;
+                    invalidate.notify_one();
+                }
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(POLL_INTERVAL) => {}
+                }
+            }
+        });
+    }
+
+    pub fn new(config: LockKeysConfig, invalidate: Arc<Notify>) -> Self {
+        let state = Arc::new(Mutex::new(LockState::default()));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_poller(state.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            config,
+            state,
+            destruct,
+        };
+    }
+
+    fn indicator(&self, text: &str, active: bool) -> Value {
+        let mut item = json!({ "full_text": text });
+        if !active {
+            item["color"] = json!(color::MUTED);
+        } else if self.config.urgent {
+            item["urgent"] = json!(true);
+        } else {
+            item["color"] = json!(color::WARNING);
+        }
+        return item;
+    }
+}
+
+#[async_trait]
+impl Block for LockKeysBlock {
+    fn name(&self) -> &str {
+        return "lock_keys";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let state = *self.state.lock().unwrap();
+
+        let mut items = Vec::new();
+        if let (true, Some(active)) = (self.config.caps_lock, state.caps_lock) {
+            items.push(self.indicator("CAPS", active));
+        }
+        if let (true, Some(active)) = (self.config.num_lock, state.num_lock) {
+            items.push(self.indicator("NUM", active));
+        }
+        return items;
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The poller invalidates on changes
+        return None;
+    }
+}
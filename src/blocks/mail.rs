@@ -0,0 +1,149 @@
+use std::{fs, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{block::Block, icons::{Icon, IconSet}, inotify::{self, Inotify}, log::warn};
+
+// Catches changes in case inotify isn't available or missed something
+const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug)]
+pub struct MailConfig {
+    /// Maildirs to count unread messages in, the directories with `new` and `cur` in them
+    pub maildirs: Vec<PathBuf>,
+    /// Mark the block urgent above this many unread messages
+    #[serde(default)]
+    pub urgent: Option<usize>,
+    /// Show `0` instead of hiding when there's nothing unread
+    #[serde(default)]
+    pub show_zero: bool,
+}
+
+/// Whether the maildir file `name` hasn't been seen, its flags come after `:2,`
+fn is_unread(name: &str) -> bool {
+    return match name.rsplit_once(":2,") {
+        Some((_, flags)) => !flags.contains('S'),
+        None => true,
+    };
+}
+
+/// Messages in `new` and messages in `cur` without the seen flag
+fn count_unread(maildir: &Path) -> usize {
+    let count = |directory: &str, unread: fn(&str) -> bool| -> usize {
+        let Ok(entries) = fs::read_dir(maildir.join(directory))
+        else { return 0 };
+
+        return entries.filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_str().is_some_and(|name| !name.starts_with('.') && unread(name)))
+            .count();
+    };
+    return count("new", |_| true) + count("cur", is_unread);
+}
+
+/// Unread messages in local maildirs
+pub struct MailBlock {
+    urgent: Option<usize>,
+    show_zero: bool,
+    unread: Arc<Mutex<Option<usize>>>,
+    destruct: Arc<Notify>,
+    icons: IconSet,
+}
+
+impl Drop for MailBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl MailBlock {
+    fn watch(maildirs: &[PathBuf]) -> Option<Inotify> {
+        let inotify = match Inotify::new() {
+            Ok(inotify) => inotify,
+            Err(err) => {
+                warn!("Failed to set up inotify, mail is only counted every minute: {}", err);
+                return None;
+            }
+        };
+        let mask = inotify::IN_CREATE | inotify::IN_DELETE | inotify::IN_MOVED_FROM | inotify::IN_MOVED_TO;
+        for directory in maildirs.iter().flat_map(|maildir| [maildir.join("new"), maildir.join("cur")]) {
+            if let Err(err) = inotify.watch(&directory, mask) {
+                warn!("Failed to watch {}: {}", directory.display(), err);
+            }
+        }
+        return Some(inotify);
+    }
+
+    fn create_counter(maildirs: Vec<PathBuf>, unread: Arc<Mutex<Option<usize>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let mut inotify = Self::watch(&maildirs);
+            loop {
+                let current = maildirs.iter().map(|maildir| count_unread(maildir)).sum();
+                if *unread.lock().unwrap() != Some(current) {
+                    *unread.lock().unwrap() = Some(current);
+                    invalidate.notify_one();
+                }
+
+                let changed = async {
+                    match &mut inotify {
+                        Some(inotify) => {
+                            if let Err(err) = inotify.read().await {
+                                warn!("Failed to read inotify events: {}", err);
+                                std::future::pending::<()>().await;
+                            }
+                        }
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = changed => {}
+                    _ = sleep(RESCAN_INTERVAL) => {}
+                }
+            }
+        });
+    }
+
+    pub fn new(config: MailConfig, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let unread = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_counter(config.maildirs, unread.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            urgent: config.urgent,
+            show_zero: config.show_zero,
+            unread,
+            destruct,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for MailBlock {
+    fn name(&self) -> &str {
+        return "mail";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(unread) = *self.unread.lock().unwrap()
+        else { return Vec::new() };
+
+        if unread == 0 && !self.show_zero {
+            return Vec::new();
+        }
+        let mut item = json!({ "full_text": format!("{} {}", self.icons.get(Icon::Mail), unread) });
+        if self.urgent.is_some_and(|urgent| unread > urgent) {
+            item["urgent"] = json!(true);
+        }
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The counter invalidates on changes
+        return None;
+    }
+}
@@ -0,0 +1,205 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sysinfo::{System, SystemExt};
+
+use crate::{block::Block, byte_count::{ByteCount, ByteDelta}, color, template::Template};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Used memory percentage at which the block turns yellow
+    pub warning_percent: f64,
+    /// Used memory percentage at which the block turns red
+    pub critical_percent: f64,
+    /// Also show free swap, turn off on systems without any
+    pub show_swap: bool,
+    /// What to show, like `{used}/{total}`. Has `{available}`, `{used}`, `{free}`, `{total}`,
+    /// `{used_percent}`, `{swap_used}`, `{swap_free}` and `{swap_total}`, replaces `show_swap`.
+    /// `{used_delta}` is how much the used memory changed since the last update, like `+1.2MiB`.
+    pub format: Option<Template>,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        return Self {
+            warning_percent: 80.0,
+            critical_percent: 90.0,
+            show_swap: true,
+            format: None,
+        };
+    }
+}
+
+/// Memory that isn't available, as a percentage of the total
+fn used_percent(available: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    return total.saturating_sub(available) as f64 * 100.0 / total as f64;
+}
+
+/// One reading of the memory counters, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub total: u64,
+    pub available: u64,
+    pub free: u64,
+    pub total_swap: u64,
+    pub used_swap: u64,
+    pub free_swap: u64,
+}
+
+impl MemoryStats {
+    fn read(sys: &System) -> Self {
+        return Self {
+            total: sys.total_memory(),
+            available: sys.available_memory(),
+            free: sys.free_memory(),
+            total_swap: sys.total_swap(),
+            used_swap: sys.used_swap(),
+            free_swap: sys.free_swap(),
+        };
+    }
+
+    fn used(&self) -> u64 {
+        return self.total.saturating_sub(self.available);
+    }
+}
+
+/// The value of the `{name}` placeholder for `stats`, `previous` is the reading before it
+fn placeholder(stats: &MemoryStats, previous: Option<&MemoryStats>, name: &str) -> Option<String> {
+    let bytes = match name {
+        "available" => stats.available,
+        "used" => stats.used(),
+        "used_delta" => {
+            let delta = previous.map_or(0, |previous| stats.used() as i64 - previous.used() as i64);
+            return Some(format!("{:.1}", ByteDelta(delta)));
+        }
+        "free" => stats.free,
+        "total" => stats.total,
+        "used_percent" => return Some(format!("{:.0}%", used_percent(stats.available, stats.total))),
+        "swap_used" => stats.used_swap,
+        "swap_free" => stats.free_swap,
+        "swap_total" => stats.total_swap,
+        _ => return None,
+    };
+    return Some(format!("{:.2}", ByteCount::from(bytes)));
+}
+
+/// Available memory and free swap
+pub struct MemoryBlock {
+    sys: System,
+    warning_percent: f64,
+    critical_percent: f64,
+    format: Template,
+    previous: Option<MemoryStats>,
+}
+
+impl MemoryBlock {
+    pub fn new(config: MemoryConfig) -> Self {
+        let format = config.format.unwrap_or_else(|| {
+            let format = if config.show_swap { "M {available} ({used_percent}) S {swap_free}" } else { "M {available} ({used_percent})" };
+            let Ok(format) = format.parse();
+            format
+        });
+        return Self {
+            sys: System::new(),
+            warning_percent: config.warning_percent,
+            critical_percent: config.critical_percent,
+            format,
+            previous: None,
+        };
+    }
+
+    pub fn render_stats(&mut self, stats: MemoryStats) -> Vec<Value> {
+        let available = stats.available;
+        let total = stats.total;
+        let percent = used_percent(available, total);
+
+        let text = self.format.render(|name| placeholder(&stats, self.previous.as_ref(), name));
+        self.previous = Some(stats);
+
+        let tooltip = format!(
+            "Memory: {:.1} of {:.1} used ({:.0}%), {:.1} available\nSwap: {:.1} of {:.1} used ({:.0}%)",
+            ByteCount::from(total.saturating_sub(available)),
+            ByteCount::from(total),
+            percent,
+            ByteCount::from(available),
+            ByteCount::from(stats.used_swap),
+            ByteCount::from(stats.total_swap),
+            used_percent(stats.free_swap, stats.total_swap),
+        );
+
+        let mut item = json!({ "full_text": text, "tooltip": tooltip });
+        if percent >= self.critical_percent {
+            item["color"] = json!(color::CRITICAL);
+        } else if percent >= self.warning_percent {
+            item["color"] = json!(color::WARNING);
+        }
+        return vec![item];
+    }
+}
+
+#[async_trait]
+impl Block for MemoryBlock {
+    fn name(&self) -> &str {
+        return "memory";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        self.sys.refresh_memory();
+        return self.render_stats(MemoryStats::read(&self.sys));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1 << 30;
+    const MIB: u64 = 1 << 20;
+
+    fn stats(available: u64) -> MemoryStats {
+        return MemoryStats { total: 8 * GIB, available, free: GIB, total_swap: 0, used_swap: 0, free_swap: 0 };
+    }
+
+    fn render(block: &mut MemoryBlock, available: u64) -> Value {
+        return block.render_stats(stats(available)).remove(0)["full_text"].clone();
+    }
+
+    #[test]
+    fn used_delta_follows_the_used_memory() {
+        let format = "{used} {used_delta}".parse().unwrap();
+        let mut block = MemoryBlock::new(MemoryConfig { format: Some(format), ..MemoryConfig::default() });
+        // Nothing to compare the first reading to
+        assert_eq!(render(&mut block, 4 * GIB), "4.00GiB 0.0KiB");
+        assert_eq!(render(&mut block, 4 * GIB - 300 * MIB), "4.29GiB +300.0MiB");
+        assert_eq!(render(&mut block, 5 * GIB), "3.00GiB -1.3GiB");
+        assert_eq!(render(&mut block, 5 * GIB), "3.00GiB 0.0KiB");
+    }
+
+    #[test]
+    fn placeholders() {
+        let stats = MemoryStats { total: 16 * GIB, available: 4 * GIB, free: 2 * GIB, total_swap: 8 * GIB, used_swap: GIB, free_swap: 7 * GIB };
+        let value = |name| placeholder(&stats, None, name);
+        assert_eq!(value("used").as_deref(), Some("12.00GiB"));
+        assert_eq!(value("used_percent").as_deref(), Some("75%"));
+        assert_eq!(value("swap_free").as_deref(), Some("7.00GiB"));
+        assert_eq!(value("cached"), None);
+    }
+
+    #[test]
+    fn templates() {
+        let stats = MemoryStats { total: 16 * GIB, available: 6 * GIB, free: 512 * MIB, total_swap: 4 * GIB, used_swap: GIB, free_swap: 3 * GIB };
+        let text = |config: MemoryConfig| MemoryBlock::new(config).render_stats(stats).remove(0)["full_text"].clone();
+        let format = |format: &str| MemoryConfig { format: Some(format.parse().unwrap()), ..MemoryConfig::default() };
+
+        assert_eq!(text(MemoryConfig::default()), "M 6.00GiB (62%) S 3.00GiB");
+        assert_eq!(text(MemoryConfig { show_swap: false, ..MemoryConfig::default() }), "M 6.00GiB (62%)");
+        assert_eq!(text(format("{used}/{total}")), "10.00GiB/16.00GiB");
+        assert_eq!(text(format("RAM {free} swap {swap_used}/{swap_total}")), "RAM 512.00MiB swap 1.00GiB/4.00GiB");
+        // The format wins over show_swap
+        assert_eq!(text(MemoryConfig { show_swap: false, ..format("{swap_free}") }), "3.00GiB");
+    }
+}
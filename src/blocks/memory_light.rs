@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sysinfo::{System, SystemExt};
+
+use crate::{block::Block, color};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn color(&self) -> &'static str {
+        return match self {
+            Severity::Ok => color::GOOD,
+            Severity::Warning => color::WARNING,
+            Severity::Critical => color::CRITICAL,
+        };
+    }
+}
+
+/// Usage fractions (0.0 - 1.0) at which the light changes color
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct Thresholds {
+    pub warning: f64,
+    pub critical: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        return Self {
+            warning: 0.75,
+            critical: 0.90,
+        };
+    }
+}
+
+impl Thresholds {
+    fn classify(&self, used: u64, total: u64) -> Severity {
+        if total == 0 {
+            return Severity::Ok;
+        }
+        let usage = used as f64 / total as f64;
+        if usage >= self.critical {
+            return Severity::Critical;
+        }
+        if usage >= self.warning {
+            return Severity::Warning;
+        }
+        return Severity::Ok;
+    }
+
+    /// Overall memory health, whichever of RAM and swap is in worse shape.
+    /// Systems without swap only look at RAM.
+    pub fn severity(&self, used_memory: u64, total_memory: u64, used_swap: u64, total_swap: u64) -> Severity {
+        return self.classify(used_memory, total_memory)
+            .max(self.classify(used_swap, total_swap));
+    }
+}
+
+/// A single glanceable dot summarizing memory health
+pub struct MemoryLightBlock {
+    sys: System,
+    thresholds: Thresholds,
+}
+
+impl MemoryLightBlock {
+    pub fn new(thresholds: Thresholds) -> Self {
+        return Self {
+            sys: System::new(),
+            thresholds,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for MemoryLightBlock {
+    fn name(&self) -> &str {
+        return "memory_light";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        self.sys.refresh_memory();
+        let severity = self.thresholds.severity(
+            self.sys.used_memory(), self.sys.total_memory(),
+            self.sys.used_swap(), self.sys.total_swap());
+
+        return vec![json!({
+            "full_text": "\u{25cf}",
+            "color": severity.color(),
+        })];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn memory_alone_sets_the_light() {
+        let thresholds = Thresholds::default();
+        assert_eq!(thresholds.severity(4 * GIB, 16 * GIB, 0, 0), Severity::Ok);
+        assert_eq!(thresholds.severity(12 * GIB, 16 * GIB, 0, 0), Severity::Warning);
+        assert_eq!(thresholds.severity(15 * GIB, 16 * GIB, 0, 0), Severity::Critical);
+    }
+
+    #[test]
+    fn busy_swap_outweighs_idle_memory() {
+        let thresholds = Thresholds::default();
+        assert_eq!(thresholds.severity(2 * GIB, 16 * GIB, 7 * GIB, 8 * GIB), Severity::Warning);
+        assert_eq!(thresholds.severity(2 * GIB, 16 * GIB, 8 * GIB, 8 * GIB), Severity::Critical);
+        // And the other way around
+        assert_eq!(thresholds.severity(15 * GIB, 16 * GIB, 0, 8 * GIB), Severity::Critical);
+    }
+
+    #[test]
+    fn thresholds_are_inclusive() {
+        let thresholds = Thresholds { warning: 0.5, critical: 0.8 };
+        assert_eq!(thresholds.severity(49, 100, 0, 0), Severity::Ok);
+        assert_eq!(thresholds.severity(50, 100, 0, 0), Severity::Warning);
+        assert_eq!(thresholds.severity(80, 100, 0, 0), Severity::Critical);
+    }
+
+    #[test]
+    fn nothing_installed_is_ok() {
+        assert_eq!(Thresholds::default().severity(0, 0, 0, 0), Severity::Ok);
+    }
+}
@@ -0,0 +1,536 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, sync::Notify};
+
+use crate::{album_art, block::{self, Block, ClickEvent, Markup, MouseButton}, bus::Bus, color, icons::{Icon, IconSet}, log::warn, mpris::{Mpris, PlayerState}, template::Template, text};
+
+/// Splits `text` on a char boundary so that the first part holds `fraction` of its characters
+fn split_at_fraction(text: &str, fraction: f64) -> (&str, &str) {
+    let chars = text.chars().count();
+    let split = (chars as f64 * fraction.clamp(0.0, 1.0)).round() as usize;
+    let index = text.char_indices()
+        .nth(split)
+        .map(|(index, _)| index)
+        .unwrap_or(text.len());
+    return text.split_at(index);
+}
+
+/// The loop status a click switches to: None, Playlist, Track and back around
+fn next_loop_status(loop_status: &str) -> &'static str {
+    return match loop_status {
+        "None" => "Playlist",
+        "Playlist" => "Track",
+        _ => "None",
+    };
+}
+
+/// The value of the `{name}` placeholder for `state`, with `artist_separator` between artists
+fn placeholder(state: &PlayerState, name: &str, artist_separator: &str) -> Option<String> {
+    return match name {
+        "artist" => Some(state.artists.join(artist_separator)),
+        "title" => Some(state.title.clone()),
+        "album" => Some(state.album.clone()),
+        "track_number" => state.track_number.map(|number| number.to_string()),
+        _ => None,
+    };
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct MprisConfig {
+    /// Only follow the player `org.mpris.MediaPlayer2.<instance>`. Unset or `auto` follows
+    /// every player and shows the one playing, a player that isn't running just shows nothing.
+    pub instance: Option<String>,
+    /// Seconds to seek per scroll step
+    pub seek_step: u64,
+    /// Scroll the track name through a window this many characters wide while playing
+    pub marquee_width: Option<usize>,
+    /// Milliseconds between scrolling by one character
+    pub marquee_step: u64,
+    /// Cut the track off with an ellipsis past this many characters, ignored with a marquee
+    pub max_length: Option<usize>,
+    /// Run with `sh -c` when right clicking the track, instead of raising the player
+    pub right_click_command: Option<String>,
+    /// `artist - album - title` instead of `artist - title`
+    pub show_album: bool,
+    /// Between the artists of tracks with more than one
+    pub artist_separator: String,
+    /// Between the artist, album and title, ignored with a `format`
+    pub title_separator: String,
+    /// Prefix the track with its number like `[3] artist - title`
+    pub show_track_number: bool,
+    /// How to show the track, like `{artist} - {title} ({album})`. Has `{artist}`, `{title}`,
+    /// `{album}` and `{track_number}`, replaces `show_album` and `show_track_number`.
+    pub format: Option<Template>,
+    /// Show the player's own volume, scrolling over it changes the volume instead of seeking
+    pub show_volume: bool,
+    /// Percent per scroll step over the volume
+    pub volume_step: u32,
+    /// Color the track with the most common color of its album art, needs ImageMagick's
+    /// `convert` or `magick`
+    pub art_color: bool,
+    /// Also download art that isn't a local file for `art_color`
+    pub remote_art: bool,
+    /// Dim the track and time with the theme's muted color while paused
+    pub mute_paused: bool,
+}
+
+impl Default for MprisConfig {
+    fn default() -> Self {
+        return Self {
+            instance: None,
+            seek_step: 5,
+            marquee_width: None,
+            marquee_step: 500,
+            max_length: None,
+            right_click_command: None,
+            show_album: false,
+            artist_separator: " - ".to_string(),
+            title_separator: " - ".to_string(),
+            show_track_number: false,
+            format: None,
+            show_volume: false,
+            volume_step: 5,
+            art_color: false,
+            remote_art: false,
+            mute_paused: false,
+        };
+    }
+}
+
+struct Marquee {
+    width: usize,
+    step: Duration,
+    /// Track the offset belongs to, a new one starts from the beginning
+    track: String,
+    offset: usize,
+    last_step: Instant,
+}
+
+impl Marquee {
+    fn new(width: usize, step: Duration) -> Self {
+        return Self {
+            width,
+            step,
+            track: "".to_string(),
+            offset: 0,
+            last_step: Instant::now(),
+        };
+    }
+
+    fn scrolls(&self) -> bool {
+        return self.track.chars().count() > self.width;
+    }
+
+    /// The visible part of `track`, moved along by however many steps passed while playing
+    fn window(&mut self, track: &str, playing: bool) -> String {
+        if track != self.track {
+            self.track = track.to_string();
+            self.offset = 0;
+            self.last_step = Instant::now();
+        } else if !playing || self.step.is_zero() {
+            self.last_step = Instant::now();
+        } else {
+            // Counted from elapsed time so extra renders don't speed it up
+            let steps = (self.last_step.elapsed().as_millis() / self.step.as_millis()) as u32;
+            self.offset += steps as usize;
+            self.last_step += self.step * steps;
+        }
+        return text::marquee(track, self.width, self.offset);
+    }
+}
+
+/// Past the most art URLs we keep colors for, the cache starts over
+const ART_CACHE_SIZE: usize = 64;
+
+/// Album art colors by art URL, None while they're being worked out or when there's no
+/// usable art
+struct ArtColors {
+    remote: bool,
+    colors: Arc<Mutex<HashMap<String, Option<String>>>>,
+    invalidate: Arc<Notify>,
+}
+
+impl ArtColors {
+    /// The color for `art_url`, starting to work it out in the background the first time
+    fn get(&self, art_url: &str) -> Option<String> {
+        let mut colors = self.colors.lock().unwrap();
+        if let Some(color) = colors.get(art_url) {
+            return color.clone();
+        }
+        if colors.len() >= ART_CACHE_SIZE {
+            colors.clear();
+        }
+        colors.insert(art_url.to_string(), None);
+
+        let art_url = art_url.to_string();
+        let remote = self.remote;
+        let colors = self.colors.clone();
+        let invalidate = self.invalidate.clone();
+        tokio::spawn(async move {
+            let Some(color) = album_art::color(&art_url, remote).await
+            else { return };
+
+            colors.lock().unwrap().insert(art_url, Some(color));
+            invalidate.notify_one();
+        });
+        return None;
+    }
+}
+
+/// The `format` that `show_track_number`, `show_album` and `title_separator` stand for
+fn default_format(config: &MprisConfig) -> Template {
+    let mut format = "".to_string();
+    if config.show_track_number {
+        format += "[{track_number}] ";
+    }
+    format += "{artist}";
+    format += &config.title_separator;
+    if config.show_album {
+        format += "{album}";
+        format += &config.title_separator;
+    }
+    format += "{title}";
+    let Ok(format) = format.parse();
+    return format;
+}
+
+pub struct MprisBlock {
+    mpris: Mpris,
+    seek_step: Duration,
+    marquee: Option<Marquee>,
+    max_length: Option<usize>,
+    markup: Markup,
+    right_click_command: Option<String>,
+    format: Template,
+    artist_separator: String,
+    show_volume: bool,
+    volume_step: u32,
+    art_colors: Option<ArtColors>,
+    mute_paused: bool,
+    icons: IconSet,
+}
+
+impl MprisBlock {
+    pub fn new(config: MprisConfig, bus: Bus, invalidate: Arc<Notify>, markup: Markup, icons: IconSet) -> Self {
+        let format = config.format.clone().unwrap_or_else(|| default_format(&config));
+
+        let art_colors = config.art_color.then(|| ArtColors {
+            remote: config.remote_art,
+            colors: Arc::new(Mutex::new(HashMap::new())),
+            invalidate: invalidate.clone(),
+        });
+
+        return Self {
+            mpris: Mpris::new(bus, config.instance.as_deref().filter(|&instance| instance != "auto"), invalidate),
+            seek_step: Duration::from_secs(config.seek_step),
+            marquee: config.marquee_width
+                .map(|width| Marquee::new(width, Duration::from_millis(config.marquee_step))),
+            max_length: config.max_length,
+            markup,
+            right_click_command: config.right_click_command,
+            format,
+            artist_separator: config.artist_separator,
+            show_volume: config.show_volume,
+            volume_step: config.volume_step,
+            art_colors,
+            mute_paused: config.mute_paused,
+            icons,
+        };
+    }
+
+    /// The items for `state`, the player's state at the time of the render
+    pub fn render_state(&mut self, state: &PlayerState) -> Vec<Value> {
+        let current_track = if state.title.is_empty() {
+            "".to_string()
+        } else {
+            self.format.render(|name| placeholder(state, name, &self.artist_separator))
+        };
+        let playing = state.playing;
+        let progress = state.length
+            .map(|length| state.position().as_secs_f64() / length.as_secs_f64());
+        let time = match state.length {
+            Some(length) => format!("{} / {}", text::format_duration(state.position()), text::format_duration(length)),
+            None => text::format_duration(state.position()),
+        };
+        let current_track = match (&mut self.marquee, self.max_length) {
+            (Some(marquee), _) => marquee.window(&current_track, playing),
+            (None, Some(max_length)) => text::truncate(&current_track, max_length),
+            (None, None) => current_track,
+        };
+        if current_track.is_empty() {
+            return Vec::new();
+        }
+
+        // Titles come from the player and may contain anything
+        let escape = |text: &str| if self.markup == Markup::Pango { text::escape_markup(text) } else { text.to_string() };
+
+        // i3bar can't partially fill a block, so the title is split in two at the playback
+        // position with the played part getting a different background
+        let mut items = match progress {
+            Some(progress) => {
+                let (played, remaining) = split_at_fraction(&current_track, progress);
+                vec![
+                    json!({
+                        "full_text": escape(played),
+                        "background": color::ACCENT,
+                        "separator_block_width": 0,
+                    }),
+                    json!({
+                        "full_text": escape(remaining),
+                    }),
+                ]
+            }
+            None => vec![
+                json!({
+                    "full_text": escape(&current_track),
+                }),
+            ],
+        };
+
+        let track_items = items.len();
+        items.push(json!({
+            "full_text": time,
+        }));
+
+        if let Some(volume) = state.volume.filter(|_| self.show_volume) {
+            items.push(json!({
+                "full_text": format!("{} {:.0}%", self.icons.get(Icon::PlayerVolume), volume * 100.0),
+                "instance": "volume",
+            }));
+        }
+
+        // Players that can't be controlled don't get buttons that would do nothing
+        if state.can_control {
+            let mut previous = json!({
+                "full_text": self.icons.get(Icon::Previous),
+                "instance": "previous",
+            });
+            if !state.can_go_previous {
+                previous["color"] = json!(color::MUTED);
+            }
+            items.push(previous);
+
+            if playing && state.can_pause {
+                items.push(json!({
+                    "full_text": self.icons.get(Icon::Pause),
+                    "instance": "pause",
+                }));
+            } else if !playing && state.can_play {
+                items.push(json!({
+                    "full_text": self.icons.get(Icon::Play),
+                    "instance": "play",
+                }));
+            }
+
+            let mut next = json!({
+                "full_text": self.icons.get(Icon::Next),
+                "instance": "next",
+            });
+            if !state.can_go_next {
+                next["color"] = json!(color::MUTED);
+            }
+            items.push(next);
+
+            if let Some(shuffle) = state.shuffle {
+                let mut item = json!({
+                    "full_text": self.icons.get(Icon::Shuffle),
+                    "instance": "shuffle",
+                });
+                if !shuffle {
+                    item["color"] = json!(color::MUTED);
+                }
+                items.push(item);
+            }
+
+            if let Some(loop_status) = &state.loop_status {
+                let mut item = json!({
+                    "full_text": self.icons.get(if loop_status == "Track" { Icon::LoopTrack } else { Icon::Loop }),
+                    "instance": "loop",
+                });
+                if loop_status == "None" {
+                    item["color"] = json!(color::MUTED);
+                }
+                items.push(item);
+            }
+        }
+
+        // Without art the theme's foreground stays
+        let art_color = match (&self.art_colors, &state.art_url) {
+            (Some(art_colors), Some(art_url)) => art_colors.get(art_url),
+            _ => None,
+        };
+        if let Some(art_color) = art_color {
+            for item in items.iter_mut().take(track_items) {
+                item["color"] = json!(art_color);
+            }
+        }
+        if self.mute_paused && !playing {
+            // The time item comes right after the track
+            for item in items.iter_mut().take(track_items + 1) {
+                item["color"] = json!(color::MUTED);
+            }
+        }
+
+        block::join(&mut items);
+        return items;
+    }
+}
+
+#[async_trait]
+impl Block for MprisBlock {
+    fn name(&self) -> &str {
+        return "mpris";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let state = self.mpris.state();
+        if state.playing {
+            self.mpris.refresh_position();
+        }
+        return self.render_state(&state);
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // Only the position and marquee move on their own, everything else arrives as signals
+        if !self.mpris.state().playing {
+            return None;
+        }
+        return match &self.marquee {
+            Some(marquee) if marquee.scrolls() => Some(marquee.step.min(Duration::from_secs(1))),
+            _ => Some(Duration::from_secs(1)),
+        };
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        // The track and time items have no instance
+        if event.mouse_button() == MouseButton::Right && event.instance.is_none() {
+            match &self.right_click_command {
+                Some(command) => {
+                    if let Err(err) = Command::new("sh").args(["-c", command]).spawn() {
+                        warn!("Failed to run {:?}: {}", command, err);
+                    }
+                }
+                None => self.mpris.raise(),
+            }
+            return;
+        }
+        if event.mouse_button() != MouseButton::Left {
+            return;
+        }
+        match event.instance.as_deref() {
+            Some("play") => self.mpris.play(),
+            Some("pause") => self.mpris.pause(),
+            Some("previous") => self.mpris.previous(),
+            Some("next") => self.mpris.next(),
+            Some("shuffle") => {
+                if let Some(shuffle) = self.mpris.state().shuffle {
+                    self.mpris.set_shuffle(!shuffle);
+                }
+            }
+            Some("loop") => {
+                if let Some(loop_status) = self.mpris.state().loop_status {
+                    self.mpris.set_loop_status(next_loop_status(&loop_status));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_scroll(&mut self, event: &ClickEvent, steps: i32) {
+        if event.instance.as_deref() == Some("volume") {
+            if let Some(volume) = self.mpris.state().volume {
+                self.mpris.set_volume(volume + self.volume_step as f64 / 100.0 * steps as f64);
+            }
+            return;
+        }
+        let offset = self.seek_step.as_micros() as i64 * steps as i64;
+        self.mpris.seek(offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(config: &MprisConfig, artists: &[&str]) -> String {
+        let mut state = PlayerState::default();
+        state.title = "Song".to_string();
+        state.artists = artists.iter().map(|artist| artist.to_string()).collect();
+        state.album = "Record".to_string();
+        state.track_number = Some(7);
+        let format = config.format.clone().unwrap_or_else(|| default_format(config));
+        return format.render(|name| placeholder(&state, name, &config.artist_separator));
+    }
+
+    #[test]
+    fn one_artist() {
+        assert_eq!(track(&MprisConfig::default(), &["Solo"]), "Solo - Song");
+    }
+
+    #[test]
+    fn three_artists_keep_the_old_separator() {
+        assert_eq!(track(&MprisConfig::default(), &["A", "B", "C"]), "A - B - C - Song");
+    }
+
+    #[test]
+    fn separators_are_separate() {
+        let config = MprisConfig {
+            artist_separator: ", ".to_string(),
+            title_separator: " \u{2013} ".to_string(),
+            ..MprisConfig::default()
+        };
+        assert_eq!(track(&config, &["Solo"]), "Solo \u{2013} Song");
+        assert_eq!(track(&config, &["A", "B", "C"]), "A, B, C \u{2013} Song");
+    }
+
+    #[test]
+    fn album_and_track_number() {
+        let config = MprisConfig { show_album: true, show_track_number: true, ..MprisConfig::default() };
+        assert_eq!(track(&config, &["A", "B"]), "[7] A - B - Record - Song");
+    }
+
+    #[test]
+    fn format_uses_the_artist_separator() {
+        let config = MprisConfig {
+            artist_separator: " & ".to_string(),
+            format: Some("{title} by {artist}".parse().unwrap()),
+            ..MprisConfig::default()
+        };
+        assert_eq!(track(&config, &["A", "B", "C"]), "Song by A & B & C");
+    }
+
+    #[test]
+    fn marquee_starts_over_for_a_new_track() {
+        let mut marquee = Marquee::new(4, Duration::from_millis(100));
+        assert_eq!(marquee.window("First track", true), "Firs");
+        marquee.last_step -= Duration::from_millis(250);
+        assert_eq!(marquee.window("First track", true), "rst ");
+        assert_eq!(marquee.window("Second", true), "Seco");
+    }
+
+    #[test]
+    fn marquee_holds_still_while_paused() {
+        let mut marquee = Marquee::new(4, Duration::from_millis(100));
+        marquee.window("Paused track", false);
+        marquee.last_step -= Duration::from_secs(1);
+        assert_eq!(marquee.window("Paused track", false), "Paus");
+        assert!(marquee.scrolls());
+    }
+
+    #[test]
+    fn templates_over_the_player_state() {
+        let custom = |format: &str| MprisConfig {
+            format: Some(format.parse().unwrap()),
+            ..MprisConfig::default()
+        };
+        assert_eq!(track(&custom("{artist} \u{2014} {title} ({album})"), &["Solo"]), "Solo \u{2014} Song (Record)");
+        assert_eq!(track(&custom("{track_number}. {title}"), &[]), "7. Song");
+        // No artist, no separator in front of the title
+        assert_eq!(track(&custom("{artist} - {title}"), &[]), "Song");
+        assert_eq!(track(&custom("{title} {genre}"), &["Solo"]), "Song");
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fs, path::Path, time::{Duration, Instant}};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::{Align, Block}, byte_count::ByteRate, sparkline::History, sysfs};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+/// Widest rates, each unit goes up to 1023.99 before switching to the next
+const MIN_WIDTH: &str = "\u{2193} 1023.99MiB/s \u{2191} 1023.99MiB/s";
+const MIN_WIDTH_BITS: &str = "\u{2193} 999.99Mbit/s \u{2191} 999.99Mbit/s";
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Interface name or `auto` for the first non-loopback interface that is up
+    pub interface: String,
+    /// Seconds of combined traffic to show as a sparkline behind the rates, 0 shows none
+    pub history: usize,
+    /// Show rates in bits like `9.60Mbit/s` instead of bytes
+    pub bits: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        return Self {
+            interface: "auto".to_string(),
+            history: 0,
+            bits: false,
+        };
+    }
+}
+
+struct Snapshot {
+    interface: String,
+    rx_bytes: u64,
+    tx_bytes: u64,
+    taken: Instant,
+}
+
+/// Receive and transmit rates between two snapshots, zero when they're of different interfaces
+fn rates(previous: Option<&Snapshot>, current: &Snapshot) -> (ByteRate, ByteRate) {
+    return match previous {
+        Some(previous) if previous.interface == current.interface => {
+            let elapsed = current.taken - previous.taken;
+            // Counters reset when the interface goes down and back up
+            (
+                ByteRate::new(current.rx_bytes.saturating_sub(previous.rx_bytes), elapsed),
+                ByteRate::new(current.tx_bytes.saturating_sub(previous.tx_bytes), elapsed),
+            )
+        }
+        _ => (ByteRate::new(0, Duration::ZERO), ByteRate::new(0, Duration::ZERO)),
+    };
+}
+
+fn auto_interface() -> Option<String> {
+    let mut interfaces: Vec<String> = fs::read_dir(SYS_CLASS_NET).ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name != "lo")
+        .collect();
+    interfaces.sort();
+    return interfaces.into_iter()
+        .find(|name| sysfs::read_string(Path::new(SYS_CLASS_NET).join(name).join("operstate")).as_deref() == Some("up"));
+}
+
+pub struct NetworkBlock {
+    interface: String,
+    previous: Option<Snapshot>,
+    history: History,
+    bits: bool,
+}
+
+impl NetworkBlock {
+    pub fn new(config: NetworkConfig) -> Self {
+        return Self {
+            interface: config.interface,
+            previous: None,
+            history: History::new(config.history),
+            bits: config.bits,
+        };
+    }
+
+    fn snapshot(&self) -> Option<Snapshot> {
+        let interface = if self.interface == "auto" { auto_interface()? } else { self.interface.clone() };
+        let statistics = Path::new(SYS_CLASS_NET).join(&interface).join("statistics");
+        return Some(Snapshot {
+            rx_bytes: sysfs::read_value(statistics.join("rx_bytes"))?,
+            tx_bytes: sysfs::read_value(statistics.join("tx_bytes"))?,
+            interface,
+            taken: Instant::now(),
+        });
+    }
+}
+
+#[async_trait]
+impl Block for NetworkBlock {
+    fn name(&self) -> &str {
+        return "network";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(current) = self.snapshot()
+        else {
+            self.previous = None;
+            return vec![json!({ "full_text": "NET ERROR" })];
+        };
+
+        let (rx, tx) = rates(self.previous.as_ref(), &current);
+        self.previous = Some(current);
+        self.history.push((rx.bytes_per_second() + tx.bytes_per_second()) as f64);
+
+        let (rx, tx, min_width) = if self.bits { (rx.bits(), tx.bits(), MIN_WIDTH_BITS) } else { (rx, tx, MIN_WIDTH) };
+        let mut full_text = format!("\u{2193} {:.2} \u{2191} {:.2}", rx, tx);
+        // Scaled to the busiest second shown, there's no fixed maximum for traffic
+        let trend = self.history.sparkline(self.history.max());
+        if !trend.is_empty() {
+            full_text += &format!(" {}", trend);
+        }
+        return vec![json!({
+            "full_text": full_text,
+            "min_width": min_width,
+            "align": Align::Right.as_str(),
+        })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return Some(Duration::from_secs(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(interface: &str, rx_bytes: u64, tx_bytes: u64, taken: Instant) -> Snapshot {
+        return Snapshot { interface: interface.to_string(), rx_bytes, tx_bytes, taken };
+    }
+
+    fn per_second((rx, tx): (ByteRate, ByteRate)) -> (u64, u64) {
+        return (rx.bytes_per_second(), tx.bytes_per_second());
+    }
+
+    #[test]
+    fn rate_over_the_time_between_snapshots() {
+        let start = Instant::now();
+        let previous = snapshot("eth0", 1_000_000, 50_000, start);
+        let current = snapshot("eth0", 3_500_000, 80_000, start + Duration::from_millis(2500));
+        assert_eq!(per_second(rates(Some(&previous), &current)), (1_000_000, 12_000));
+
+        let (rx, tx) = rates(Some(&previous), &current);
+        assert_eq!(format!("\u{2193} {:.2} \u{2191} {:.2}", rx, tx), "\u{2193} 976.56KiB/s \u{2191} 11.72KiB/s");
+    }
+
+    #[test]
+    fn first_snapshot_has_no_rate() {
+        assert_eq!(per_second(rates(None, &snapshot("eth0", 10, 10, Instant::now()))), (0, 0));
+    }
+
+    #[test]
+    fn reset_counters_are_not_negative() {
+        let start = Instant::now();
+        let previous = snapshot("wlan0", 9_000, 9_000, start);
+        let current = snapshot("wlan0", 100, 12_000, start + Duration::from_secs(1));
+        assert_eq!(per_second(rates(Some(&previous), &current)), (0, 3_000));
+    }
+
+    #[test]
+    fn switched_interface_starts_over() {
+        let start = Instant::now();
+        let previous = snapshot("eth0", 0, 0, start);
+        let current = snapshot("wlan0", 5_000, 5_000, start + Duration::from_secs(1));
+        assert_eq!(per_second(rates(Some(&previous), &current)), (0, 0));
+    }
+}
@@ -0,0 +1,116 @@
+use std::{io, process::Stdio, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::{io::{AsyncBufReadExt, BufReader}, process::Command, sync::Notify, time::{sleep, Instant}};
+
+use crate::{block::Block, blocks::command::parse_output, log::{info, warn}};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A process that ran at least this long wasn't crashing on startup, restart it quickly
+const STABLE_RUNTIME: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct PersistentCommandConfig {
+    /// Run with `sh -c`, every line it prints replaces the block's text
+    pub command: String,
+}
+
+/// Feeds lines from `command` into `output` until it exits or closes stdout
+async fn follow(command: &str, output: &Mutex<Option<Value>>, invalidate: &Notify) -> io::Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdout = child.stdout.take()
+        .ok_or_else(|| io::Error::other("no stdout"))?;
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        *output.lock().unwrap() = Some(parse_output(&line));
+        invalidate.notify_one();
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(io::Error::other(status.to_string()));
+    }
+    return Ok(());
+}
+
+/// Latest line printed by a long-running command, restarted with backoff when it exits
+pub struct PersistentCommandBlock {
+    output: Arc<Mutex<Option<Value>>>,
+    destruct: Arc<Notify>,
+}
+
+impl Drop for PersistentCommandBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl PersistentCommandBlock {
+    fn create_runner(command: String, output: Arc<Mutex<Option<Value>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let mut backoff = MIN_BACKOFF;
+            loop {
+                let started = Instant::now();
+                // Dropping `follow` when destructed kills the child
+                let result = tokio::select! {
+                    result = follow(&command, &output, &invalidate) => result,
+                    _ = destruct.notified() => return,
+                };
+                match result {
+                    Ok(()) => info!("Command {:?} exited", command),
+                    Err(err) => warn!("Command {:?} failed: {}", command, err),
+                }
+
+                if started.elapsed() >= STABLE_RUNTIME {
+                    backoff = MIN_BACKOFF;
+                }
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(backoff) => {}
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+    }
+
+    pub fn new(config: PersistentCommandConfig, invalidate: Arc<Notify>) -> Self {
+        let output = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_runner(config.command, output.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            output,
+            destruct,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for PersistentCommandBlock {
+    fn name(&self) -> &str {
+        return "persistent_command";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        return self.output.lock().unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // Every line invalidates
+        return None;
+    }
+}
@@ -0,0 +1,125 @@
+use std::{io, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{net::{lookup_host, TcpStream}, sync::Notify, time::{sleep, timeout}};
+
+use crate::{block::Block, color, icons::{Icon, IconSet}};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct PingConfig {
+    pub host: String,
+    /// Latency is measured as the time a TCP connection to this port takes, which unlike
+    /// ICMP doesn't need privileges
+    pub port: u16,
+    /// Seconds between measurements
+    pub interval: u64,
+    /// Milliseconds after which the host counts as unreachable
+    pub timeout: u64,
+    /// Milliseconds above which the block turns yellow
+    pub warning: u64,
+}
+
+impl Default for PingConfig {
+    fn default() -> Self {
+        return Self {
+            host: "1.1.1.1".to_string(),
+            port: 443,
+            interval: 10,
+            timeout: 2000,
+            warning: 100,
+        };
+    }
+}
+
+/// Time it takes to connect to `host`, None when it can't be resolved or reached in time
+async fn measure(host: &str, port: u16, limit: Duration) -> Option<Duration> {
+    // Resolved first so DNS doesn't count towards the latency
+    let address = timeout(limit, lookup_host((host, port))).await.ok()?.ok()?.next()?;
+    let start = Instant::now();
+    return match timeout(limit, TcpStream::connect(address)).await.ok()? {
+        // A refusal made the round trip just as well
+        Err(err) if err.kind() != io::ErrorKind::ConnectionRefused => None,
+        _ => Some(start.elapsed()),
+    };
+}
+
+/// Round trip time to a host
+pub struct PingBlock {
+    warning: Duration,
+    /// None until the first measurement finished, then None inside when unreachable
+    latency: Arc<Mutex<Option<Option<Duration>>>>,
+    destruct: Arc<Notify>,
+    icons: IconSet,
+}
+
+impl Drop for PingBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl PingBlock {
+    fn create_pinger(config: PingConfig, latency: Arc<Mutex<Option<Option<Duration>>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let limit = Duration::from_millis(config.timeout);
+            loop {
+                let current = tokio::select! {
+                    _ = destruct.notified() => return,
+                    current = measure(&config.host, config.port, limit) => current,
+                };
+                *latency.lock().unwrap() = Some(current);
+                invalidate.notify_one();
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(Duration::from_secs(config.interval)) => {}
+                }
+            }
+        });
+    }
+
+    pub fn new(config: PingConfig, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let latency = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+        let warning = Duration::from_millis(config.warning);
+
+        Self::create_pinger(config, latency.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            warning,
+            latency,
+            destruct,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for PingBlock {
+    fn name(&self) -> &str {
+        return "ping";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let item = match *self.latency.lock().unwrap() {
+            None => return Vec::new(),
+            Some(Some(latency)) => {
+                let mut item = json!({ "full_text": format!("{} {}ms", self.icons.get(Icon::Latency), latency.as_millis()) });
+                if latency > self.warning {
+                    item["color"] = json!(color::WARNING);
+                }
+                item
+            }
+            Some(None) => json!({ "full_text": self.icons.get(Icon::Unreachable), "color": color::CRITICAL }),
+        };
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The pinger invalidates after every measurement
+        return None;
+    }
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::{self, Block}, color, sysfs};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Resource {
+    Cpu,
+    Memory,
+    Io,
+}
+
+impl Resource {
+    fn path(&self) -> &'static str {
+        return match self {
+            Resource::Cpu => "/proc/pressure/cpu",
+            Resource::Memory => "/proc/pressure/memory",
+            Resource::Io => "/proc/pressure/io",
+        };
+    }
+
+    fn label(&self) -> &'static str {
+        return match self {
+            Resource::Cpu => "cpu",
+            Resource::Memory => "mem",
+            Resource::Io => "io",
+        };
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct PressureConfig {
+    /// Which of `cpu`, `memory` and `io` to show, in this order
+    pub resources: Vec<Resource>,
+    /// Percent of the last 10 seconds some task was stalled
+    pub warning: f64,
+    pub critical: f64,
+}
+
+impl Default for PressureConfig {
+    fn default() -> Self {
+        return Self {
+            resources: vec![Resource::Memory],
+            warning: 10.0,
+            critical: 30.0,
+        };
+    }
+}
+
+/// The `avg10` of the `some` line in a `/proc/pressure` file like
+/// `some avg10=4.20 avg60=1.03 avg300=0.25 total=123456`
+fn parse_some_avg10(pressure: &str) -> Option<f64> {
+    let line = pressure.lines().find(|line| line.split_whitespace().next() == Some("some"))?;
+    return line.split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse().ok();
+}
+
+/// Pressure stall information, how much of the time tasks waited on the CPU, memory or IO
+pub struct PressureBlock {
+    config: PressureConfig,
+}
+
+impl PressureBlock {
+    pub fn new(config: PressureConfig) -> Self {
+        return Self { config };
+    }
+}
+
+#[async_trait]
+impl Block for PressureBlock {
+    fn name(&self) -> &str {
+        return "pressure";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        // Kernels without PSI have no /proc/pressure, the resource is left out
+        let mut items: Vec<Value> = self.config.resources.iter()
+            .filter_map(|resource| Some((resource, parse_some_avg10(&sysfs::read_string(resource.path())?)?)))
+            .enumerate()
+            .map(|(index, (resource, stalled))| {
+                // Joined up the items read `PSI cpu 4% mem 12%`
+                let prefix = if index == 0 { "PSI " } else { "" };
+                let mut item = json!({
+                    "full_text": format!("{}{} {:.0}%", prefix, resource.label(), stalled),
+                    "instance": resource.label(),
+                });
+                if stalled >= self.config.critical {
+                    item["color"] = json!(color::CRITICAL);
+                } else if stalled >= self.config.warning {
+                    item["color"] = json!(color::WARNING);
+                }
+                item
+            })
+            .collect();
+        block::join(&mut items);
+        return items;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn some_line_of_a_memory_file() {
+        let memory = "some avg10=4.20 avg60=1.03 avg300=0.25 total=123456\nfull avg10=1.50 avg60=0.40 avg300=0.08 total=45678\n";
+        assert_eq!(parse_some_avg10(memory), Some(4.2));
+    }
+
+    #[test]
+    fn full_line_is_not_used() {
+        // The full line comes second, going by its name rather than its position
+        let reordered = "full avg10=9.99 avg60=0.00 avg300=0.00 total=0\nsome avg10=0.00 avg60=0.00 avg300=0.00 total=0\n";
+        assert_eq!(parse_some_avg10(reordered), Some(0.0));
+        assert_eq!(parse_some_avg10("full avg10=9.99 avg60=0.00 avg300=0.00 total=0\n"), None);
+    }
+
+    #[test]
+    fn garbled_files() {
+        assert_eq!(parse_some_avg10(""), None);
+        assert_eq!(parse_some_avg10("some avg60=1.03 avg300=0.25 total=1\n"), None);
+        assert_eq!(parse_some_avg10("some avg10=high\n"), None);
+        assert_eq!(parse_some_avg10("something avg10=1.00\n"), None);
+    }
+}
@@ -0,0 +1,179 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use crate::{block::{Block, PollWait, Poller}, color, http};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct PriceConfig {
+    /// Shown in front of the price and put in place of `{ticker}` in `url`
+    pub ticker: String,
+    /// Endpoint answering with JSON that has the price in it
+    pub url: String,
+    /// JSON pointer to the price in the response, a number or a string with one
+    pub pointer: String,
+    /// Put in front of the number, like a currency sign
+    pub prefix: String,
+    /// Digits after the decimal point
+    pub decimals: usize,
+    /// Seconds between fetches
+    pub interval: u64,
+    /// Seconds before a fetch gives up
+    pub timeout: u64,
+}
+
+impl Default for PriceConfig {
+    fn default() -> Self {
+        return Self {
+            ticker: "BTC".to_string(),
+            url: "https://api.coinbase.com/v2/prices/{ticker}-USD/spot".to_string(),
+            pointer: "/data/amount".to_string(),
+            prefix: "$".to_string(),
+            decimals: 0,
+            interval: 300,
+            timeout: 10,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quote {
+    price: f64,
+    /// Price of the fetch before, None after the first one
+    previous: Option<f64>,
+    /// The last fetch failed and this is what we had before
+    stale: bool,
+}
+
+/// The number at `pointer` in the JSON `response`
+fn parse_price(response: &str, pointer: &str) -> Option<f64> {
+    let response: Value = serde_json::from_str(response).ok()?;
+    let price = response.pointer(pointer)?;
+    return price.as_f64()
+        .or_else(|| price.as_str()?.trim().parse().ok());
+}
+
+/// `price` with `decimals` digits and a comma between every three digits of the whole part
+fn format_price(price: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, price.abs());
+    let (whole, fraction) = match formatted.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+
+    let mut text = String::new();
+    if price < 0.0 {
+        text.push('-');
+    }
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        text.push('.');
+        text.push_str(fraction);
+    }
+    return text;
+}
+
+/// Price of one ticker from an HTTP API, fetched in the background. Add one block per ticker.
+pub struct PriceBlock {
+    ticker: String,
+    prefix: String,
+    decimals: usize,
+    quote: Arc<Mutex<Option<Quote>>>,
+    poller: Poller,
+}
+
+impl PriceBlock {
+    fn create_fetcher(config: &PriceConfig, quote: Arc<Mutex<Option<Quote>>>, wait: PollWait, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        let url = config.url.replace("{ticker}", &config.ticker);
+        let pointer = config.pointer.clone();
+        let interval = Duration::from_secs(config.interval);
+        let timeout = config.timeout;
+        return tokio::spawn(async move {
+            loop {
+                let price = tokio::select! {
+                    _ = wait.stopped() => return,
+                    response = http::get(&url, timeout) => response.and_then(|response| parse_price(&response, &pointer)),
+                };
+
+                let last = *quote.lock().unwrap();
+                let current = match (price, last) {
+                    (Some(price), last) => Some(Quote { price, previous: last.map(|last| last.price), stale: false }),
+                    // Errors keep showing what we had
+                    (None, Some(last)) => Some(Quote { stale: true, ..last }),
+                    (None, None) => None,
+                };
+                if last != current {
+                    *quote.lock().unwrap() = current;
+                    invalidate.notify_one();
+                }
+
+                if !wait.sleep(interval).await {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn new(config: PriceConfig, invalidate: Arc<Notify>) -> Self {
+        let quote = Arc::new(Mutex::new(None));
+        let (poller, wait) = Poller::new();
+
+        Self::create_fetcher(&config, quote.clone(), wait, invalidate);
+
+        return Self {
+            ticker: config.ticker,
+            prefix: config.prefix,
+            decimals: config.decimals,
+            quote,
+            poller,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for PriceBlock {
+    fn name(&self) -> &str {
+        return "price";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(quote) = *self.quote.lock().unwrap()
+        else { return Vec::new() };
+
+        let mut text = format!("{} {}{}", self.ticker, self.prefix, format_price(quote.price, self.decimals));
+        let (arrow, color) = match quote.previous {
+            Some(previous) if quote.price > previous => (Some("\u{25b2}"), Some(color::GOOD)),
+            Some(previous) if quote.price < previous => (Some("\u{25bc}"), Some(color::CRITICAL)),
+            _ => (None, None),
+        };
+        if let Some(arrow) = arrow {
+            text += &format!(" {}", arrow);
+        }
+
+        let mut item = json!({ "full_text": text });
+        if quote.stale {
+            item["color"] = json!(color::MUTED);
+        } else if let Some(color) = color {
+            item["color"] = json!(color);
+        }
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The fetcher invalidates on changes
+        return None;
+    }
+
+    fn refresh(&mut self) {
+        self.poller.refresh();
+    }
+}
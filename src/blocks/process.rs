@@ -0,0 +1,154 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sysinfo::{ProcessExt, ProcessRefreshKind, System, SystemExt};
+
+use crate::{block::{Block, ClickEvent, MouseButton}, icons::{Icon, IconSet}};
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessMode {
+    /// Number of running processes
+    #[default]
+    Count,
+    /// Process using the most CPU, in percent of one core like `top`
+    TopCpu,
+    /// Process using the most memory, in percent of the total
+    TopMemory,
+}
+
+impl ProcessMode {
+    /// The mode a left click switches to
+    fn next(self) -> Self {
+        return match self {
+            ProcessMode::Count => ProcessMode::TopCpu,
+            ProcessMode::TopCpu => ProcessMode::TopMemory,
+            ProcessMode::TopMemory => ProcessMode::Count,
+        };
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ProcessConfig {
+    pub mode: ProcessMode,
+    /// Seconds between updates, listing every process is expensive
+    pub interval: u64,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        return Self {
+            mode: ProcessMode::default(),
+            interval: 10,
+        };
+    }
+}
+
+/// The name with the highest total `usage`. Processes of the same name are added up so a
+/// program split over many processes, like a browser, counts as one.
+fn top_process<'a>(processes: impl IntoIterator<Item = (&'a str, f64)>) -> Option<(&'a str, f64)> {
+    let mut totals: HashMap<&str, f64> = HashMap::new();
+    for (name, usage) in processes {
+        *totals.entry(name).or_default() += usage;
+    }
+    return totals.into_iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+}
+
+/// Process count or the process using the most CPU or memory, left click cycles between them
+pub struct ProcessBlock {
+    sys: System,
+    mode: ProcessMode,
+    interval: Duration,
+    icons: IconSet,
+}
+
+impl ProcessBlock {
+    pub fn new(config: ProcessConfig, icons: IconSet) -> Self {
+        return Self {
+            sys: System::new(),
+            mode: config.mode,
+            interval: Duration::from_secs(config.interval),
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for ProcessBlock {
+    fn name(&self) -> &str {
+        return "process";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        // CPU usage is measured between two refreshes, it reads 0 on the first one
+        self.sys.refresh_processes_specifics(ProcessRefreshKind::new().with_cpu());
+        if self.mode == ProcessMode::TopMemory {
+            self.sys.refresh_memory();
+        }
+        let processes = self.sys.processes().values();
+
+        let text = match self.mode {
+            ProcessMode::Count => processes.count().to_string(),
+            ProcessMode::TopCpu => {
+                let top = top_process(processes.map(|process| (process.name(), process.cpu_usage() as f64)));
+                let Some((name, usage)) = top
+                else { return Vec::new() };
+
+                format!("{} {:.0}%", name, usage)
+            }
+            ProcessMode::TopMemory => {
+                let total = self.sys.total_memory().max(1) as f64;
+                let top = top_process(processes.map(|process| (process.name(), process.memory() as f64)));
+                let Some((name, memory)) = top
+                else { return Vec::new() };
+
+                format!("{} {:.0}%", name, memory / total * 100.0)
+            }
+        };
+        return vec![json!({ "full_text": format!("{} {}", self.icons.get(Icon::Process), text) })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return Some(self.interval);
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        if event.mouse_button() == MouseButton::Left {
+            self.mode = self.mode.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busiest_process_wins() {
+        let processes = [("sshd", 0.1), ("cargo", 87.5), ("i3", 0.4), ("rustc", 64.0)];
+        assert_eq!(top_process(processes), Some(("cargo", 87.5)));
+    }
+
+    #[test]
+    fn processes_of_one_name_add_up() {
+        // Four browser content processes beat the one compiler
+        let processes = [("firefox", 12.0), ("rustc", 40.0), ("firefox", 15.0), ("firefox", 9.5), ("firefox", 6.0)];
+        assert_eq!(top_process(processes), Some(("firefox", 42.5)));
+    }
+
+    #[test]
+    fn no_processes() {
+        assert_eq!(top_process([]), None);
+    }
+
+    #[test]
+    fn clicks_cycle_through_the_modes() {
+        assert_eq!(ProcessMode::Count.next(), ProcessMode::TopCpu);
+        assert_eq!(ProcessMode::TopCpu.next(), ProcessMode::TopMemory);
+        assert_eq!(ProcessMode::TopMemory.next(), ProcessMode::Count);
+    }
+}
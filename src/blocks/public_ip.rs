@@ -0,0 +1,99 @@
+use std::{net::IpAddr, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use crate::{block::{Block, PollWait, Poller}, color, http};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct PublicIpConfig {
+    /// Responds with nothing but the address
+    pub url: String,
+    /// Seconds between lookups
+    pub interval: u64,
+    /// Seconds before a lookup gives up
+    pub timeout: u64,
+}
+
+impl Default for PublicIpConfig {
+    fn default() -> Self {
+        return Self {
+            url: "https://api.ipify.org".to_string(),
+            interval: 600,
+            timeout: 10,
+        };
+    }
+}
+
+/// Asks `url` for our address, None when offline or the answer isn't an address
+async fn lookup(url: &str, timeout: u64) -> Option<IpAddr> {
+    return http::get(url, timeout).await?.trim().parse().ok();
+}
+
+/// Our address as seen from the internet, looked up in the background
+pub struct PublicIpBlock {
+    /// None until the first lookup finished, then None inside when offline
+    address: Arc<Mutex<Option<Option<IpAddr>>>>,
+    poller: Poller,
+}
+
+impl PublicIpBlock {
+    fn create_updater(config: PublicIpConfig, address: Arc<Mutex<Option<Option<IpAddr>>>>, wait: PollWait, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            loop {
+                let current = tokio::select! {
+                    _ = wait.stopped() => return,
+                    current = lookup(&config.url, config.timeout) => current,
+                };
+                if *address.lock().unwrap() != Some(current) {
+                    *address.lock().unwrap() = Some(current);
+                    invalidate.notify_one();
+                }
+
+                if !wait.sleep(Duration::from_secs(config.interval)).await {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn new(config: PublicIpConfig, invalidate: Arc<Notify>) -> Self {
+        let address = Arc::new(Mutex::new(None));
+        let (poller, wait) = Poller::new();
+
+        Self::create_updater(config, address.clone(), wait, invalidate);
+
+        return Self {
+            address,
+            poller,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for PublicIpBlock {
+    fn name(&self) -> &str {
+        return "public_ip";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let item = match *self.address.lock().unwrap() {
+            None => return Vec::new(),
+            Some(Some(address)) => json!({ "full_text": format!("IP {}", address) }),
+            Some(None) => json!({ "full_text": "offline", "color": color::MUTED }),
+        };
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The updater invalidates when the address changes
+        return None;
+    }
+
+    fn refresh(&mut self) {
+        self.poller.refresh();
+    }
+}
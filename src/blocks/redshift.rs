@@ -0,0 +1,235 @@
+use std::{process::Stdio, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sysinfo::{PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt};
+use tokio::{process::Command, sync::Notify, time::timeout};
+
+use crate::{block::{Block, ClickEvent, MouseButton, PollWait, Poller}, color, icons::{Icon, IconSet}, log::{debug, warn}};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct RedshiftConfig {
+    /// The first of these that is running is followed, both toggle on SIGUSR1
+    pub programs: Vec<String>,
+    /// Seconds between checks, neither program tells when it changes
+    pub interval: u64,
+    /// Seconds before `<program> -p` gives up, working out the location may take a while
+    pub timeout: u64,
+}
+
+impl Default for RedshiftConfig {
+    fn default() -> Self {
+        return Self {
+            programs: vec!["redshift".to_string(), "gammastep".to_string()],
+            interval: 60,
+            timeout: 10,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RedshiftState {
+    program: String,
+    pid: u32,
+    /// More night than day, None when `-p` didn't say
+    night: Option<bool>,
+    /// Kelvin
+    temperature: Option<u32>,
+}
+
+/// Night and temperature out of what `redshift -p` or `gammastep -p` print, like
+/// `Period: Transition (42.00% day)` and `Color temperature: 4500K`
+fn parse_status(status: &str) -> (Option<bool>, Option<u32>) {
+    let mut night = None;
+    let mut temperature = None;
+    for line in status.lines() {
+        let Some((key, value)) = line.split_once(':')
+        else { continue };
+
+        let value = value.trim();
+        match key.trim() {
+            "Period" if value == "Night" => night = Some(true),
+            "Period" if value == "Daytime" => night = Some(false),
+            "Period" => {
+                // Transitions tell how far towards the day they are
+                let day = value.strip_prefix("Transition (")
+                    .and_then(|rest| rest.split_once('%'))
+                    .and_then(|(percent, _)| percent.trim().parse::<f64>().ok());
+                night = day.map(|day| day < 50.0);
+            }
+            "Color temperature" => temperature = value.trim_end_matches('K').parse().ok(),
+            _ => {}
+        }
+    }
+    return (night, temperature);
+}
+
+/// What the running program would set now, it answers the same whether or not it's toggled off
+async fn query(program: &str, limit: Duration) -> (Option<bool>, Option<u32>) {
+    let child = Command::new(program)
+        .arg("-p")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            debug!("Failed to run {} -p: {}", program, err);
+            return (None, None);
+        }
+    };
+    return match timeout(limit, child.wait_with_output()).await {
+        Ok(Ok(output)) => parse_status(&String::from_utf8_lossy(&output.stdout)),
+        _ => (None, None),
+    };
+}
+
+/// Whether redshift or gammastep is adjusting the color temperature, left click toggles it.
+/// Neither program tells whether a running instance is toggled off, they have no DBus
+/// interface and `-p` only works out what would be set now. So the off state is what our
+/// clicks did, toggling it some other way like `pkill -USR1 gammastep` isn't noticed.
+pub struct RedshiftBlock {
+    /// None while neither program is running
+    state: Arc<Mutex<Option<RedshiftState>>>,
+    /// Process we toggled off, the programs can't be asked so this is our own bookkeeping.
+    /// A restarted program has a new pid and starts out on again.
+    paused: Option<u32>,
+    poller: Poller,
+    icons: IconSet,
+}
+
+impl RedshiftBlock {
+    fn create_watcher(config: RedshiftConfig, state: Arc<Mutex<Option<RedshiftState>>>, wait: PollWait, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let interval = Duration::from_secs(config.interval);
+            let limit = Duration::from_secs(config.timeout);
+            let mut sys = System::new();
+            loop {
+                sys.refresh_processes_specifics(ProcessRefreshKind::new());
+                let running = config.programs.iter().find_map(|program| {
+                    let pid = sys.processes_by_exact_name(program).next()?.pid().as_u32();
+                    Some((program.clone(), pid))
+                });
+
+                let current = match running {
+                    Some((program, pid)) => {
+                        let (night, temperature) = tokio::select! {
+                            _ = wait.stopped() => return,
+                            status = query(&program, limit) => status,
+                        };
+                        Some(RedshiftState { program, pid, night, temperature })
+                    }
+                    None => None,
+                };
+                if *state.lock().unwrap() != current {
+                    *state.lock().unwrap() = current;
+                    invalidate.notify_one();
+                }
+
+                if !wait.sleep(interval).await {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn new(config: RedshiftConfig, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let state = Arc::new(Mutex::new(None));
+        let (poller, wait) = Poller::new();
+
+        Self::create_watcher(config, state.clone(), wait, invalidate);
+
+        return Self {
+            state,
+            paused: None,
+            poller,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for RedshiftBlock {
+    fn name(&self) -> &str {
+        return "redshift";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(state) = self.state.lock().unwrap().clone()
+        else { return Vec::new() };
+
+        let icon = self.icons.get(if state.night == Some(true) { Icon::Night } else { Icon::Day });
+        if self.paused == Some(state.pid) {
+            return vec![json!({ "full_text": format!("{} off", icon), "color": color::MUTED })];
+        }
+        let text = match state.temperature {
+            Some(temperature) => format!("{} {}K", icon, temperature),
+            None => icon.to_string(),
+        };
+        return vec![json!({ "full_text": text })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The watcher invalidates on changes
+        return None;
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        if event.mouse_button() != MouseButton::Left {
+            return;
+        }
+        let Some(state) = self.state.lock().unwrap().clone()
+        else { return };
+
+        if unsafe { libc::kill(state.pid as libc::pid_t, libc::SIGUSR1) } != 0 {
+            warn!("Failed to toggle {}: {}", state.program, std::io::Error::last_os_error());
+            return;
+        }
+        self.paused = if self.paused == Some(state.pid) { None } else { Some(state.pid) };
+        self.poller.refresh();
+    }
+
+    fn refresh(&mut self) {
+        self.poller.refresh();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn night() {
+        let status = "Location: 48.86 N, 2.35 E\nPeriod: Night\nColor temperature: 3500K\nBrightness: 1.00\n";
+        assert_eq!(parse_status(status), (Some(true), Some(3500)));
+    }
+
+    #[test]
+    fn daytime() {
+        assert_eq!(parse_status("Period: Daytime\nColor temperature: 6500K\n"), (Some(false), Some(6500)));
+    }
+
+    #[test]
+    fn transition_goes_by_how_much_day_it_is() {
+        assert_eq!(parse_status("Period: Transition (42.00% day)\nColor temperature: 4500K"), (Some(true), Some(4500)));
+        assert_eq!(parse_status("Period: Transition (75.50% day)\nColor temperature: 5800K"), (Some(false), Some(5800)));
+        assert_eq!(parse_status("Period: Transition (later)"), (None, None));
+    }
+
+    #[test]
+    fn temperature_alone() {
+        // Without a location there's no period, like with a fixed temperature
+        assert_eq!(parse_status("Color temperature: 4000K\n"), (None, Some(4000)));
+        assert_eq!(parse_status("Color temperature: warm\n"), (None, None));
+    }
+
+    #[test]
+    fn nothing_useful() {
+        assert_eq!(parse_status(""), (None, None));
+        assert_eq!(parse_status("Trying location provider `geoclue2'...\nUnable to connect to GeoClue.\n"), (None, None));
+    }
+}
@@ -0,0 +1,150 @@
+use std::{io, os::unix::fs::FileTypeExt, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{io::{AsyncBufReadExt, BufReader}, net::{UnixListener, UnixStream}, sync::Notify};
+
+use crate::{block::Block, log::{error, warn}};
+
+const PLACEHOLDER: &str = "\u{2026}";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct SocketConfig {
+    /// Defaults to `$XDG_RUNTIME_DIR/i3-status-rs.sock`
+    pub path: Option<PathBuf>,
+}
+
+fn is_socket(path: &Path) -> bool {
+    return std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket());
+}
+
+/// Lets an external program push a block over a unix socket. Every line
+/// received is a JSON object following the i3bar block schema and replaces
+/// the previously shown block.
+pub struct Socket {
+    /// Set once the listener bound it, a socket another bar owns is left alone
+    bound: Arc<Mutex<Option<PathBuf>>>,
+    destruct: Arc<Notify>,
+    latest: Arc<Mutex<Option<Value>>>,
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+        if let Some(path) = self.bound.lock().unwrap().take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Socket {
+    async fn handle_client(stream: UnixStream, latest: Arc<Mutex<Option<Value>>>, invalidate: Arc<Notify>) {
+        let mut lines = BufReader::new(stream).lines();
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return,
+                Err(err) => {
+                    warn!("Failed to read from socket client: {}", err);
+                    return;
+                }
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<Value>(&line) {
+                Ok(block @ Value::Object(_)) => {
+                    *latest.lock().unwrap() = Some(block);
+                    invalidate.notify_one();
+                }
+                Ok(_) => warn!("Ignoring socket message that isn't a JSON object"),
+                Err(err) => warn!("Ignoring malformed socket message: {}", err),
+            }
+        }
+    }
+
+    /// Binds `path`, replacing a socket left behind by a bar that didn't shut down cleanly but
+    /// not one another bar is still listening on
+    async fn bind(path: &Path) -> io::Result<UnixListener> {
+        match UnixStream::connect(path).await {
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is listening on it")),
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused && is_socket(path) => std::fs::remove_file(path)?,
+            Err(_) => {}
+        }
+        return UnixListener::bind(path);
+    }
+
+    fn create_listener(path: PathBuf, bound: Arc<Mutex<Option<PathBuf>>>, destruct: Arc<Notify>, latest: Arc<Mutex<Option<Value>>>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let listener = match Self::bind(&path).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    error!("Failed to bind {}: {}", path.display(), err);
+                    return;
+                }
+            };
+            *bound.lock().unwrap() = Some(path);
+
+            loop {
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            tokio::spawn(Self::handle_client(stream, latest.clone(), invalidate.clone()));
+                        }
+                        Err(err) => warn!("Failed to accept socket client: {}", err),
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn new(config: SocketConfig, invalidate: Arc<Notify>) -> Self {
+        let bound = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+        let latest = Arc::new(Mutex::new(None));
+
+        match config.path.or_else(Self::default_path) {
+            Some(path) => {
+                Self::create_listener(path, bound.clone(), destruct.clone(), latest.clone(), invalidate);
+            }
+            None => error!("Not listening on a socket block, XDG_RUNTIME_DIR is unset so it needs a path"),
+        }
+
+        return Self {
+            bound,
+            destruct,
+            latest,
+        };
+    }
+
+    /// `$XDG_RUNTIME_DIR/i3-status-rs.sock`. Anything else, like /tmp, is shared with other
+    /// users who could take the name or send blocks.
+    fn default_path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty())?;
+        return Some(PathBuf::from(dir).join("i3-status-rs.sock"));
+    }
+}
+
+#[async_trait]
+impl Block for Socket {
+    fn name(&self) -> &str {
+        return "socket";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let block = self.latest.lock().unwrap()
+            .clone()
+            .unwrap_or(json!({ "full_text": PLACEHOLDER }));
+        return vec![block];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // Clients invalidate when they send something new
+        return None;
+    }
+}
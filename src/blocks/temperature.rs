@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::{Align, Block}, color, glob, sysfs};
+
+/// Three digits are as hot as it gets
+const MIN_WIDTH: &str = "100°C";
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct TemperatureConfig {
+    /// Sensor input in millidegrees, wildcards pick the first match
+    pub path: String,
+    pub warning: f64,
+    pub critical: f64,
+}
+
+impl Default for TemperatureConfig {
+    fn default() -> Self {
+        return Self {
+            path: "/sys/class/hwmon/hwmon*/temp1_input".to_string(),
+            warning: 70.0,
+            critical: 85.0,
+        };
+    }
+}
+
+/// Degrees celsius of the first sensor input matching `pattern`, which reads millidegrees
+pub fn read_celsius(pattern: &str) -> Option<f64> {
+    let path = glob::resolve(pattern).into_iter().next()?;
+    return sysfs::read_value::<i64>(path).map(|millidegrees| millidegrees as f64 / 1000.0);
+}
+
+pub struct TemperatureBlock {
+    config: TemperatureConfig,
+}
+
+impl TemperatureBlock {
+    pub fn new(config: TemperatureConfig) -> Self {
+        return Self {
+            config,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for TemperatureBlock {
+    fn name(&self) -> &str {
+        return "temperature";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(celsius) = read_celsius(&self.config.path)
+        else { return vec![json!({ "full_text": "ERROR" })] };
+
+        let mut item = json!({
+            "full_text": format!("{:.0}°C", celsius),
+            "min_width": MIN_WIDTH,
+            "align": Align::Right.as_str(),
+        });
+        if celsius >= self.config.critical {
+            item["color"] = json!(color::CRITICAL);
+        } else if celsius >= self.config.warning {
+            item["color"] = json!(color::WARNING);
+        }
+        return vec![item];
+    }
+}
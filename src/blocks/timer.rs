@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use crate::{block::{Block, ClickEvent, MouseButton}, color, icons::{Icon, IconSet}, log::warn, text};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct TimerConfig {
+    /// Minutes to count down from
+    pub duration: u64,
+    /// Minutes added or taken away per scroll step
+    pub step: u64,
+    /// Run with `sh -c` when the countdown reaches zero
+    pub command: Option<String>,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        return Self {
+            duration: 25,
+            step: 5,
+            command: None,
+        };
+    }
+}
+
+enum State {
+    Idle,
+    Running { end: Instant },
+    Paused { remaining: Duration },
+    Finished { since: Instant },
+}
+
+/// Countdown started with a left click, which pauses and resumes it afterwards.
+/// Right click resets and scrolling changes the time.
+pub struct TimerBlock {
+    duration: Duration,
+    step: Duration,
+    command: Option<String>,
+    state: State,
+    icons: IconSet,
+}
+
+impl TimerBlock {
+    pub fn new(config: TimerConfig, icons: IconSet) -> Self {
+        return Self {
+            duration: Duration::from_secs(config.duration * 60),
+            step: Duration::from_secs(config.step * 60),
+            command: config.command,
+            state: State::Idle,
+            icons,
+        };
+    }
+
+    /// Moves a countdown that ran out on to `Finished`
+    fn update(&mut self) {
+        let State::Running { end } = self.state
+        else { return };
+
+        if end > Instant::now() {
+            return;
+        }
+        self.state = State::Finished { since: end };
+        if let Some(command) = &self.command {
+            if let Err(err) = Command::new("sh").args(["-c", command]).spawn() {
+                warn!("Failed to run {:?}: {}", command, err);
+            }
+        }
+    }
+}
+
+/// `remaining` rounded up to whole seconds, so a fresh countdown shows its full length
+fn format_remaining(remaining: Duration, icons: IconSet) -> String {
+    let seconds = remaining.as_secs() + (remaining.subsec_nanos() > 0) as u64;
+    return format!("{} {}", icons.get(Icon::Timer), text::format_duration(Duration::from_secs(seconds)));
+}
+
+#[async_trait]
+impl Block for TimerBlock {
+    fn name(&self) -> &str {
+        return "timer";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        self.update();
+        let item = match self.state {
+            State::Idle => json!({ "full_text": format_remaining(self.duration, self.icons) }),
+            State::Running { end } => json!({ "full_text": format_remaining(end.saturating_duration_since(Instant::now()), self.icons) }),
+            State::Paused { remaining } => json!({
+                "full_text": format_remaining(remaining, self.icons),
+                "color": color::MUTED,
+            }),
+            State::Finished { since } => json!({
+                "full_text": format_remaining(Duration::ZERO, self.icons),
+                // Flashes by toggling every second
+                "urgent": since.elapsed().as_secs() % 2 == 0,
+            }),
+        };
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return match self.state {
+            State::Running { end } => {
+                // Right when the shown second changes
+                let remaining = end.saturating_duration_since(Instant::now());
+                Some(Duration::from_nanos(remaining.subsec_nanos().into()).max(Duration::from_millis(1)))
+            }
+            State::Finished { .. } => Some(Duration::from_secs(1)),
+            State::Idle | State::Paused { .. } => None,
+        };
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        self.update();
+        match event.mouse_button() {
+            MouseButton::Left => {
+                let now = Instant::now();
+                self.state = match self.state {
+                    State::Idle => State::Running { end: now + self.duration },
+                    State::Running { end } => State::Paused { remaining: end.saturating_duration_since(now) },
+                    State::Paused { remaining } => State::Running { end: now + remaining },
+                    State::Finished { .. } => State::Idle,
+                };
+            }
+            MouseButton::Right => self.state = State::Idle,
+            _ => {}
+        }
+    }
+
+    fn handle_scroll(&mut self, _event: &ClickEvent, steps: i32) {
+        let change = self.step * steps.unsigned_abs();
+        let adjust = |duration: Duration| if steps > 0 {
+            duration + change
+        } else {
+            duration.saturating_sub(change)
+        };
+
+        let now = Instant::now();
+        match &mut self.state {
+            // Never below one step, a zero length countdown would finish right away
+            State::Idle => self.duration = adjust(self.duration).max(self.step),
+            State::Running { end } => *end = now + adjust(end.saturating_duration_since(now)),
+            State::Paused { remaining } => *remaining = adjust(*remaining),
+            State::Finished { .. } => {}
+        }
+    }
+}
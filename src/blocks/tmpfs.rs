@@ -0,0 +1,100 @@
+use std::{io, path::PathBuf};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{block::Block, byte_count::ByteCount, statvfs::{self, FsStats}};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct TmpfsConfig {
+    pub path: PathBuf,
+    pub urgent_percent: f64,
+}
+
+impl Default for TmpfsConfig {
+    fn default() -> Self {
+        return Self {
+            path: PathBuf::from("/tmp"),
+            urgent_percent: 80.0,
+        };
+    }
+}
+
+/// Used space of an in-memory filesystem, turns urgent when it's filling up
+pub struct TmpfsBlock {
+    path: PathBuf,
+    urgent_percent: f64,
+}
+
+impl TmpfsBlock {
+    pub fn new(config: TmpfsConfig) -> Self {
+        return Self {
+            path: config.path,
+            urgent_percent: config.urgent_percent,
+        };
+    }
+
+    fn render_stats(&self, stats: io::Result<FsStats>) -> Vec<Value> {
+        let urgent = stats.as_ref()
+            .map(|stats| stats.used_percent() >= self.urgent_percent)
+            .unwrap_or(false);
+        let usage = stats
+            .map(|stats| format!("{:.2}/{:.2} {:.0}%",
+                ByteCount::from(stats.used()),
+                ByteCount::from(stats.total),
+                stats.used_percent()))
+            .unwrap_or("ERROR".to_string());
+
+        return vec![json!({
+            "full_text": format!("{} {}", self.path.display(), usage),
+            "urgent": urgent,
+        })];
+    }
+}
+
+#[async_trait]
+impl Block for TmpfsBlock {
+    fn name(&self) -> &str {
+        return "tmpfs";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        return self.render_stats(statvfs::statvfs(&self.path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1 << 20;
+
+    fn render(used: u64, total: u64) -> Value {
+        let block = TmpfsBlock::new(TmpfsConfig::default());
+        let stats = FsStats { total, free: total - used, available: total - used };
+        return block.render_stats(Ok(stats)).remove(0);
+    }
+
+    #[test]
+    fn shows_used_of_total() {
+        let item = render(512 * MIB, 2048 * MIB);
+        assert_eq!(item["full_text"], "/tmp 512.00MiB/2.00GiB 25%");
+        assert_eq!(item["urgent"], false);
+    }
+
+    #[test]
+    fn urgent_from_the_threshold() {
+        assert_eq!(render(1600 * MIB, 2000 * MIB)["urgent"], true);
+        assert_eq!(render(1599 * MIB, 2000 * MIB)["urgent"], false);
+    }
+
+    #[test]
+    fn missing_path_is_an_error() {
+        let block = TmpfsBlock::new(TmpfsConfig { path: PathBuf::from("/nonexistent"), ..TmpfsConfig::default() });
+        let item = block.render_stats(statvfs::statvfs(&block.path)).remove(0);
+        assert_eq!(item["full_text"], "/nonexistent ERROR");
+        assert_eq!(item["urgent"], false);
+    }
+}
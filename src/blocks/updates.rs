@@ -0,0 +1,142 @@
+use std::{io, process::Stdio, sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, sync::Notify, time::timeout};
+
+use crate::{block::{Block, PollWait, Poller}, color, icons::{Icon, IconSet}, log::warn};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct UpdatesConfig {
+    /// Run with `sh -c`, prints one line per pending update
+    pub command: String,
+    /// Seconds between checks
+    pub interval: u64,
+    /// Seconds before a check is killed and counts as failed
+    pub timeout: u64,
+    /// Turn yellow above this many updates
+    pub warning: usize,
+    /// Show `0` instead of hiding when up to date
+    pub show_zero: bool,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        return Self {
+            command: "checkupdates".to_string(),
+            interval: 30 * 60,
+            timeout: 120,
+            warning: 25,
+            show_zero: false,
+        };
+    }
+}
+
+/// Number of non-empty lines `command` prints. `checkupdates` exits with 2 when there is
+/// nothing to update which isn't a failure.
+async fn count_updates(command: &str, limit: Duration) -> io::Result<usize> {
+    let child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let output = timeout(limit, child.wait_with_output()).await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out"))??;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let count = stdout.lines().filter(|line| !line.trim().is_empty()).count();
+    let up_to_date = output.status.code() == Some(2) && count == 0;
+    if !output.status.success() && !up_to_date {
+        return Err(io::Error::other(output.status.to_string()));
+    }
+    return Ok(count);
+}
+
+/// Number of pending package updates, checked in the background
+pub struct UpdatesBlock {
+    warning: usize,
+    show_zero: bool,
+    /// None until the first check finished
+    count: Arc<Mutex<Option<io::Result<usize>>>>,
+    poller: Poller,
+    icons: IconSet,
+}
+
+impl UpdatesBlock {
+    fn create_checker(command: String, interval: Duration, limit: Duration, count: Arc<Mutex<Option<io::Result<usize>>>>, wait: PollWait, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            loop {
+                let result = tokio::select! {
+                    _ = wait.stopped() => return,
+                    result = count_updates(&command, limit) => result,
+                };
+                if let Err(err) = &result {
+                    warn!("Checking for updates with {:?} failed: {}", command, err);
+                }
+                *count.lock().unwrap() = Some(result);
+                invalidate.notify_one();
+
+                if !wait.sleep(interval).await {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn new(config: UpdatesConfig, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let count = Arc::new(Mutex::new(None));
+        let (poller, wait) = Poller::new();
+
+        Self::create_checker(
+            config.command,
+            Duration::from_secs(config.interval),
+            Duration::from_secs(config.timeout),
+            count.clone(),
+            wait,
+            invalidate,
+        );
+
+        return Self {
+            warning: config.warning,
+            show_zero: config.show_zero,
+            count,
+            poller,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for UpdatesBlock {
+    fn name(&self) -> &str {
+        return "updates";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let item = match &*self.count.lock().unwrap() {
+            None => return Vec::new(),
+            Some(Err(_)) => json!({ "full_text": format!("{} ERROR", self.icons.get(Icon::Updates)), "color": color::CRITICAL }),
+            Some(Ok(0)) if !self.show_zero => return Vec::new(),
+            Some(Ok(count)) => {
+                let mut item = json!({ "full_text": format!("{} {}", self.icons.get(Icon::Updates), count) });
+                if *count > self.warning {
+                    item["color"] = json!(color::WARNING);
+                }
+                item
+            }
+        };
+        return vec![item];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The checker invalidates after every check
+        return None;
+    }
+
+    fn refresh(&mut self) {
+        self.poller.refresh();
+    }
+}
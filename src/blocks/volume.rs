@@ -0,0 +1,391 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use dbus::{arg::Variant, channel::{Channel, MatchingReceiver}, message::MatchRule, nonblock::{Proxy, SyncConnection}, Message, Path};
+use dbus_tokio::connection;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{block::{Align, Block, ClickEvent}, bus::Bus, icons::{Icon, IconSet}, log::warn};
+
+// PulseAudio's DBus protocol, only there with module-dbus-protocol loaded. Neither PulseAudio
+// nor PipeWire load it by default.
+const SERVER_LOOKUP_NAME: &str = "org.PulseAudio1";
+const SERVER_LOOKUP_PATH: &str = "/org/pulseaudio/server_lookup1";
+const SERVER_LOOKUP_INTERFACE: &str = "org.PulseAudio.ServerLookup1";
+// The server is a peer-to-peer connection so the destination is ignored
+const CORE_NAME: &str = "org.PulseAudio.Core1";
+const CORE_PATH: &str = "/org/pulseaudio/core1";
+const CORE_INTERFACE: &str = "org.PulseAudio.Core1";
+const DEVICE_INTERFACE: &str = "org.PulseAudio.Core1.Device";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// Channel volume of 100%
+const VOLUME_NORM: u32 = 65536;
+/// Volume can go above 100% but rarely does
+const MIN_WIDTH: &str = "VOL 100%";
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct VolumeConfig {
+    /// Percent to change the volume by per scroll step
+    pub step: u32,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        return Self {
+            step: 5,
+        };
+    }
+}
+
+struct Sink {
+    path: Path<'static>,
+    /// Per channel, `VOLUME_NORM` is 100%
+    volume: Vec<u32>,
+    muted: bool,
+}
+
+/// Volume in percent of the loudest channel
+fn percent(volume: &[u32]) -> u32 {
+    let loudest = volume.iter().copied().max().unwrap_or(0);
+    return ((loudest as u64 * 100 + VOLUME_NORM as u64 / 2) / VOLUME_NORM as u64) as u32;
+}
+
+/// `volume` with the loudest channel at `percent`, keeping the balance between channels
+fn with_percent(volume: &[u32], percent: u32) -> Vec<u32> {
+    let target = (percent as u64 * VOLUME_NORM as u64 / 100) as u32;
+    let loudest = volume.iter().copied().max().unwrap_or(0);
+    if loudest == 0 {
+        return vec![target; volume.len()];
+    }
+    return volume.iter()
+        .map(|&channel| (channel as u64 * target as u64 / loudest as u64) as u32)
+        .collect();
+}
+
+/// Volume in percent after scrolling `steps` of `step` from `current`. Scrolling up stops at
+/// 100%, a sink that's already louder than that isn't turned down by it.
+fn scrolled(current: u32, step: u32, steps: i32) -> u32 {
+    let max = current.max(100) as i64;
+    return (current as i64 + step as i64 * steps as i64).clamp(0, max) as u32;
+}
+
+struct VolumeState {
+    invalidate: Arc<Notify>,
+    conn: Option<Arc<SyncConnection>>,
+    sink: Option<Sink>,
+}
+
+impl VolumeState {
+    fn set_sink(&mut self, sink: Option<Sink>) {
+        if self.sink.is_none() && sink.is_none() {
+            return;
+        }
+        self.sink = sink;
+        self.invalidate.notify_one();
+    }
+}
+
+fn proxy(conn: Arc<SyncConnection>, path: Path<'static>) -> Proxy<'static, Arc<SyncConnection>> {
+    return Proxy::new(CORE_NAME, path, TIMEOUT, conn);
+}
+
+/// Volume and mute state of the sink at `path`
+async fn fetch_sink(conn: Arc<SyncConnection>, path: Path<'static>) -> Result<Sink, dbus::Error> {
+    let proxy = proxy(conn, path.clone());
+    let (volume,): (Variant<Vec<u32>>,) = proxy.method_call(PROPERTIES_INTERFACE, "Get", (DEVICE_INTERFACE, "Volume")).await?;
+    let (muted,): (Variant<bool>,) = proxy.method_call(PROPERTIES_INTERFACE, "Get", (DEVICE_INTERFACE, "Mute")).await?;
+    return Ok(Sink { path, volume: volume.0, muted: muted.0 });
+}
+
+/// The default sink, `None` when there isn't one
+async fn fetch_fallback_sink(conn: Arc<SyncConnection>) -> Option<Sink> {
+    let core = proxy(conn.clone(), Path::from(CORE_PATH));
+    let reply: Result<(Variant<Path<'static>>,), _> = core.method_call(PROPERTIES_INTERFACE, "Get", (CORE_INTERFACE, "FallbackSink")).await;
+    // Fails with `org.freedesktop.DBus.Error.NoSuchProperty` when no sink is set
+    let Ok((path,)) = reply
+    else { return None };
+
+    return match fetch_sink(conn, path.0).await {
+        Ok(sink) => Some(sink),
+        Err(err) => {
+            warn!("Failed to get sink volume: {}", err);
+            None
+        }
+    };
+}
+
+/// Opens a connection to the PulseAudio server, whose address is published on the session bus
+async fn connect(bus: &mut Bus) -> Result<(connection::IOResource<SyncConnection>, Arc<SyncConnection>), dbus::Error> {
+    let session = loop {
+        if let Some(session) = bus.current_and_update() {
+            break session;
+        }
+        bus.changed().await;
+    };
+
+    let lookup = Proxy::new(SERVER_LOOKUP_NAME, SERVER_LOOKUP_PATH, TIMEOUT, session);
+    let (address,): (Variant<String>,) = lookup.method_call(PROPERTIES_INTERFACE, "Get", (SERVER_LOOKUP_INTERFACE, "Address")).await?;
+    return connection::from_channel(Channel::open_private(&address.0)?);
+}
+
+/// Asks the server to send us `signals`, it only does so for listeners that asked
+async fn listen_for_signals(conn: Arc<SyncConnection>, signals: &[&str]) -> Result<(), dbus::Error> {
+    let core = proxy(conn, Path::from(CORE_PATH));
+    for signal in signals {
+        let objects: Vec<Path<'static>> = Vec::new();
+        core.method_call::<(), _, _, _>(CORE_INTERFACE, "ListenForSignal", (*signal, objects)).await?;
+    }
+    return Ok(());
+}
+
+fn receive_signals(conn: &SyncConnection, state: Arc<Mutex<VolumeState>>) {
+    fn on_sink<T>(state: &Mutex<VolumeState>, message: &Message, update: impl FnOnce(&mut Sink, T)) where T: for<'a> dbus::arg::Get<'a> + dbus::arg::Arg {
+        let Ok(value) = message.read1::<T>()
+        else { return };
+
+        let mut state = state.lock().unwrap();
+        let state = &mut *state;
+        let Some(sink) = state.sink.as_mut()
+        else { return };
+
+        if message.path().as_ref() != Some(&sink.path) {
+            return;
+        }
+        update(sink, value);
+        state.invalidate.notify_one();
+    }
+
+    {
+        let state = state.clone();
+        conn.start_receive(MatchRule::new_signal(DEVICE_INTERFACE, "VolumeUpdated"), Box::new(move |message, _| {
+            on_sink(&state, &message, |sink, volume: Vec<u32>| sink.volume = volume);
+            true
+        }));
+    }
+    {
+        let state = state.clone();
+        conn.start_receive(MatchRule::new_signal(DEVICE_INTERFACE, "MuteUpdated"), Box::new(move |message, _| {
+            on_sink(&state, &message, |sink, muted: bool| sink.muted = muted);
+            true
+        }));
+    }
+    {
+        let state = state.clone();
+        conn.start_receive(MatchRule::new_signal(CORE_INTERFACE, "FallbackSinkUpdated"), Box::new(move |message, _| {
+            let Ok(path) = message.read1::<Path<'static>>()
+            else { return true };
+
+            let Some(conn) = state.lock().unwrap().conn.clone()
+            else { return true };
+
+            let state = state.clone();
+            tokio::spawn(async move {
+                match fetch_sink(conn, path).await {
+                    Ok(sink) => state.lock().unwrap().set_sink(Some(sink)),
+                    Err(err) => warn!("Failed to get sink volume: {}", err),
+                }
+            });
+            true
+        }));
+    }
+    conn.start_receive(MatchRule::new_signal(CORE_INTERFACE, "FallbackSinkUnset"), Box::new(move |_, _| {
+        state.lock().unwrap().set_sink(None);
+        true
+    }));
+}
+
+/// Keeps `state` in sync with the default sink, reconnecting with backoff
+async fn watch(mut bus: Bus, state: Arc<Mutex<VolumeState>>, destruct: Arc<Notify>) {
+    let mut backoff = MIN_BACKOFF;
+    let mut reported = false;
+    loop {
+        let result = tokio::select! {
+            result = connect(&mut bus) => result,
+            _ = destruct.notified() => return,
+        };
+
+        match result {
+            Ok((resource, conn)) => {
+                let mut lost = tokio::spawn(resource);
+
+                receive_signals(&conn, state.clone());
+                let signals = [
+                    "org.PulseAudio.Core1.Device.VolumeUpdated",
+                    "org.PulseAudio.Core1.Device.MuteUpdated",
+                    "org.PulseAudio.Core1.FallbackSinkUpdated",
+                    "org.PulseAudio.Core1.FallbackSinkUnset",
+                ];
+                if let Err(err) = listen_for_signals(conn.clone(), &signals).await {
+                    warn!("Failed to listen for volume changes: {}", err);
+                }
+
+                let sink = fetch_fallback_sink(conn.clone()).await;
+                {
+                    let mut state = state.lock().unwrap();
+                    state.conn = Some(conn);
+                    state.set_sink(sink);
+                }
+
+                tokio::select! {
+                    err = &mut lost => {
+                        if let Ok(err) = err {
+                            warn!("Lost connection to PulseAudio: {}", err);
+                        }
+                    }
+                    _ = destruct.notified() => {
+                        lost.abort();
+                        return;
+                    }
+                }
+                backoff = MIN_BACKOFF;
+                reported = false;
+            }
+            Err(err) => {
+                // Not having PulseAudio is a valid setup, don't repeat ourselves
+                if !reported && err.name() == Some("org.freedesktop.DBus.Error.ServiceUnknown") {
+                    warn!("{} isn't on the session bus, the volume block needs module-dbus-protocol loaded into the sound server", SERVER_LOOKUP_NAME);
+                } else if !reported {
+                    warn!("Failed to connect to PulseAudio: {}", err);
+                }
+                reported = true;
+            }
+        }
+
+        {
+            let mut state = state.lock().unwrap();
+            state.conn = None;
+            state.set_sink(None);
+        }
+
+        tokio::select! {
+            _ = sleep(backoff) => {}
+            _ = destruct.notified() => return,
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Default sink volume, scrolling changes it. Talks to the sound server over PulseAudio's
+/// DBus protocol, which needs `load-module module-dbus-protocol` in its config. The block
+/// stays hidden without it.
+pub struct VolumeBlock {
+    state: Arc<Mutex<VolumeState>>,
+    destruct: Arc<Notify>,
+    step: u32,
+    icons: IconSet,
+}
+
+impl Drop for VolumeBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl VolumeBlock {
+    pub fn new(config: VolumeConfig, bus: Bus, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let state = Arc::new(Mutex::new(VolumeState {
+            invalidate,
+            conn: None,
+            sink: None,
+        }));
+        let destruct = Arc::new(Notify::new());
+        tokio::spawn(watch(bus, state.clone(), destruct.clone()));
+        return Self {
+            state,
+            destruct,
+            step: config.step,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for VolumeBlock {
+    fn name(&self) -> &str {
+        return "volume";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        let Some(sink) = &state.sink
+        else { return Vec::new() };
+
+        let text = if sink.muted { self.icons.get(Icon::Muted).to_string() } else { format!("VOL {}%", percent(&sink.volume)) };
+        return vec![json!({ "full_text": text, "min_width": MIN_WIDTH, "align": Align::Right.as_str() })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // Changes arrive as signals
+        return None;
+    }
+
+    fn handle_scroll(&mut self, _event: &ClickEvent, steps: i32) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let Some(conn) = state.conn.clone()
+        else { return };
+
+        let Some(sink) = state.sink.as_mut()
+        else { return };
+
+        let target = scrolled(percent(&sink.volume), self.step, steps);
+        let volume = with_percent(&sink.volume, target);
+        // Show the new volume right away, the server confirms it with VolumeUpdated
+        sink.volume = volume.clone();
+        state.invalidate.notify_one();
+
+        let reply = proxy(conn, sink.path.clone())
+            .method_call::<(), _, _, _>(PROPERTIES_INTERFACE, "Set", (DEVICE_INTERFACE, "Volume", Variant(volume)));
+        tokio::spawn(async {
+            if let Err(err) = reply.await {
+                warn!("Failed to set volume: {}", err);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_of_the_loudest_channel() {
+        assert_eq!(percent(&[VOLUME_NORM, VOLUME_NORM]), 100);
+        assert_eq!(percent(&[VOLUME_NORM / 4, VOLUME_NORM / 2]), 50);
+        assert_eq!(percent(&[VOLUME_NORM * 3 / 2]), 150);
+        // Rounded to the nearest percent rather than down
+        assert_eq!(percent(&[32500]), 50);
+        assert_eq!(percent(&[]), 0);
+    }
+
+    #[test]
+    fn with_percent_keeps_the_balance() {
+        let half = VOLUME_NORM / 2;
+        assert_eq!(with_percent(&[VOLUME_NORM, half], 50), [half, half / 2]);
+        assert_eq!(percent(&with_percent(&[VOLUME_NORM, half], 35)), 35);
+        // Channels all at zero have no balance to keep, they all get the volume
+        assert_eq!(with_percent(&[0, 0], 100), [VOLUME_NORM, VOLUME_NORM]);
+    }
+
+    #[test]
+    fn scrolling_stays_within_range() {
+        assert_eq!(scrolled(50, 5, 1), 55);
+        assert_eq!(scrolled(50, 5, -3), 35);
+        assert_eq!(scrolled(98, 5, 1), 100);
+        assert_eq!(scrolled(3, 5, -1), 0);
+    }
+
+    #[test]
+    fn scrolling_up_never_turns_an_amplified_sink_down() {
+        assert_eq!(scrolled(150, 5, 1), 150);
+        assert_eq!(scrolled(150, 5, -1), 145);
+        assert_eq!(scrolled(100, 5, 2), 100);
+    }
+}
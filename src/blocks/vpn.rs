@@ -0,0 +1,216 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use dbus::{message::{MatchRule, MessageType}, nonblock::{stdintf::org_freedesktop_dbus::Properties, MsgMatch, Proxy, SyncConnection}};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use crate::{block::Block, bus::Bus, color, glob, icons::{Icon, IconSet}, log::warn, sysfs};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const NETWORK_MANAGER: &str = "org.freedesktop.NetworkManager";
+const ACTIVE_CONNECTION: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// `IFF_UP` in an interface's `flags`
+const IFF_UP: u32 = 0x1;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VpnBackend {
+    /// Any of `interfaces` being up
+    #[default]
+    Interface,
+    /// A VPN or WireGuard connection active in NetworkManager
+    NetworkManager,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct VpnConfig {
+    pub backend: VpnBackend,
+    /// Globs of the interface names that count as VPN, for the `interface` backend
+    pub interfaces: Vec<String>,
+    /// Seconds between checking the interfaces
+    pub interval: u64,
+    /// Show the connection name, only NetworkManager knows it
+    pub show_name: bool,
+}
+
+impl Default for VpnConfig {
+    fn default() -> Self {
+        return Self {
+            backend: VpnBackend::default(),
+            interfaces: vec!["tun*".to_string(), "wg*".to_string()],
+            interval: 5,
+            show_name: false,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct VpnState {
+    connected: bool,
+    /// Name of the connection in NetworkManager
+    name: Option<String>,
+}
+
+/// Whether an interface matching one of `patterns` is up. Tunnels report their operstate as
+/// `unknown`, so the administrative up flag is what counts.
+fn interface_up(patterns: &[String]) -> bool {
+    return patterns.iter()
+        .flat_map(|pattern| glob::resolve(&format!("{}/{}", SYS_CLASS_NET, pattern)))
+        .filter_map(|interface| sysfs::read_string(interface.join("flags")))
+        .filter_map(|flags| u32::from_str_radix(flags.trim_start_matches("0x"), 16).ok())
+        .any(|flags| flags & IFF_UP != 0);
+}
+
+/// Whether a VPN is connected, going by the network interfaces or NetworkManager
+pub struct VpnBlock {
+    backend: VpnBackend,
+    interfaces: Vec<String>,
+    interval: Duration,
+    show_name: bool,
+    /// From NetworkManager, None while it isn't reachable
+    state: Arc<Mutex<Option<VpnState>>>,
+    destruct: Arc<Notify>,
+    icons: IconSet,
+}
+
+impl Drop for VpnBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl VpnBlock {
+    /// The first active VPN connection, NetworkManager lists them next to the regular ones
+    async fn fetch(conn: Arc<SyncConnection>) -> Option<VpnState> {
+        let manager = Proxy::new(NETWORK_MANAGER, "/org/freedesktop/NetworkManager", TIMEOUT, conn.clone());
+        let active: Vec<dbus::Path<'static>> = match manager.get(NETWORK_MANAGER, "ActiveConnections").await {
+            Ok(active) => active,
+            // Not running
+            Err(_) => return None,
+        };
+
+        for path in active {
+            let connection = Proxy::new(NETWORK_MANAGER, path, TIMEOUT, conn.clone());
+            let Ok(kind) = connection.get::<String>(ACTIVE_CONNECTION, "Type").await
+            else { continue };
+
+            if kind == "vpn" || kind == "wireguard" {
+                let name = connection.get::<String>(ACTIVE_CONNECTION, "Id").await.ok();
+                return Some(VpnState { connected: true, name });
+            }
+        }
+        return Some(VpnState { connected: false, name: None });
+    }
+
+    /// Anything NetworkManager signals notifies `changed`
+    async fn watch(conn: Arc<SyncConnection>, changed: Arc<Notify>) -> Result<MsgMatch, dbus::Error> {
+        let rule = MatchRule::new()
+            .with_type(MessageType::Signal)
+            .with_sender(NETWORK_MANAGER);
+        return Ok(conn.add_match(rule).await?
+            .msg_cb(move |_| {
+                changed.notify_one();
+                true
+            }));
+    }
+
+    fn create_watcher(mut bus: Bus, state: Arc<Mutex<Option<VpnState>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let set_state = |current: Option<VpnState>| {
+                if *state.lock().unwrap() != current {
+                    *state.lock().unwrap() = current;
+                    invalidate.notify_one();
+                }
+            };
+
+            loop {
+                let changed = Arc::new(Notify::new());
+                let watching = match bus.current_and_update() {
+                    Some(conn) => match Self::watch(conn.clone(), changed.clone()).await {
+                        Ok(signals) => Some((conn, signals)),
+                        Err(err) => {
+                            warn!("Failed to AddMatch on NetworkManager signals: {}", err);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                loop {
+                    match &watching {
+                        Some((conn, _)) => set_state(Self::fetch(conn.clone()).await),
+                        None => set_state(None),
+                    }
+
+                    tokio::select! {
+                        _ = destruct.notified() => {
+                            if let Some((conn, signals)) = watching {
+                                let _ = conn.remove_match(signals.token()).await;
+                            }
+                            return;
+                        }
+                        _ = bus.changed() => break,
+                        _ = changed.notified() => {}
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn new(config: VpnConfig, invalidate: Arc<Notify>, icons: IconSet) -> Self {
+        let state = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        if config.backend == VpnBackend::NetworkManager {
+            Self::create_watcher(Bus::connect_system(), state.clone(), destruct.clone(), invalidate);
+        }
+
+        return Self {
+            backend: config.backend,
+            interfaces: config.interfaces,
+            interval: Duration::from_secs(config.interval),
+            show_name: config.show_name,
+            state,
+            destruct,
+            icons,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for VpnBlock {
+    fn name(&self) -> &str {
+        return "vpn";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let state = match self.backend {
+            VpnBackend::Interface => VpnState { connected: interface_up(&self.interfaces), name: None },
+            VpnBackend::NetworkManager => match self.state.lock().unwrap().clone() {
+                Some(state) => state,
+                None => return Vec::new(),
+            },
+        };
+
+        if !state.connected {
+            return vec![json!({ "full_text": format!("VPN {}", self.icons.get(Icon::VpnDown)), "color": color::MUTED })];
+        }
+        let mut text = format!("VPN {}", self.icons.get(Icon::VpnUp));
+        if let (true, Some(name)) = (self.show_name, state.name) {
+            text += &format!(" {}", name);
+        }
+        return vec![json!({ "full_text": text, "color": color::GOOD })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return match self.backend {
+            VpnBackend::Interface => Some(self.interval),
+            // The watcher invalidates on changes
+            VpnBackend::NetworkManager => None,
+        };
+    }
+}
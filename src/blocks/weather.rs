@@ -0,0 +1,161 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use crate::{block::{Block, PollWait, Poller}, http};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    fn symbol(&self) -> &'static str {
+        return match self {
+            TemperatureUnit::Celsius => "\u{b0}C",
+            TemperatureUnit::Fahrenheit => "\u{b0}F",
+        };
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WeatherConfig {
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default = "default_unit")]
+    pub unit: TemperatureUnit,
+    /// Open-Meteo compatible forecast API
+    #[serde(default = "default_api")]
+    pub api: String,
+    /// Seconds between fetches
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_unit() -> TemperatureUnit {
+    return TemperatureUnit::Celsius;
+}
+
+fn default_api() -> String {
+    return "https://api.open-meteo.com/v1/forecast".to_string();
+}
+
+fn default_interval() -> u64 {
+    return 30 * 60;
+}
+
+const TIMEOUT: u64 = 10;
+
+#[derive(Clone, Copy, PartialEq)]
+struct Reading {
+    temperature: f64,
+    code: u64,
+}
+
+/// The `current_weather` of an Open-Meteo forecast response
+fn parse_reading(response: &str) -> Option<Reading> {
+    let response: Value = serde_json::from_str(response).ok()?;
+    let current = response.get("current_weather")?;
+    return Some(Reading {
+        temperature: current.get("temperature")?.as_f64()?,
+        code: current.get("weathercode")?.as_u64()?,
+    });
+}
+
+/// Glyph for a WMO weather interpretation code
+fn glyph(code: u64) -> &'static str {
+    return match code {
+        0 => "\u{2600}",
+        1 | 2 => "\u{26c5}",
+        3 => "\u{2601}",
+        45 | 48 => "\u{1f32b}",
+        51..=67 | 80..=82 => "\u{1f327}",
+        71..=77 | 85 | 86 => "\u{2744}",
+        95..=99 => "\u{26c8}",
+        _ => "?",
+    };
+}
+
+/// Current conditions from Open-Meteo, which doesn't need an API key
+pub struct WeatherBlock {
+    unit: TemperatureUnit,
+    /// Last good reading, kept when a fetch fails
+    reading: Arc<Mutex<Option<Reading>>>,
+    poller: Poller,
+}
+
+impl WeatherBlock {
+    fn create_fetcher(url: String, interval: Duration, reading: Arc<Mutex<Option<Reading>>>, wait: PollWait, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            loop {
+                let response = tokio::select! {
+                    _ = wait.stopped() => return,
+                    response = http::get(&url, TIMEOUT) => response,
+                };
+                if let Some(current) = response.as_deref().and_then(parse_reading) {
+                    if *reading.lock().unwrap() != Some(current) {
+                        *reading.lock().unwrap() = Some(current);
+                        invalidate.notify_one();
+                    }
+                }
+
+                if !wait.sleep(interval).await {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn new(config: WeatherConfig, invalidate: Arc<Notify>) -> Self {
+        let url = format!(
+            "{}?latitude={}&longitude={}&current_weather=true&temperature_unit={}",
+            config.api,
+            config.latitude,
+            config.longitude,
+            match config.unit {
+                TemperatureUnit::Celsius => "celsius",
+                TemperatureUnit::Fahrenheit => "fahrenheit",
+            },
+        );
+        let reading = Arc::new(Mutex::new(None));
+        let (poller, wait) = Poller::new();
+
+        Self::create_fetcher(url, Duration::from_secs(config.interval), reading.clone(), wait, invalidate);
+
+        return Self {
+            unit: config.unit,
+            reading,
+            poller,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for WeatherBlock {
+    fn name(&self) -> &str {
+        return "weather";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(reading) = *self.reading.lock().unwrap()
+        else { return Vec::new() };
+
+        return vec![json!({
+            "full_text": format!("{} {:.0}{}", glyph(reading.code), reading.temperature, self.unit.symbol()),
+        })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The fetcher invalidates when a new reading arrives
+        return None;
+    }
+
+    fn refresh(&mut self) {
+        self.poller.refresh();
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fs, path::Path, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{process::Command, time::timeout};
+
+use crate::{block::{Block, Markup}, color, log::warn, sysfs, text};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const IW_TIMEOUT: Duration = Duration::from_secs(2);
+const BARS: [&str; 4] = ["\u{2582}", "\u{2584}", "\u{2586}", "\u{2588}"];
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct WifiConfig {
+    /// Interface name or `auto` for the first wireless interface
+    pub interface: String,
+    /// Longer SSIDs are cut off with an ellipsis
+    pub max_length: Option<usize>,
+}
+
+impl Default for WifiConfig {
+    fn default() -> Self {
+        return Self {
+            interface: "auto".to_string(),
+            max_length: None,
+        };
+    }
+}
+
+fn auto_interface() -> Option<String> {
+    let mut interfaces: Vec<String> = fs::read_dir(SYS_CLASS_NET).ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| Path::new(SYS_CLASS_NET).join(name).join("wireless").exists())
+        .collect();
+    interfaces.sort();
+    return interfaces.into_iter().next();
+}
+
+/// Signal quality of `interface` in percent from the contents of /proc/net/wireless,
+/// None when it isn't listed (not associated)
+fn parse_wireless(wireless: &str, interface: &str) -> Option<u8> {
+    for line in wireless.lines() {
+        let Some((name, fields)) = line.split_once(':')
+        else { continue };
+
+        if name.trim() != interface {
+            continue;
+        }
+
+        // status link level noise ..., the values have a trailing `.` when they were updated
+        let fields: Vec<f64> = fields.split_whitespace()
+            .skip(1)
+            .take(2)
+            .filter_map(|field| field.trim_end_matches('.').parse().ok())
+            .collect();
+        let [link, level] = fields[..]
+        else { return None };
+
+        // Prefer the level in dBm, mapping -100..-50 to 0..100 like NetworkManager,
+        // drivers that don't report one give the link quality out of 70
+        let quality = if level < 0.0 { 2.0 * (level + 100.0) } else { link / 70.0 * 100.0 };
+        return Some(quality.clamp(0.0, 100.0).round() as u8);
+    }
+    return None;
+}
+
+/// The `SSID:` line of `iw dev <interface> link`
+fn parse_ssid(link: &str) -> Option<String> {
+    return link.lines()
+        .find_map(|line| line.trim().strip_prefix("SSID:"))
+        .map(|ssid| ssid.trim().to_string());
+}
+
+fn bars(quality: u8) -> String {
+    let count = (quality as usize * BARS.len()).div_ceil(100).max(1);
+    return BARS[..count].concat();
+}
+
+async fn ssid(interface: &str) -> Option<String> {
+    let mut command = Command::new("iw");
+    command.args(["dev", interface, "link"]).kill_on_drop(true);
+    let output = match timeout(IW_TIMEOUT, command.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => {
+            warn!("Failed to run iw: {}", err);
+            return None;
+        }
+        Err(_) => return None,
+    };
+    return parse_ssid(&String::from_utf8_lossy(&output.stdout));
+}
+
+/// Connected network and signal strength of a wireless interface
+pub struct WifiBlock {
+    interface: String,
+    max_length: Option<usize>,
+    markup: Markup,
+}
+
+impl WifiBlock {
+    pub fn new(config: WifiConfig, markup: Markup) -> Self {
+        return Self {
+            interface: config.interface,
+            max_length: config.max_length,
+            markup,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for WifiBlock {
+    fn name(&self) -> &str {
+        return "wifi";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let down = || vec![json!({ "full_text": "WIFI down", "color": color::MUTED })];
+
+        let interface = if self.interface == "auto" { auto_interface() } else { Some(self.interface.clone()) };
+        let Some(interface) = interface
+        else { return down() };
+
+        let up = sysfs::read_string(Path::new(SYS_CLASS_NET).join(&interface).join("operstate")).as_deref() == Some("up");
+        let quality = fs::read_to_string("/proc/net/wireless").ok()
+            .and_then(|wireless| parse_wireless(&wireless, &interface));
+        let (true, Some(quality)) = (up, quality)
+        else { return down() };
+
+        let mut full_text = "WIFI".to_string();
+        if let Some(ssid) = ssid(&interface).await {
+            let ssid = match self.max_length {
+                Some(max_length) => text::truncate(&ssid, max_length),
+                None => ssid,
+            };
+            let ssid = if self.markup == Markup::Pango { text::escape_markup(&ssid) } else { ssid };
+            full_text += &format!(" {}", ssid);
+        }
+        full_text += &format!(" {}% {}", quality, bars(quality));
+        return vec![json!({ "full_text": full_text })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return Some(Duration::from_secs(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIRELESS: &str = "\
+Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+wlp3s0: 0000   54.  -56.  -256        0      0      0      0     12        0
+  wlan1: 0000   35    0    0          0      0      0      0      0        0
+";
+
+    #[test]
+    fn level_in_dbm() {
+        // -56 dBm is 44 dB above the floor of -100
+        assert_eq!(parse_wireless(WIRELESS, "wlp3s0"), Some(88));
+    }
+
+    #[test]
+    fn link_quality_without_a_level() {
+        assert_eq!(parse_wireless(WIRELESS, "wlan1"), Some(50));
+    }
+
+    #[test]
+    fn interface_not_listed() {
+        assert_eq!(parse_wireless(WIRELESS, "wlp4s0"), None);
+        assert_eq!(parse_wireless(WIRELESS, "face"), None);
+        assert_eq!(parse_wireless("", "wlp3s0"), None);
+    }
+
+    #[test]
+    fn ssid_from_iw() {
+        let link = "Connected to 12:34:56:78:9a:bc (on wlp3s0)\n\tSSID: my network\n\tfreq: 5180\n";
+        assert_eq!(parse_ssid(link).as_deref(), Some("my network"));
+        assert_eq!(parse_ssid("Not connected.\n"), None);
+    }
+
+    #[test]
+    fn bars_follow_the_quality() {
+        assert_eq!(bars(0), BARS[0]);
+        assert_eq!(bars(100), BARS.concat());
+        assert_eq!(bars(50), BARS[..2].concat());
+        assert_eq!(bars(51), BARS[..3].concat());
+    }
+}
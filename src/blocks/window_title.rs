@@ -0,0 +1,164 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{block::{Block, Markup}, i3ipc::{self, I3Ipc, Message}, log::{debug, warn}, text};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct WindowTitleConfig {
+    /// Longer titles are cut off with an ellipsis
+    pub max_length: Option<usize>,
+}
+
+impl Default for WindowTitleConfig {
+    fn default() -> Self {
+        return Self {
+            max_length: Some(60),
+        };
+    }
+}
+
+/// Title of the focused window in an i3 tree, None when a workspace without windows is focused
+fn focused_title(node: &Value) -> Option<String> {
+    if node.get("focused").and_then(Value::as_bool) == Some(true) {
+        let is_window = matches!(node.get("type").and_then(Value::as_str), Some("con" | "floating_con"));
+        return is_window.then(|| node.get("name")?.as_str().map(str::to_string)).flatten();
+    }
+    return ["nodes", "floating_nodes"].iter()
+        .filter_map(|children| node.get(children)?.as_array())
+        .flatten()
+        .find_map(focused_title);
+}
+
+/// Title of the focused window, following i3 or sway over their IPC socket
+pub struct WindowTitleBlock {
+    title: Arc<Mutex<Option<String>>>,
+    max_length: Option<usize>,
+    markup: Markup,
+    destruct: Arc<Notify>,
+}
+
+impl Drop for WindowTitleBlock {
+    fn drop(&mut self) {
+        self.destruct.notify_one();
+    }
+}
+
+impl WindowTitleBlock {
+    /// The connection for events and one for asking for the tree, as the subscribed one
+    /// gets events in between the replies
+    async fn connect() -> std::io::Result<(I3Ipc, I3Ipc)> {
+        let mut events = I3Ipc::connect().await?;
+        events.subscribe(&["window", "workspace"]).await?;
+        return Ok((events, I3Ipc::connect().await?));
+    }
+
+    /// Follows focus changes until the connection breaks
+    async fn follow(mut events: I3Ipc, mut queries: I3Ipc, title: &Mutex<Option<String>>, invalidate: &Notify) -> std::io::Error {
+        loop {
+            let tree = match queries.request(i3ipc::GET_TREE, "").await {
+                Ok(tree) => tree,
+                Err(err) => return err,
+            };
+            let current = focused_title(&tree);
+            if *title.lock().unwrap() != current {
+                *title.lock().unwrap() = current;
+                invalidate.notify_one();
+            }
+
+            // Any window or workspace event may have moved the focus or renamed the window
+            loop {
+                match events.read().await {
+                    Ok(Message::Event(..)) => break,
+                    Ok(Message::Reply(..)) => {}
+                    Err(err) => return err,
+                }
+            }
+        }
+    }
+
+    fn create_watcher(title: Arc<Mutex<Option<String>>>, destruct: Arc<Notify>, invalidate: Arc<Notify>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            let mut backoff = MIN_BACKOFF;
+            // Without i3 there's nothing to show, retrying shouldn't fill the log
+            let mut warned = false;
+            loop {
+                let connected = tokio::select! {
+                    _ = destruct.notified() => return,
+                    connected = Self::connect() => connected,
+                };
+                match connected {
+                    Ok((events, queries)) => {
+                        backoff = MIN_BACKOFF;
+                        warned = false;
+                        let err = tokio::select! {
+                            _ = destruct.notified() => return,
+                            err = Self::follow(events, queries, &title, &invalidate) => err,
+                        };
+                        warn!("Lost i3 IPC connection: {}", err);
+                        if title.lock().unwrap().take().is_some() {
+                            invalidate.notify_one();
+                        }
+                    }
+                    Err(err) if warned => debug!("Failed to connect to i3 IPC: {}", err),
+                    Err(err) => {
+                        warn!("Failed to connect to i3 IPC, the window title stays empty until it's available: {}", err);
+                        warned = true;
+                    }
+                }
+
+                tokio::select! {
+                    _ = destruct.notified() => return,
+                    _ = sleep(backoff) => {}
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+    }
+
+    pub fn new(config: WindowTitleConfig, invalidate: Arc<Notify>, markup: Markup) -> Self {
+        let title = Arc::new(Mutex::new(None));
+        let destruct = Arc::new(Notify::new());
+
+        Self::create_watcher(title.clone(), destruct.clone(), invalidate);
+
+        return Self {
+            title,
+            max_length: config.max_length,
+            markup,
+            destruct,
+        };
+    }
+}
+
+#[async_trait]
+impl Block for WindowTitleBlock {
+    fn name(&self) -> &str {
+        return "window_title";
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let Some(title) = self.title.lock().unwrap().clone()
+        else { return Vec::new() };
+
+        let title = match self.max_length {
+            Some(max_length) => text::truncate(&title, max_length),
+            None => title,
+        };
+        // Window titles may contain anything
+        let title = if self.markup == Markup::Pango { text::escape_markup(&title) } else { title };
+        return vec![json!({ "full_text": title })];
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        // The watcher invalidates on focus changes
+        return None;
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use dbus::nonblock::SyncConnection;
+use dbus_tokio::connection::{self, IOResource};
+use tokio::{sync::watch, time::{sleep, Duration}};
+
+use crate::log::{debug, warn};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+type Connect = fn() -> Result<(IOResource<SyncConnection>, Arc<SyncConnection>), dbus::Error>;
+
+/// Handle to a bus connection, which gets replaced when the bus goes away.
+/// `None` while we're not connected.
+#[derive(Clone)]
+pub struct Bus {
+    receiver: watch::Receiver<Option<Arc<SyncConnection>>>,
+}
+
+impl Bus {
+    /// Connects to the session bus in the background, reconnecting with backoff whenever the
+    /// connection is lost
+    pub fn connect() -> Self {
+        // NOTE: I'd rather use `new_session_local` but it leads to errors
+        return Self::spawn(connection::new_session_sync);
+    }
+
+    /// Like `connect` but for the system bus
+    pub fn connect_system() -> Self {
+        return Self::spawn(connection::new_system_sync);
+    }
+
+    fn spawn(connect: Connect) -> Self {
+        let (sender, receiver) = watch::channel(None);
+
+        tokio::spawn(async move {
+            let mut backoff = MIN_BACKOFF;
+            // Without a bus at all, like over SSH, retrying shouldn't fill the log
+            let mut warned = false;
+            loop {
+                match connect() {
+                    Ok((resource, conn)) => {
+                        backoff = MIN_BACKOFF;
+                        warned = false;
+                        sender.send_replace(Some(conn));
+                        let err = resource.await;
+                        warn!("Lost connection to DBus: {}", err);
+                        sender.send_replace(None);
+                    }
+                    Err(err) if warned => debug!("Failed to connect to DBus: {}", err),
+                    Err(err) => {
+                        warn!("Failed to connect to DBus, blocks using it stay empty until it's available: {}", err);
+                        warned = true;
+                    }
+                }
+
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+
+        return Self { receiver };
+    }
+
+    pub fn current(&self) -> Option<Arc<SyncConnection>> {
+        return self.receiver.borrow().clone();
+    }
+
+    /// Like `current` but marks the connection as seen by `changed`
+    pub fn current_and_update(&mut self) -> Option<Arc<SyncConnection>> {
+        return self.receiver.borrow_and_update().clone();
+    }
+
+    /// Waits until we connect, disconnect or reconnect
+    pub async fn changed(&mut self) {
+        if self.receiver.changed().await.is_err() {
+            // The connection task never stops, don't spin if it somehow did
+            std::future::pending::<()>().await;
+        }
+    }
+}
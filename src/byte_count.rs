@@ -1,10 +1,42 @@
-use std::fmt::{self, Write};
+use std::{fmt::{self, Write}, str::FromStr, time::Duration};
 
-pub struct ByteCount { bytes: u64 }
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ByteCount {
+    bytes: u64,
+    si: bool,
+    /// Replaces `B` or `iB` after the prefix
+    unit: Option<&'static str>,
+}
 
 impl From<u64> for ByteCount {
     fn from(value: u64) -> Self {
-        return ByteCount { bytes: value };
+        return ByteCount { bytes: value, si: false, unit: None };
+    }
+}
+
+impl ByteCount {
+    /// Use powers of 1000 (`KB`, `MB`, ...) instead of powers of 1024 (`KiB`, `MiB`, ...)
+    pub fn si(self, si: bool) -> Self {
+        return ByteCount { si, ..self };
+    }
+
+    /// Counts something other than bytes, like `bit` for `Mbit`
+    pub fn unit(self, unit: &'static str) -> Self {
+        return ByteCount { unit: Some(unit), ..self };
+    }
+
+    pub fn bytes(&self) -> u64 {
+        return self.bytes;
+    }
+}
+
+/// Sizes in the config are strings like `"10GiB"`
+impl<'de> Deserialize<'de> for ByteCount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        return source.parse().map_err(de::Error::custom);
     }
 }
 
@@ -12,16 +44,343 @@ impl fmt::Display for ByteCount {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         const UNITS: [char; 7] = ['K', 'M', 'G', 'T', 'P', 'E', 'Z'];
 
-        // we start at kibibytes
-        let mut bytes = self.bytes as f64 / 1024.0;
+        let base = if self.si { 1000.0 } else { 1024.0 };
+
+        // we start at kilobytes and stay at the largest unit once we run out
+        let mut bytes = self.bytes as f64 / base;
         let mut n: usize = 0;
-        while bytes >= 1024.0 || n == UNITS.len() {
-            bytes /= 1024.0;
+        while bytes >= base && n < UNITS.len() - 1 {
+            bytes /= base;
             n += 1;
         }
 
-        bytes.fmt(formatter)?;
-        formatter.write_char(UNITS[n])?;
-        return Ok(());
+        let unit = self.unit.unwrap_or(if self.si { "B" } else { "iB" });
+        let text = match formatter.precision() {
+            Some(precision) => format!("{:.*}{}{}", precision, bytes, UNITS[n], unit),
+            None => format!("{}{}{}", bytes, UNITS[n], unit),
+        };
+        return write_padded(formatter, &text);
+    }
+}
+
+/// Writes `text` padded to the formatter's width, right aligned by default like other numbers
+fn write_padded(formatter: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    let padding = formatter.width()
+        .map(|width| width.saturating_sub(text.chars().count()))
+        .unwrap_or(0);
+    let (before, after) = match formatter.align() {
+        Some(fmt::Alignment::Left) => (0, padding),
+        Some(fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+        Some(fmt::Alignment::Right) | None => (padding, 0),
+    };
+
+    let fill = formatter.fill();
+    for _ in 0..before {
+        formatter.write_char(fill)?;
+    }
+    formatter.write_str(text)?;
+    for _ in 0..after {
+        formatter.write_char(fill)?;
+    }
+    return Ok(());
+}
+
+/// A change in bytes, displayed like a `ByteCount` with a sign in front: `+1.2MiB` or
+/// `-300.0KiB`. No change has no sign.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteDelta(pub i64);
+
+impl fmt::Display for ByteDelta {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let sign = match self.0.signum() {
+            1 => "+",
+            -1 => "-",
+            _ => "",
+        };
+        let magnitude = ByteCount::from(self.0.unsigned_abs());
+        // Width applies to the sign, number and unit together
+        let text = match formatter.precision() {
+            Some(precision) => format!("{}{:.*}", sign, precision, magnitude),
+            None => format!("{}{}", sign, magnitude),
+        };
+        return write_padded(formatter, &text);
+    }
+}
+
+/// Bytes per second, displayed like a `ByteCount` followed by `/s`
+pub struct ByteRate {
+    bytes: ByteCount,
+    /// Displayed in bits rather than bytes, the count stays in bytes
+    bits: bool,
+}
+
+impl ByteRate {
+    /// Rate of `delta` bytes over `elapsed`, zero when no time has passed
+    pub fn new(delta: u64, elapsed: Duration) -> Self {
+        let elapsed = elapsed.as_secs_f64();
+        let bytes = if elapsed <= 0.0 { 0 } else { (delta as f64 / elapsed) as u64 };
+        return ByteRate { bytes: ByteCount::from(bytes), bits: false };
+    }
+
+    pub fn bytes_per_second(&self) -> u64 {
+        return self.bytes.bytes();
+    }
+
+    /// Displayed in bits like network speeds usually are, `Mbit/s` instead of `MiB/s`
+    pub fn bits(self) -> Self {
+        return ByteRate { bits: true, ..self };
+    }
+}
+
+impl fmt::Display for ByteRate {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        // Width and precision apply to the count only
+        if self.bits {
+            ByteCount::from(self.bytes.bytes().saturating_mul(8)).si(true).unit("bit").fmt(formatter)?;
+        } else {
+            self.bytes.fmt(formatter)?;
+        }
+        return formatter.write_str("/s");
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseByteCountError {
+    Empty,
+    InvalidNumber(String),
+    UnknownUnit(String),
+    Overflow,
+}
+
+impl fmt::Display for ParseByteCountError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ParseByteCountError::Empty => write!(formatter, "empty byte count"),
+            ParseByteCountError::InvalidNumber(number) => write!(formatter, "invalid number {:?}", number),
+            ParseByteCountError::UnknownUnit(unit) => write!(formatter, "unknown unit {:?}", unit),
+            ParseByteCountError::Overflow => write!(formatter, "byte count too large"),
+        };
+    }
+}
+
+impl std::error::Error for ParseByteCountError {}
+
+impl FromStr for ByteCount {
+    type Err = ParseByteCountError;
+
+    /// Parses sizes like `512`, `500M`, `10GiB` or `1.5TB`. A unit with `i` or without `B`
+    /// is a power of 1024, a plain `KB`/`MB`/... is a power of 1000.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        const UNITS: [char; 7] = ['K', 'M', 'G', 'T', 'P', 'E', 'Z'];
+
+        let source = source.trim();
+        if source.is_empty() {
+            return Err(ParseByteCountError::Empty);
+        }
+
+        let split = source.find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(source.len());
+        let (number, unit) = source.split_at(split);
+        let unit = unit.trim_start();
+
+        let number: f64 = number.parse()
+            .map_err(|_| ParseByteCountError::InvalidNumber(number.to_string()))?;
+
+        let mut chars = unit.chars();
+        let (exponent, si) = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            None => (0, false),
+            Some('B') if chars.as_str().is_empty() => (0, false),
+            Some(prefix) => {
+                let Some(index) = UNITS.iter().position(|&c| c == prefix)
+                else { return Err(ParseByteCountError::UnknownUnit(unit.to_string())) };
+
+                let si = match chars.as_str() {
+                    "" | "i" | "iB" => false,
+                    "B" => true,
+                    _ => return Err(ParseByteCountError::UnknownUnit(unit.to_string())),
+                };
+                (index as i32 + 1, si)
+            }
+        };
+
+        let base: f64 = if si { 1000.0 } else { 1024.0 };
+        let bytes = (number * base.powi(exponent)).round();
+        if bytes >= u64::MAX as f64 {
+            return Err(ParseByteCountError::Overflow);
+        }
+        return Ok(ByteCount { bytes: bytes as u64, si, unit: None });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positive_delta() {
+        assert_eq!(format!("{:.1}", ByteDelta(1_258_291)), "+1.2MiB");
+        assert_eq!(format!("{}", ByteDelta(512)), "+0.5KiB");
+    }
+
+    #[test]
+    fn negative_delta() {
+        assert_eq!(format!("{:.1}", ByteDelta(-300 * 1024)), "-300.0KiB");
+        assert_eq!(format!("{:.0}", ByteDelta(i64::MIN)), "-8EiB");
+    }
+
+    #[test]
+    fn zero_delta_has_no_sign() {
+        assert_eq!(format!("{:.1}", ByteDelta(0)), "0.0KiB");
+    }
+
+    #[test]
+    fn delta_width_covers_the_sign() {
+        assert_eq!(format!("{:>9.1}", ByteDelta(-2048)), "  -2.0KiB");
+        assert_eq!(format!("{:<9.1}", ByteDelta(2048)), "+2.0KiB  ");
+    }
+
+    #[test]
+    fn zero_bytes() {
+        assert_eq!(format!("{:.2}", ByteCount::from(0)), "0.00KiB");
+    }
+
+    #[test]
+    fn just_under_a_kibibyte() {
+        assert_eq!(format!("{:.2}", ByteCount::from(1023)), "1.00KiB");
+        assert_eq!(format!("{:.1}", ByteCount::from(1023 << 10)), "1023.0KiB");
+    }
+
+    #[test]
+    fn exactly_one_gibibyte() {
+        assert_eq!(format!("{:.2}", ByteCount::from(1 << 30)), "1.00GiB");
+        assert_eq!(format!("{:.2}", ByteCount::from((1 << 30) - (1 << 20))), "1023.00MiB");
+    }
+
+    #[test]
+    fn largest_count_stays_in_range() {
+        assert_eq!(format!("{:.2}", ByteCount::from(u64::MAX)), "16.00EiB");
+        assert_eq!(format!("{:.2}", ByteCount::from(u64::MAX).si(true)), "18.45EB");
+    }
+
+    #[test]
+    fn si_and_binary_units() {
+        assert_eq!(format!("{:.2}", ByteCount::from(1500).si(true)), "1.50KB");
+        assert_eq!(format!("{:.2}", ByteCount::from(1500)), "1.46KiB");
+        assert_eq!(format!("{:.2}", ByteCount::from(1500).si(true).si(false)), "1.46KiB");
+    }
+
+    #[test]
+    fn si_switches_units_at_a_thousand() {
+        assert_eq!(format!("{:.0}", ByteCount::from(999_999).si(true)), "1000KB");
+        assert_eq!(format!("{:.2}", ByteCount::from(1_000_000).si(true)), "1.00MB");
+        assert_eq!(format!("{:.1}", ByteCount::from(2_500_000_000).si(true)), "2.5GB");
+    }
+
+    #[test]
+    fn other_units() {
+        assert_eq!(format!("{:.1}", ByteCount::from(9_600_000).si(true).unit("bit")), "9.6Mbit");
+    }
+
+    #[test]
+    fn width_pads_number_and_unit() {
+        let count = ByteCount::from(1536);
+        assert_eq!(format!("{:>8.1}", count), "  1.5KiB");
+        assert_eq!(format!("{:8.1}", count), "  1.5KiB");
+        assert_eq!(format!("{:<8.1}|", count), "1.5KiB  |");
+        assert_eq!(format!("{:^9.1}|", count), " 1.5KiB  |");
+        assert_eq!(format!("{:*>8.1}", count), "**1.5KiB");
+    }
+
+    #[test]
+    fn width_smaller_than_the_text() {
+        assert_eq!(format!("{:>3.2}", ByteCount::from(5 << 30)), "5.00GiB");
+    }
+
+    #[test]
+    fn precision_without_width() {
+        let count = ByteCount::from(1 << 29);
+        assert_eq!(format!("{:.0}", count), "512MiB");
+        assert_eq!(format!("{:.3}", count), "512.000MiB");
+        assert_eq!(format!("{}", ByteCount::from(1536)), "1.5KiB");
+    }
+
+    fn parse(source: &str) -> Result<u64, ParseByteCountError> {
+        return source.parse::<ByteCount>().map(|count| count.bytes());
+    }
+
+    #[test]
+    fn parses_human_sizes() {
+        assert_eq!(parse("512"), Ok(512));
+        assert_eq!(parse("512B"), Ok(512));
+        assert_eq!(parse("500M"), Ok(500 << 20));
+        assert_eq!(parse("10GiB"), Ok(10 << 30));
+        assert_eq!(parse("1.5TB"), Ok(1_500_000_000_000));
+        assert_eq!(parse(" 2 ki "), Ok(2048));
+        assert_eq!(parse("3kB"), Ok(3000));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for bytes in [1 << 10, 3 << 20, 10 << 30, 1536, 7 << 40] {
+            let shown = format!("{:.2}", ByteCount::from(bytes));
+            assert_eq!(parse(&shown), Ok(bytes), "{}", shown);
+        }
+        let shown = format!("{:.3}", ByteCount::from(1_250_000).si(true));
+        assert_eq!(parse(&shown), Ok(1_250_000), "{}", shown);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse(""), Err(ParseByteCountError::Empty));
+        assert_eq!(parse("   "), Err(ParseByteCountError::Empty));
+        assert_eq!(parse("GiB"), Err(ParseByteCountError::InvalidNumber("".to_string())));
+        assert_eq!(parse("1.2.3G"), Err(ParseByteCountError::InvalidNumber("1.2.3".to_string())));
+        assert_eq!(parse("10XB"), Err(ParseByteCountError::UnknownUnit("XB".to_string())));
+        assert_eq!(parse("10Gb"), Err(ParseByteCountError::UnknownUnit("Gb".to_string())));
+        assert_eq!(parse("-5M"), Err(ParseByteCountError::InvalidNumber("".to_string())));
+        assert_eq!(parse("20ZiB"), Err(ParseByteCountError::Overflow));
+    }
+
+    #[test]
+    fn errors_describe_the_problem() {
+        assert_eq!(parse("10XB").unwrap_err().to_string(), "unknown unit \"XB\"");
+        assert_eq!(parse("").unwrap_err().to_string(), "empty byte count");
+    }
+
+    #[test]
+    fn rate_per_second() {
+        let rate = ByteRate::new(2 << 20, Duration::from_secs(2));
+        assert_eq!(rate.bytes_per_second(), 1 << 20);
+        assert_eq!(format!("{:.2}", rate), "1.00MiB/s");
+    }
+
+    #[test]
+    fn rate_over_a_fraction_of_a_second() {
+        assert_eq!(ByteRate::new(300, Duration::from_millis(100)).bytes_per_second(), 3000);
+    }
+
+    #[test]
+    fn rate_without_elapsed_time_is_zero() {
+        assert_eq!(format!("{:.1}", ByteRate::new(1 << 30, Duration::ZERO)), "0.0KiB/s");
+    }
+
+    #[test]
+    fn rate_width_leaves_out_the_suffix() {
+        assert_eq!(format!("{:>9.1}", ByteRate::new(1536, Duration::from_secs(1))), "   1.5KiB/s");
+    }
+
+    #[test]
+    fn mebibyte_per_second_in_bits() {
+        // 1048576 bytes are 8388608 bits, the bit mode counts in powers of 1000
+        let rate = ByteRate::new(1 << 20, Duration::from_secs(1)).bits();
+        assert_eq!(format!("{:.2}", rate), "8.39Mbit/s");
+        // Only the display is in bits
+        assert_eq!(rate.bytes_per_second(), 1 << 20);
+    }
+
+    #[test]
+    fn slow_and_idle_links_in_bits() {
+        assert_eq!(format!("{:.2}", ByteRate::new(125, Duration::from_secs(1)).bits()), "1.00Kbit/s");
+        assert_eq!(format!("{:.2}", ByteRate::new(0, Duration::from_secs(1)).bits()), "0.00Kbit/s");
     }
 }
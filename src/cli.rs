@@ -0,0 +1,130 @@
+use std::{ffi::OsString, path::PathBuf};
+
+use crate::output::OutputMode;
+
+pub const USAGE: &str = "\
+Usage: i3-status-rs [OPTIONS] [CONFIG]
+
+Options:
+  -c, --config <PATH>         Config file, defaults to $XDG_CONFIG_HOME/i3-status-rs/config.toml
+  -o, --output <i3|waybar>    Bar to write for, overrides `output` from the config
+      --once                  Print a single status line and exit
+  -v, --verbose               Log more, repeat for debug messages. Also set with RUST_LOG,
+                              which takes `module=level` overrides
+  -h, --help                  Print this help";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub output: Option<OutputMode>,
+    pub once: bool,
+    /// Levels to log above the default
+    pub verbose: u8,
+    pub help: bool,
+}
+
+fn parse_output(value: &str) -> Result<OutputMode, String> {
+    return match value {
+        "i3" | "i3bar" => Ok(OutputMode::I3bar),
+        "waybar" => Ok(OutputMode::Waybar),
+        _ => Err(format!("unknown output {:?}, expected i3 or waybar", value)),
+    };
+}
+
+impl Args {
+    /// Parses the arguments after the program name. A bare path is the config, like before
+    /// there were options.
+    pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(text) = arg.to_str()
+            else {
+                parsed.set_config(arg.into())?;
+                continue;
+            };
+
+            // `--option=value` is the same as `--option value`
+            let (option, inline) = match text.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => (option, Some(OsString::from(value))),
+                _ => (text, None),
+            };
+            let mut value = |name: &str| inline.clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} needs a value", name));
+
+            match option {
+                "-c" | "--config" => {
+                    let path = value(option)?;
+                    parsed.set_config(path.into())?;
+                }
+                "-o" | "--output" => {
+                    let mode = value(option)?;
+                    parsed.output = Some(parse_output(&mode.to_string_lossy())?);
+                }
+                "--once" => parsed.once = true,
+                "-v" | "--verbose" => parsed.verbose = parsed.verbose.saturating_add(1),
+                _ if text.len() > 2 && text.strip_prefix('-').is_some_and(|flags| flags.chars().all(|flag| flag == 'v')) => {
+                    let count = u8::try_from(text.len() - 1).unwrap_or(u8::MAX);
+                    parsed.verbose = parsed.verbose.saturating_add(count);
+                }
+                "-h" | "--help" => parsed.help = true,
+                _ if text.starts_with('-') => return Err(format!("unknown option {}", text)),
+                _ => parsed.set_config(arg.into())?,
+            }
+        }
+        return Ok(parsed);
+    }
+
+    fn set_config(&mut self, path: PathBuf) -> Result<(), String> {
+        if self.config.is_some() {
+            return Err("more than one config given".to_string());
+        }
+        self.config = Some(path);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        return Args::parse(args.iter().map(OsString::from));
+    }
+
+    #[test]
+    fn no_args_is_the_default() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.config, None);
+        assert_eq!(args.output, None);
+        assert!(!args.once);
+        assert_eq!(args.verbose, 0);
+    }
+
+    #[test]
+    fn options_and_values() {
+        let args = parse(&["--config=/etc/bar.toml", "-o", "waybar", "--once"]).unwrap();
+        assert_eq!(args.config, Some(PathBuf::from("/etc/bar.toml")));
+        assert_eq!(args.output, Some(OutputMode::Waybar));
+        assert!(args.once);
+
+        assert_eq!(parse(&["bar.toml"]).unwrap().config, Some(PathBuf::from("bar.toml")));
+        assert!(parse(&["-c", "a.toml", "b.toml"]).is_err());
+        assert!(parse(&["--output"]).is_err());
+        assert!(parse(&["--output", "dzen"]).is_err());
+        assert!(parse(&["--loud"]).is_err());
+    }
+
+    #[test]
+    fn verbose_flags_add_up() {
+        assert_eq!(parse(&["-v", "-vv", "--verbose"]).unwrap().verbose, 4);
+    }
+
+    #[test]
+    fn verbose_saturates() {
+        let many = format!("-{}", "v".repeat(300));
+        assert_eq!(parse(&[many.as_str()]).unwrap().verbose, u8::MAX);
+        assert_eq!(parse(&[many.as_str(), "-v", "-vvv"]).unwrap().verbose, u8::MAX);
+    }
+}
@@ -0,0 +1,58 @@
+//! Colors blocks pick by meaning, `Theme::apply` swaps them for the theme's hex colors
+
+pub const GOOD: &str = "good";
+pub const WARNING: &str = "warning";
+pub const CRITICAL: &str = "critical";
+pub const MUTED: &str = "muted";
+/// Highlights, like the played part of a track
+pub const ACCENT: &str = "accent";
+
+/// A color set in the config, `#rrggbb`, `#rrggbbaa` or one of the names above
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct ConfigColor(String);
+
+impl ConfigColor {
+    pub fn as_str(&self) -> &str {
+        return &self.0;
+    }
+}
+
+/// `#rrggbb` or `#rrggbbaa`
+fn is_hex(color: &str) -> bool {
+    return color.strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()));
+}
+
+impl TryFrom<String> for ConfigColor {
+    type Error = String;
+
+    fn try_from(color: String) -> Result<Self, Self::Error> {
+        if [GOOD, WARNING, CRITICAL, MUTED, ACCENT].contains(&color.as_str()) || is_hex(&color) {
+            return Ok(Self(color));
+        }
+        return Err(format!("invalid color \"{}\", expected #rrggbb, #rrggbbaa or a theme color like \"{}\"", color, GOOD));
+    }
+}
+
+/// A `#rrggbb` or `#rrggbbaa` color, what the theme turns the names above into
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct HexColor(String);
+
+impl HexColor {
+    pub fn as_str(&self) -> &str {
+        return &self.0;
+    }
+}
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(color: String) -> Result<Self, Self::Error> {
+        if !is_hex(&color) {
+            return Err(format!("invalid color \"{}\", expected #rrggbb or #rrggbbaa", color));
+        }
+        return Ok(Self(color));
+    }
+}
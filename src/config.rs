@@ -0,0 +1,391 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use serde::Deserialize;
+
+use crate::{
+    block::{Align, Block, Context, Markup, MinWidth},
+    blocks::{
+        battery::{BatteryBlock, BatteryConfig},
+        binding_mode::{BindingModeBlock, BindingModeConfig},
+        bluetooth::{BluetoothBlock, BluetoothConfig},
+        calendar::{CalendarBlock, CalendarConfig},
+        clock::{ClockBlock, ClockConfig},
+        command::{CommandBlock, CommandConfig},
+        cpu::{CpuBlock, CpuConfig},
+        cpu_freq::{CpuFreqBlock, CpuFreqConfig},
+        ddc::{Ddc, DdcConfig},
+        disk::{DiskBlock, DiskConfig},
+        disk_io::{DiskIoBlock, DiskIoConfig},
+        dnd::{DndBlock, DndConfig},
+        fan::{FanBlock, FanConfig},
+        gpu::{GpuBlock, GpuConfig},
+        idle_inhibitor::{IdleInhibitorBlock, IdleInhibitorConfig},
+        keyboard_layout::{KeyboardLayoutBlock, KeyboardLayoutConfig},
+        load::{LoadBlock, LoadConfig},
+        lock_keys::{LockKeysBlock, LockKeysConfig},
+        mail::{MailBlock, MailConfig},
+        memory::{MemoryBlock, MemoryConfig},
+        memory_light::{MemoryLightBlock, Thresholds},
+        mpris::{MprisBlock, MprisConfig},
+        network::{NetworkBlock, NetworkConfig},
+        persistent_command::{PersistentCommandBlock, PersistentCommandConfig},
+        ping::{PingBlock, PingConfig},
+        pressure::{PressureBlock, PressureConfig},
+        price::{PriceBlock, PriceConfig},
+        process::{ProcessBlock, ProcessConfig},
+        public_ip::{PublicIpBlock, PublicIpConfig},
+        redshift::{RedshiftBlock, RedshiftConfig},
+        socket::{Socket, SocketConfig},
+        temperature::{TemperatureBlock, TemperatureConfig},
+        timer::{TimerBlock, TimerConfig},
+        tmpfs::{TmpfsBlock, TmpfsConfig},
+        updates::{UpdatesBlock, UpdatesConfig},
+        volume::{VolumeBlock, VolumeConfig},
+        vpn::{VpnBlock, VpnConfig},
+        weather::{WeatherBlock, WeatherConfig},
+        wifi::{WifiBlock, WifiConfig},
+        window_title::{WindowTitleBlock, WindowTitleConfig},
+    },
+    bus::Bus,
+    color::ConfigColor,
+    decorated::Decorated,
+    icons::IconSet,
+    output::OutputMode,
+    theme::Theme,
+};
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "block", rename_all = "snake_case")]
+pub enum BlockConfig {
+    Mpris(MprisConfig),
+    Ddc(DdcConfig),
+    Socket(SocketConfig),
+    Disk(DiskConfig),
+    Tmpfs(TmpfsConfig),
+    Memory(MemoryConfig),
+    MemoryLight(Thresholds),
+    Cpu(CpuConfig),
+    Battery(BatteryConfig),
+    Network(NetworkConfig),
+    Temperature(TemperatureConfig),
+    Volume(VolumeConfig),
+    Command(CommandConfig),
+    PersistentCommand(PersistentCommandConfig),
+    DiskIo(DiskIoConfig),
+    Load(LoadConfig),
+    KeyboardLayout(KeyboardLayoutConfig),
+    LockKeys(LockKeysConfig),
+    Bluetooth(BluetoothConfig),
+    Wifi(WifiConfig),
+    Updates(UpdatesConfig),
+    Timer(TimerConfig),
+    PublicIp(PublicIpConfig),
+    Ping(PingConfig),
+    Weather(WeatherConfig),
+    IdleInhibitor(IdleInhibitorConfig),
+    Dnd(DndConfig),
+    Mail(MailConfig),
+    Process(ProcessConfig),
+    Gpu(GpuConfig),
+    Fan(FanConfig),
+    CpuFreq(CpuFreqConfig),
+    Calendar(CalendarConfig),
+    Price(PriceConfig),
+    WindowTitle(WindowTitleConfig),
+    BindingMode(BindingModeConfig),
+    Vpn(VpnConfig),
+    Pressure(PressureConfig),
+    Redshift(RedshiftConfig),
+    Clock(ClockConfig),
+}
+
+impl BlockConfig {
+    pub fn build(self, context: &Context) -> Box<dyn Block> {
+        return match self {
+            BlockConfig::Mpris(config) => Box::new(MprisBlock::new(config, context.bus.clone(), context.invalidate.clone(), context.markup, context.icons)),
+            BlockConfig::Ddc(config) => Box::new(Ddc::new(config, context.invalidate.clone(), context.icons)),
+            BlockConfig::Socket(config) => Box::new(Socket::new(config, context.invalidate.clone())),
+            BlockConfig::Disk(config) => Box::new(DiskBlock::new(config)),
+            BlockConfig::Tmpfs(config) => Box::new(TmpfsBlock::new(config)),
+            BlockConfig::Memory(config) => Box::new(MemoryBlock::new(config)),
+            BlockConfig::MemoryLight(config) => Box::new(MemoryLightBlock::new(config)),
+            BlockConfig::Cpu(config) => Box::new(CpuBlock::new(config)),
+            BlockConfig::Battery(config) => Box::new(BatteryBlock::new(config, context.icons)),
+            BlockConfig::Network(config) => Box::new(NetworkBlock::new(config)),
+            BlockConfig::Temperature(config) => Box::new(TemperatureBlock::new(config)),
+            BlockConfig::Volume(config) => Box::new(VolumeBlock::new(config, context.bus.clone(), context.invalidate.clone(), context.icons)),
+            BlockConfig::Command(config) => Box::new(CommandBlock::new(config, context.invalidate.clone())),
+            BlockConfig::PersistentCommand(config) => Box::new(PersistentCommandBlock::new(config, context.invalidate.clone())),
+            BlockConfig::DiskIo(config) => Box::new(DiskIoBlock::new(config)),
+            BlockConfig::Load(config) => Box::new(LoadBlock::new(config)),
+            BlockConfig::KeyboardLayout(config) => Box::new(KeyboardLayoutBlock::new(config, context.invalidate.clone())),
+            BlockConfig::LockKeys(config) => Box::new(LockKeysBlock::new(config, context.invalidate.clone())),
+            BlockConfig::Bluetooth(config) => Box::new(BluetoothBlock::new(config, Bus::connect_system(), context.invalidate.clone(), context.icons)),
+            BlockConfig::Wifi(config) => Box::new(WifiBlock::new(config, context.markup)),
+            BlockConfig::Updates(config) => Box::new(UpdatesBlock::new(config, context.invalidate.clone(), context.icons)),
+            BlockConfig::Timer(config) => Box::new(TimerBlock::new(config, context.icons)),
+            BlockConfig::PublicIp(config) => Box::new(PublicIpBlock::new(config, context.invalidate.clone())),
+            BlockConfig::Ping(config) => Box::new(PingBlock::new(config, context.invalidate.clone(), context.icons)),
+            BlockConfig::Weather(config) => Box::new(WeatherBlock::new(config, context.invalidate.clone())),
+            BlockConfig::IdleInhibitor(config) => Box::new(IdleInhibitorBlock::new(config, context.invalidate.clone(), context.icons)),
+            BlockConfig::Dnd(config) => Box::new(DndBlock::new(config, context.bus.clone(), context.invalidate.clone(), context.icons)),
+            BlockConfig::Mail(config) => Box::new(MailBlock::new(config, context.invalidate.clone(), context.icons)),
+            BlockConfig::Process(config) => Box::new(ProcessBlock::new(config, context.icons)),
+            BlockConfig::Gpu(config) => Box::new(GpuBlock::new(config, context.invalidate.clone())),
+            BlockConfig::Fan(config) => Box::new(FanBlock::new(config)),
+            BlockConfig::CpuFreq(config) => Box::new(CpuFreqBlock::new(config)),
+            BlockConfig::Calendar(config) => Box::new(CalendarBlock::new(config, context.icons)),
+            BlockConfig::Price(config) => Box::new(PriceBlock::new(config, context.invalidate.clone())),
+            BlockConfig::WindowTitle(config) => Box::new(WindowTitleBlock::new(config, context.invalidate.clone(), context.markup)),
+            BlockConfig::BindingMode(config) => Box::new(BindingModeBlock::new(config, context.invalidate.clone(), context.markup)),
+            BlockConfig::Vpn(config) => Box::new(VpnBlock::new(config, context.invalidate.clone(), context.icons)),
+            BlockConfig::Pressure(config) => Box::new(PressureBlock::new(config)),
+            BlockConfig::Redshift(config) => Box::new(RedshiftBlock::new(config, context.invalidate.clone(), context.icons)),
+            BlockConfig::Clock(config) => Box::new(ClockBlock::new(config)),
+        };
+    }
+}
+
+/// Options every block has, next to its `block` type in the config
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct CommonConfig {
+    /// What `toggle_block` refers to the block by, defaults to the block type
+    pub id: Option<String>,
+    pub markup: Markup,
+    /// Applied to every item of the block, replacing what the block picked itself
+    pub min_width: Option<MinWidth>,
+    pub align: Option<Align>,
+    /// Whether i3bar draws a separator after the block
+    pub separator: Option<bool>,
+    /// Pixels between the block and the next one
+    pub separator_block_width: Option<u32>,
+    /// Blocks next to each other with the same group have no separators between them
+    pub group: Option<String>,
+    /// Text color of items that don't pick one themselves
+    pub color: Option<ConfigColor>,
+    pub background: Option<ConfigColor>,
+    pub border: Option<ConfigColor>,
+    /// Pixels of `border` on each side, i3bar draws 1 when only the color is set
+    pub border_top: Option<u32>,
+    pub border_right: Option<u32>,
+    pub border_bottom: Option<u32>,
+    pub border_left: Option<u32>,
+    /// Keep showing the previous output while the numbers in it moved by less than this,
+    /// so a CPU percentage jittering by one doesn't redraw the bar every second
+    pub hysteresis: Option<f64>,
+    /// Left clicks make blocks polling in the background, like `weather` and `updates`,
+    /// poll right away
+    pub refresh_on_click: bool,
+    /// Turn line breaks and tabs in the text into spaces and drop other control characters,
+    /// titles and such come from other programs and could break the bar
+    pub sanitize: bool,
+}
+
+impl Default for CommonConfig {
+    fn default() -> Self {
+        return Self {
+            id: None,
+            markup: Markup::default(),
+            min_width: None,
+            align: None,
+            separator: None,
+            separator_block_width: None,
+            group: None,
+            color: None,
+            background: None,
+            border: None,
+            border_top: None,
+            border_right: None,
+            border_bottom: None,
+            border_left: None,
+            hysteresis: None,
+            refresh_on_click: false,
+            sanitize: true,
+        };
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BlockEntry {
+    #[serde(flatten)]
+    pub common: CommonConfig,
+    #[serde(flatten)]
+    pub block: BlockConfig,
+}
+
+impl From<BlockConfig> for BlockEntry {
+    fn from(block: BlockConfig) -> Self {
+        return Self { common: CommonConfig::default(), block };
+    }
+}
+
+impl BlockEntry {
+    pub fn build(self, context: &Context) -> Box<dyn Block> {
+        let context = Context { markup: self.common.markup, ..context.clone() };
+        return Box::new(Decorated::new(self.block.build(&context), self.common, context.output));
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Id of the block hidden/shown by SIGUSR1, all blocks with that id toggle together
+    pub toggle_block: Option<String>,
+    /// `i3bar` or `waybar`, which also gets tooltips and CSS classes
+    pub output: OutputMode,
+    /// `nerd_font` or `ascii` for fonts without the Nerd Font glyphs
+    pub icons: IconSet,
+    pub theme: Theme,
+    /// Leave out items with an empty `full_text` instead of showing them as gaps
+    pub collapse_empty: bool,
+    #[serde(rename = "block")]
+    pub blocks: Vec<BlockEntry>,
+    /// The `[[block]]` sections as written, telling which blocks changed on a reload
+    #[serde(skip)]
+    pub block_sources: Vec<toml::Value>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let disk = |mount_point: &str, label: &str| BlockConfig::Disk(
+            DiskConfig::new(PathBuf::from(mount_point), Some(label.to_string())));
+
+        return Self {
+            toggle_block: Some("mpris".to_string()),
+            output: OutputMode::default(),
+            icons: IconSet::default(),
+            theme: Theme::default(),
+            collapse_empty: true,
+            blocks: vec![
+                BlockConfig::Mpris(MprisConfig::default()),
+                disk("/", "/"),
+                disk("/home", "/home"),
+                disk("/srv", "HDD"),
+                BlockConfig::Memory(MemoryConfig::default()),
+                BlockConfig::Clock(ClockConfig::default()),
+            ].into_iter().map(BlockEntry::from).collect(),
+            block_sources: Vec::new(),
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        return match self {
+            ConfigError::Io(err) => err.fmt(formatter),
+            ConfigError::Parse(err) => err.fmt(formatter),
+        };
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/i3-status-rs/config.toml`, falling back to `~/.config`
+    pub fn default_path() -> PathBuf {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_default();
+        return config_home.join("i3-status-rs").join("config.toml");
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        let mut config: Self = toml::from_str(source).map_err(ConfigError::Parse)?;
+        let table: toml::Table = toml::from_str(source).map_err(ConfigError::Parse)?;
+        if let Some(toml::Value::Array(blocks)) = table.get("block") {
+            config.block_sources = blocks.clone();
+        }
+        return Ok(config);
+    }
+
+    /// Loads the config at `path`, a missing file gives the default config
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        return match fs::read_to_string(path) {
+            Ok(source) => Self::parse(&source),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(ConfigError::Io(err)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockConfig, Config};
+
+    #[test]
+    fn default_has_the_baseline_blocks() {
+        let config = Config::default();
+        let blocks: Vec<&str> = config.blocks.iter()
+            .map(|entry| match &entry.block {
+                BlockConfig::Mpris(_) => "mpris",
+                BlockConfig::Disk(_) => "disk",
+                BlockConfig::Memory(_) => "memory",
+                BlockConfig::Clock(_) => "clock",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(blocks, ["mpris", "disk", "disk", "disk", "memory", "clock"]);
+    }
+
+    #[test]
+    fn empty_file_is_the_default() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.blocks.len(), Config::default().blocks.len());
+        assert!(config.block_sources.is_empty());
+    }
+
+    #[test]
+    fn blocks_keep_their_order_and_options() {
+        let config = Config::parse(r#"
+            output = "waybar"
+
+            [[block]]
+            block = "clock"
+            format = "%H:%M"
+            align = "center"
+
+            [[block]]
+            block = "disk"
+            mount_point = "/home"
+            label = "home"
+        "#).unwrap();
+
+        assert_eq!(config.blocks.len(), 2);
+        assert_eq!(config.block_sources.len(), 2);
+        let BlockConfig::Clock(clock) = &config.blocks[0].block
+        else { panic!("expected a clock, got {:?}", config.blocks[0].block) };
+        assert_eq!(clock.format, "%H:%M");
+        assert!(config.blocks[0].common.align.is_some());
+
+        let BlockConfig::Disk(disk) = &config.blocks[1].block
+        else { panic!("expected a disk, got {:?}", config.blocks[1].block) };
+        assert_eq!(disk.mount_point.to_str(), Some("/home"));
+        assert_eq!(disk.label.as_deref(), Some("home"));
+    }
+
+    #[test]
+    fn unknown_block_type_is_an_error() {
+        assert!(Config::parse("[[block]]\nblock = \"nonexistent\"\n").is_err());
+    }
+
+    #[test]
+    fn bad_theme_color_fails_to_load() {
+        let path = std::env::temp_dir().join(format!("i3-status-rs-theme-{}.toml", std::process::id()));
+        std::fs::write(&path, "[theme]\nwarning = \"orange\"\n").unwrap();
+        let loaded = Config::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let Err(err) = loaded
+        else { panic!("a theme color that isn't hex loaded") };
+        assert!(err.to_string().contains("invalid color \"orange\""), "{}", err);
+
+        // Short hex colors aren't taken either
+        assert!(Config::parse("[theme]\nforeground = \"#fff\"\n").is_err());
+        assert!(Config::parse("[theme]\nforeground = \"#ffffff\"\nbackground = \"#00000080\"\n").is_ok());
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::{block::{Block, ClickEvent, Markup, MouseButton}, config::CommonConfig, output::OutputMode, text};
+
+/// Applies the options every block has to the items of the block it wraps
+pub struct Decorated {
+    inner: Box<dyn Block>,
+    common: CommonConfig,
+    output: OutputMode,
+    /// Whether the last render had tooltips, i3bar can't show them so a middle click
+    /// swaps them in for the text instead
+    has_tooltip: bool,
+    show_tooltip: bool,
+    /// What the last render returned, kept while `hysteresis` says the change is too small
+    last: Vec<Value>,
+}
+
+/// Whether `new` only differs from `old` by numbers in the text moving less than `threshold`,
+/// anything else changing like a color means the threshold of the block itself was crossed
+fn within_hysteresis(old: &[Value], new: &[Value], threshold: f64) -> bool {
+    if old.len() != new.len() {
+        return false;
+    }
+    return old.iter().zip(new).all(|(old, new)| {
+        let (Value::Object(old), Value::Object(new)) = (old, new)
+        else { return old == new };
+
+        let text_fields = ["full_text", "short_text"];
+        let same_fields = old.len() == new.len() && old.iter()
+            .filter(|(field, _)| !text_fields.contains(&field.as_str()))
+            .all(|(field, value)| new.get(field) == Some(value));
+        same_fields && text_fields.iter().all(|field| match (old.get(*field), new.get(*field)) {
+            (Some(Value::String(old)), Some(Value::String(new))) => text::numbers_within(old, new, threshold),
+            (old, new) => old == new,
+        })
+    });
+}
+
+impl Decorated {
+    pub fn new(inner: Box<dyn Block>, common: CommonConfig, output: OutputMode) -> Self {
+        return Self {
+            inner,
+            common,
+            output,
+            has_tooltip: false,
+            show_tooltip: false,
+            last: Vec::new(),
+        };
+    }
+}
+
+#[async_trait]
+impl Block for Decorated {
+    fn name(&self) -> &str {
+        return self.inner.name();
+    }
+
+    async fn render(&mut self) -> Vec<Value> {
+        let mut items = self.inner.render().await;
+        self.has_tooltip = false;
+        for item in items.iter_mut() {
+            let Value::Object(fields) = item
+            else { continue };
+
+            if self.common.sanitize {
+                for field in ["full_text", "short_text"] {
+                    if let Some(Value::String(text)) = fields.get_mut(field) {
+                        *text = text::sanitize_text(text);
+                    }
+                }
+            }
+
+            if let Some(Value::String(tooltip)) = fields.get("tooltip") {
+                self.has_tooltip = true;
+                if self.show_tooltip && self.output == OutputMode::I3bar {
+                    let tooltip = tooltip.replace('\n', " | ");
+                    fields.insert("full_text".to_string(), json!(tooltip));
+                    fields.remove("short_text");
+                }
+            }
+
+            if let Some(min_width) = &self.common.min_width {
+                fields.insert("min_width".to_string(), min_width.to_json());
+            }
+            if let Some(align) = self.common.align {
+                fields.insert("align".to_string(), json!(align.as_str()));
+            }
+
+            // Blocks coloring their items by state keep doing so
+            for (field, color) in [("color", &self.common.color), ("background", &self.common.background), ("border", &self.common.border)] {
+                if let Some(color) = color {
+                    fields.entry(field).or_insert_with(|| json!(color.as_str()));
+                }
+            }
+            let borders = [
+                ("border_top", self.common.border_top),
+                ("border_right", self.common.border_right),
+                ("border_bottom", self.common.border_bottom),
+                ("border_left", self.common.border_left),
+            ];
+            for (field, width) in borders {
+                if let Some(width) = width {
+                    fields.insert(field.to_string(), json!(width));
+                }
+            }
+
+            if self.common.markup != Markup::None {
+                fields.entry("markup").or_insert_with(|| json!(self.common.markup.as_str()));
+            }
+        }
+
+        // Only the last item borders the next block
+        if let Some(Value::Object(last)) = items.last_mut() {
+            if let Some(separator) = self.common.separator {
+                last.insert("separator".to_string(), json!(separator));
+            }
+            if let Some(width) = self.common.separator_block_width {
+                last.insert("separator_block_width".to_string(), json!(width));
+            }
+        }
+
+        if let Some(threshold) = self.common.hysteresis {
+            if within_hysteresis(&self.last, &items, threshold) {
+                return self.last.clone();
+            }
+            self.last = items.clone();
+        }
+        return items;
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        return self.inner.interval();
+    }
+
+    fn handle_click(&mut self, event: &ClickEvent) {
+        if self.has_tooltip && self.output == OutputMode::I3bar && event.mouse_button() == MouseButton::Middle {
+            self.show_tooltip = !self.show_tooltip;
+            return;
+        }
+        if self.common.refresh_on_click && event.mouse_button() == MouseButton::Left {
+            self.inner.refresh();
+        }
+        self.inner.handle_click(event);
+    }
+
+    fn handle_scroll(&mut self, event: &ClickEvent, steps: i32) {
+        self.inner.handle_scroll(event, steps);
+    }
+
+    fn id(&self) -> &str {
+        return self.common.id.as_deref().unwrap_or_else(|| self.inner.id());
+    }
+
+    fn group(&self) -> Option<&str> {
+        return self.common.group.as_deref();
+    }
+
+    fn refresh(&mut self) {
+        self.inner.refresh();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Always renders the same title
+    struct Title(&'static str);
+
+    #[async_trait]
+    impl Block for Title {
+        fn name(&self) -> &str {
+            return "title";
+        }
+
+        async fn render(&mut self) -> Vec<Value> {
+            return vec![json!({ "full_text": self.0, "short_text": self.0 })];
+        }
+    }
+
+    async fn render(title: &'static str, common: CommonConfig) -> Value {
+        let mut block = Decorated::new(Box::new(Title(title)), common, OutputMode::I3bar);
+        return block.render().await.remove(0);
+    }
+
+    #[tokio::test]
+    async fn control_characters_are_stripped() {
+        let item = render("Line one\nLine two\tTabbed\r\u{7}\u{1b}[0m end", CommonConfig::default()).await;
+        assert_eq!(item["full_text"], "Line one Line two Tabbed [0m end");
+        assert_eq!(item["short_text"], "Line one Line two Tabbed [0m end");
+    }
+
+    #[tokio::test]
+    async fn sanitizing_can_be_turned_off() {
+        let item = render("a\nb", CommonConfig { sanitize: false, ..CommonConfig::default() }).await;
+        assert_eq!(item["full_text"], "a\nb");
+    }
+
+    #[tokio::test]
+    async fn markup_survives() {
+        let item = render("<b>bold</b>\n", CommonConfig { markup: Markup::Pango, ..CommonConfig::default() }).await;
+        assert_eq!(item["full_text"], "<b>bold</b> ");
+        assert_eq!(item["markup"], "pango");
+    }
+}
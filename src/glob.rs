@@ -0,0 +1,47 @@
+use std::{fs, path::PathBuf};
+
+/// Matches a single path component against a pattern with `*` and `?` wildcards
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    return match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => matches(&pattern[1..], name) || (!name.is_empty() && matches(pattern, &name[1..])),
+        (Some('?'), Some(_)) => matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) => p == n && matches(&pattern[1..], &name[1..]),
+        _ => false,
+    };
+}
+
+/// Expands an absolute path pattern like `/sys/class/hwmon/hwmon*/temp1_input`
+/// to the existing paths it matches, sorted
+pub fn resolve(pattern: &str) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from("/")];
+    for component in pattern.split('/').filter(|component| !component.is_empty()) {
+        if !component.contains(['*', '?']) {
+            paths = paths.into_iter()
+                .map(|path| path.join(component))
+                .filter(|path| path.exists())
+                .collect();
+            continue;
+        }
+
+        let pattern: Vec<char> = component.chars().collect();
+        let mut expanded = Vec::new();
+        for path in paths {
+            let Ok(entries) = fs::read_dir(&path)
+            else { continue };
+
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let Ok(name) = entry.file_name().into_string()
+                else { continue };
+
+                let name: Vec<char> = name.chars().collect();
+                if matches(&pattern, &name) {
+                    expanded.push(entry.path());
+                }
+            }
+        }
+        expanded.sort();
+        paths = expanded;
+    }
+    return paths;
+}
@@ -0,0 +1,32 @@
+use std::process::Stdio;
+
+use tokio::process::Command;
+
+use crate::log::warn;
+
+/// Body of a GET request to `url` made with curl, None when it fails or takes longer than
+/// `timeout` seconds. Network errors are expected while offline and aren't logged.
+pub async fn get(url: &str, timeout: u64) -> Option<String> {
+    let body = get_bytes(url, timeout).await?;
+    return Some(String::from_utf8_lossy(&body).into_owned());
+}
+
+/// Like `get` for bodies that aren't text
+pub async fn get_bytes(url: &str, timeout: u64) -> Option<Vec<u8>> {
+    let output = Command::new("curl")
+        .args(["--silent", "--fail", "--location", "--max-time", &timeout.to_string(), url])
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output().await;
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            warn!("Failed to run curl: {}", err);
+            return None;
+        }
+    };
+    if !output.status.success() {
+        return None;
+    }
+    return Some(output.stdout);
+}
@@ -0,0 +1,106 @@
+use std::{env, io, path::PathBuf, process::Stdio};
+
+use serde_json::Value;
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::UnixStream, process::Command};
+
+const MAGIC: &[u8] = b"i3-ipc";
+
+pub const GET_TREE: u32 = 4;
+pub const GET_BINDING_STATE: u32 = 12;
+const SUBSCRIBE: u32 = 2;
+/// Event type of binding mode changes
+pub const MODE_EVENT: u32 = 2;
+/// Event types have the highest bit set, the rest tells which one it is
+const EVENT_BIT: u32 = 1 << 31;
+
+/// Socket of the running i3, or sway which speaks the same protocol
+async fn socket_path() -> Option<PathBuf> {
+    for variable in ["I3SOCK", "SWAYSOCK"] {
+        if let Some(path) = env::var_os(variable).filter(|path| !path.is_empty()) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    for program in ["i3", "sway"] {
+        let output = Command::new(program)
+            .arg("--get-socketpath")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output().await;
+        let Ok(output) = output
+        else { continue };
+
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !path.is_empty() {
+            return Some(PathBuf::from(path));
+        }
+    }
+    return None;
+}
+
+/// A message from i3, the reply to a request or an event
+pub enum Message {
+    Reply(u32, Value),
+    /// Type without the event bit, `0x3` for window events
+    Event(u32, Value),
+}
+
+/// Connection to i3's IPC socket
+pub struct I3Ipc {
+    stream: UnixStream,
+}
+
+impl I3Ipc {
+    pub async fn connect() -> io::Result<Self> {
+        let path = socket_path().await
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "neither i3 nor sway is running"))?;
+        return Ok(Self { stream: UnixStream::connect(path).await? });
+    }
+
+    async fn send(&mut self, kind: u32, payload: &str) -> io::Result<()> {
+        let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        return self.stream.write_all(&message).await;
+    }
+
+    pub async fn read(&mut self) -> io::Result<Message> {
+        let mut header = [0; MAGIC.len() + 8];
+        self.stream.read_exact(&mut header).await?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an i3 IPC message"));
+        }
+        let length = u32::from_ne_bytes(header[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap());
+        let kind = u32::from_ne_bytes(header[MAGIC.len() + 4..].try_into().unwrap());
+
+        let mut payload = vec![0; length as usize];
+        self.stream.read_exact(&mut payload).await?;
+        let payload = serde_json::from_slice(&payload)?;
+        if kind & EVENT_BIT != 0 {
+            return Ok(Message::Event(kind & !EVENT_BIT, payload));
+        }
+        return Ok(Message::Reply(kind, payload));
+    }
+
+    /// Sends a request and waits for its reply, events in between are dropped
+    pub async fn request(&mut self, kind: u32, payload: &str) -> io::Result<Value> {
+        self.send(kind, payload).await?;
+        loop {
+            if let Message::Reply(reply_kind, reply) = self.read().await? {
+                if reply_kind == kind {
+                    return Ok(reply);
+                }
+            }
+        }
+    }
+
+    /// Starts receiving the events named in `events`, like `window` or `mode`
+    pub async fn subscribe(&mut self, events: &[&str]) -> io::Result<()> {
+        let reply = self.request(SUBSCRIBE, &serde_json::to_string(events)?).await?;
+        if reply.get("success").and_then(Value::as_bool) != Some(true) {
+            return Err(io::Error::other("subscribing was refused"));
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,128 @@
+use serde::Deserialize;
+
+/// Glyphs blocks show next to or instead of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    Previous,
+    Play,
+    Pause,
+    Next,
+    Shuffle,
+    Loop,
+    LoopTrack,
+    Muted,
+    NotificationsOn,
+    NotificationsOff,
+    IdleInhibited,
+    IdleAllowed,
+    Charging,
+    PluggedIn,
+    BatteryEmpty,
+    BatteryQuarter,
+    BatteryHalf,
+    BatteryThreeQuarters,
+    BatteryFull,
+    Brightness,
+    Mail,
+    Timer,
+    Updates,
+    Latency,
+    Unreachable,
+    PowerOff,
+    Process,
+    Calendar,
+    PlayerVolume,
+    VpnUp,
+    VpnDown,
+    Day,
+    Night,
+}
+
+/// Which glyphs to use for icons, picked with `icons` in the config
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IconSet {
+    /// Needs a Nerd Font or Font Awesome for the private use area glyphs
+    #[default]
+    #[serde(rename = "nerd_font")]
+    NerdFont,
+    /// Plain text that shows up with any font
+    #[serde(rename = "ascii")]
+    Ascii,
+}
+
+impl IconSet {
+    pub fn get(self, icon: Icon) -> &'static str {
+        return match self {
+            IconSet::NerdFont => match icon {
+                Icon::Previous => "\u{f049}",
+                Icon::Play => "\u{f04b}",
+                Icon::Pause => "\u{f04c}",
+                Icon::Next => "\u{f050}",
+                Icon::Shuffle => "\u{f074}",
+                Icon::Loop => "\u{f01e}",
+                Icon::LoopTrack => "\u{f01e}1",
+                Icon::Muted => "\u{f026}",
+                Icon::NotificationsOn => "\u{f0f3}",
+                Icon::NotificationsOff => "\u{f1f6}",
+                Icon::IdleInhibited => "\u{f06e}",
+                Icon::IdleAllowed => "\u{f070}",
+                Icon::Charging => "\u{f0e7}",
+                Icon::PluggedIn => "\u{f1e6}",
+                Icon::BatteryEmpty => "\u{f244}",
+                Icon::BatteryQuarter => "\u{f243}",
+                Icon::BatteryHalf => "\u{f242}",
+                Icon::BatteryThreeQuarters => "\u{f241}",
+                Icon::BatteryFull => "\u{f240}",
+                Icon::Brightness => "\u{f185}",
+                Icon::Mail => "\u{2709}",
+                Icon::Timer => "\u{23f2}",
+                Icon::Updates => "\u{27f3}",
+                Icon::Latency => "\u{21af}",
+                Icon::Unreachable => "\u{2717}",
+                Icon::PowerOff => "\u{23fb}",
+                Icon::Process => "\u{2699}",
+                Icon::Calendar => "\u{f073}",
+                Icon::PlayerVolume => "\u{266a}",
+                Icon::VpnUp => "\u{2191}",
+                Icon::VpnDown => "\u{2193}",
+                Icon::Day => "\u{f185}",
+                Icon::Night => "\u{f186}",
+            },
+            IconSet::Ascii => match icon {
+                Icon::Previous => "|<",
+                Icon::Play => ">",
+                Icon::Pause => "||",
+                Icon::Next => ">|",
+                Icon::Shuffle => "SHUF",
+                Icon::Loop => "LOOP",
+                Icon::LoopTrack => "LOOP1",
+                Icon::Muted => "MUTE",
+                Icon::NotificationsOn => "NOTIF",
+                Icon::NotificationsOff => "DND",
+                Icon::IdleInhibited => "AWAKE",
+                Icon::IdleAllowed => "IDLE",
+                Icon::Charging => "CHR",
+                Icon::PluggedIn => "AC",
+                Icon::BatteryEmpty
+                | Icon::BatteryQuarter
+                | Icon::BatteryHalf
+                | Icon::BatteryThreeQuarters
+                | Icon::BatteryFull => "BAT",
+                Icon::Brightness => "BRI",
+                Icon::Mail => "MAIL",
+                Icon::Timer => "TIMER",
+                Icon::Updates => "UPD",
+                Icon::Latency => "PING",
+                Icon::Unreachable => "x",
+                Icon::PowerOff => "off",
+                Icon::Process => "PROC",
+                Icon::Calendar => "CAL",
+                Icon::PlayerVolume => "VOL",
+                Icon::VpnUp => "up",
+                Icon::VpnDown => "down",
+                Icon::Day => "day",
+                Icon::Night => "night",
+            },
+        };
+    }
+}
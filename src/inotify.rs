@@ -0,0 +1,77 @@
+use std::{ffi::{CString, OsString}, io, os::{fd::{AsRawFd, FromRawFd, OwnedFd}, unix::ffi::{OsStrExt, OsStringExt}}, path::Path};
+
+use tokio::io::{unix::AsyncFd, Interest};
+
+pub use libc::{IN_CLOSE_WRITE, IN_CREATE, IN_DELETE, IN_MOVED_FROM, IN_MOVED_TO};
+
+pub struct Event {
+    #[allow(dead_code)]
+    pub mask: u32,
+    /// Name of the file inside a watched directory the event is about
+    pub name: Option<OsString>,
+}
+
+/// Filesystem change notifications, read asynchronously
+pub struct Inotify {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl Inotify {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        return Ok(Self { fd: AsyncFd::with_interest(fd, Interest::READABLE)? });
+    }
+
+    /// Reports the events in `mask` for `path`, or for the files in it when it's a directory
+    pub fn watch(&self, path: &Path, mask: u32) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        return Ok(());
+    }
+
+    /// Waits for the next events, returning everything that arrived at once
+    pub async fn read(&mut self) -> io::Result<Vec<Event>> {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let mut guard = self.fd.readable_mut().await?;
+            let result = guard.try_io(|fd| {
+                let read = unsafe { libc::read(fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) };
+                if read < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                return Ok(read as usize);
+            });
+            let Ok(read) = result
+            else { continue };
+
+            return Ok(parse_events(&buffer[..read?]));
+        }
+    }
+}
+
+/// Splits what a read returned into `struct inotify_event`s
+fn parse_events(mut buffer: &[u8]) -> Vec<Event> {
+    const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+    let mut events = Vec::new();
+    while buffer.len() >= HEADER {
+        let header = unsafe { std::ptr::read_unaligned(buffer.as_ptr().cast::<libc::inotify_event>()) };
+        let end = (HEADER + header.len as usize).min(buffer.len());
+        // The name is padded with NULs
+        let name: Vec<u8> = buffer[HEADER..end].iter().copied().take_while(|&byte| byte != 0).collect();
+        events.push(Event {
+            mask: header.mask,
+            name: (!name.is_empty()).then(|| OsString::from_vec(name)),
+        });
+        buffer = &buffer[end..];
+    }
+    return events;
+}
@@ -0,0 +1,180 @@
+use std::{fmt, sync::{atomic::{AtomicU8, Ordering}, OnceLock}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    const ALL: [Level; 4] = [Level::Error, Level::Warn, Level::Info, Level::Debug];
+
+    fn label(self) -> &'static str {
+        return match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        };
+    }
+}
+
+/// Most verbose level any target gets printed at, 0 prints nothing
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+static DIRECTIVES: OnceLock<Vec<Directive>> = OnceLock::new();
+
+/// One comma separated part of `RUST_LOG`, a bare level has no target
+#[derive(Debug, PartialEq)]
+struct Directive {
+    target: Option<String>,
+    level: u8,
+}
+
+/// `off` is 0, `trace` is the same as `debug`
+fn parse_level(name: &str) -> Option<u8> {
+    let name = name.trim().to_ascii_lowercase();
+    if name == "off" {
+        return Some(0);
+    }
+    if name == "trace" {
+        return Some(Level::Debug as u8);
+    }
+    return Level::ALL.iter()
+        .find(|level| level.label() == name)
+        .map(|level| *level as u8);
+}
+
+/// Directives like env_logger takes them, `warn,i3_status_rs::mpris=debug`. A target without
+/// a level logs everything, parts with an unknown level are skipped.
+fn parse_directives(spec: &str) -> Vec<Directive> {
+    return spec.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| match part.split_once('=') {
+            Some((target, level)) => Some(Directive { target: Some(target.trim().to_string()), level: parse_level(level)? }),
+            None => Some(match parse_level(part) {
+                Some(level) => Directive { target: None, level },
+                None => Directive { target: Some(part.to_string()), level: Level::Debug as u8 },
+            }),
+        })
+        .collect();
+}
+
+/// Level of the most specific directive for the module path `target`
+fn level_for(directives: &[Directive], target: &str) -> u8 {
+    let matching = directives.iter()
+        .filter(|directive| match &directive.target {
+            Some(prefix) => target == prefix || target.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with("::")),
+            None => true,
+        })
+        .max_by_key(|directive| directive.target.as_ref().map_or(0, |prefix| prefix.len() + 1));
+    return matching.map_or(Level::Warn as u8, |directive| directive.level);
+}
+
+/// Picks the verbosity from `RUST_LOG`, a level (`off`, `error`, `warn`, `info` or `debug`)
+/// and `module::path=level` overrides. Every `verbose` step makes all of them one level louder.
+pub fn init(verbose: u8) {
+    let mut directives = parse_directives(&std::env::var("RUST_LOG").unwrap_or_default());
+    if !directives.iter().any(|directive| directive.target.is_none()) {
+        directives.push(Directive { target: None, level: Level::Warn as u8 });
+    }
+    for directive in &mut directives {
+        directive.level = directive.level.saturating_add(verbose).min(Level::Debug as u8);
+    }
+    let max = directives.iter().map(|directive| directive.level).max().unwrap_or(0);
+    MAX_LEVEL.store(max, Ordering::Relaxed);
+    let _ = DIRECTIVES.set(directives);
+}
+
+/// Whether `level` messages from the module `target` get printed
+pub fn enabled(level: Level, target: &str) -> bool {
+    if level as u8 > MAX_LEVEL.load(Ordering::Relaxed) {
+        return false;
+    }
+    return match DIRECTIVES.get() {
+        Some(directives) => level as u8 <= level_for(directives, target),
+        None => true,
+    };
+}
+
+/// Used by the macros, which skip formatting when `level` is disabled
+pub fn write(level: Level, message: fmt::Arguments) {
+    eprintln!("{}: {}", level.label(), message);
+}
+
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::log::enabled($level, module_path!()) {
+            $crate::log::write($level, format_args!($($arg)*));
+        }
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Error, $($arg)*) };
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Warn, $($arg)*) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Info, $($arg)*) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Debug, $($arg)*) };
+}
+
+#[allow(unused_imports)]
+// A plain `warn` is ambiguous with the builtin attribute, only the renamed import works
+pub(crate) use {debug, error, info, log, warning as warn};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(directives: &str, target: &str) -> u8 {
+        return level_for(&parse_directives(directives), target);
+    }
+
+    #[test]
+    fn bare_levels_apply_everywhere() {
+        assert_eq!(level("info", "i3_status_rs::blocks::cpu"), Level::Info as u8);
+        assert_eq!(level("off", "i3_status_rs"), 0);
+        assert_eq!(level("TRACE", "i3_status_rs"), Level::Debug as u8);
+        assert_eq!(level("", "i3_status_rs"), Level::Warn as u8);
+    }
+
+    #[test]
+    fn targets_override_the_default() {
+        let spec = "error,i3_status_rs::mpris=debug";
+        assert_eq!(level(spec, "i3_status_rs::mpris"), Level::Debug as u8);
+        assert_eq!(level(spec, "i3_status_rs::blocks::mpris"), Level::Error as u8);
+        assert_eq!(level(spec, "i3_status_rs"), Level::Error as u8);
+        // Only whole path segments match
+        assert_eq!(level(spec, "i3_status_rs::mpris_extra"), Level::Error as u8);
+    }
+
+    #[test]
+    fn longest_target_wins() {
+        let spec = "i3_status_rs::blocks::ddc=debug,i3_status_rs=info,i3_status_rs::blocks=off";
+        assert_eq!(level(spec, "i3_status_rs::blocks::ddc"), Level::Debug as u8);
+        assert_eq!(level(spec, "i3_status_rs::blocks::cpu"), 0);
+        assert_eq!(level(spec, "i3_status_rs::main"), Level::Info as u8);
+        // Without a bare level other crates stay at warn
+        assert_eq!(level(spec, "other"), Level::Warn as u8);
+    }
+
+    #[test]
+    fn target_without_level_logs_everything() {
+        assert_eq!(level("i3_status_rs::scheduler", "i3_status_rs::scheduler"), Level::Debug as u8);
+    }
+
+    #[test]
+    fn unknown_levels_are_skipped() {
+        assert_eq!(parse_directives("i3_status_rs=loud, ,warn"), vec![Directive { target: None, level: Level::Warn as u8 }]);
+    }
+}
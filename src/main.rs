@@ -1,153 +1,328 @@
+#![allow(clippy::needless_return)]
+
+mod album_art;
+mod block;
+mod blocks;
+mod bus;
 mod byte_count;
+mod cli;
+mod color;
+mod config;
+mod decorated;
+mod glob;
+mod http;
+mod i3ipc;
+mod icons;
+mod inotify;
+mod log;
 mod mpris;
+mod output;
+mod reload;
+mod scheduler;
+mod sparkline;
+mod statvfs;
+mod sysfs;
+mod template;
+mod text;
+mod theme;
+mod toggle;
+
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::{time::{sleep, sleep_until, Duration, Instant}, sync::{Notify, mpsc}, io::{BufReader, stdin, AsyncBufReadExt}, signal::unix::{signal, SignalKind}};
+
+use crate::{block::{ClickEvent, Context, Markup}, bus::Bus, cli::Args, config::Config, log::{debug, error, warn}, output::OutputMode, scheduler::Scheduler, theme::Theme, toggle::Toggles};
 
-use std::{cmp::min, sync::Arc};
-
-use chrono::Timelike;
-use dbus_tokio::connection;
-use serde::Deserialize;
-use serde_json::json;
-use tokio::{time::{Instant, Duration, timeout_at}, sync::Notify, io::{BufReader, stdin, AsyncBufReadExt}};
-use sysinfo::{System, SystemExt, DiskExt};
-
-use crate::{byte_count::ByteCount, mpris::Mpris};
-
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
-struct ClickEvent {
-    name: Option<String>,
-    instance: Option<String>,
-    x: i32,
-    y: i32,
-    button: i32,
-    relative_x: i32,
-    relative_y: i32,
-    width: i32,
-    height: i32,
+/// With `--once` the blocks get this long without invalidating to fill in their state
+const ONCE_QUIET: Duration = Duration::from_millis(250);
+/// Upper bound for `--once`, a block that keeps invalidating doesn't hold us up forever
+const ONCE_LIMIT: Duration = Duration::from_secs(2);
+
+/// Waits until `invalidate` stays quiet for `ONCE_QUIET`. Background watchers like MPRIS
+/// fetch their initial state right after starting and invalidate once they have it.
+async fn settle(invalidate: &Notify) {
+    let deadline = Instant::now() + ONCE_LIMIT;
+    loop {
+        tokio::select! {
+            _ = invalidate.notified() => {}
+            _ = sleep(ONCE_QUIET) => return,
+            _ = sleep_until(deadline) => return,
+        }
+    }
+}
+
+/// The array printed for one update from the items the blocks rendered
+fn frame(mut items: Vec<Value>, config_error: bool, theme: &Theme, mode: OutputMode) -> Value {
+    if config_error {
+        items.insert(0, json!({ "name": "config", "full_text": "CONFIG ERROR", "color": color::CRITICAL }));
+    }
+    theme.apply(&mut items);
+    output::adapt(&mut items, mode);
+    return Value::Array(items);
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
+    let args = match Args::parse(std::env::args_os().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    log::init(args.verbose);
+    block::install_panic_hook();
+    if args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    let config_path = args.config.clone()
+        .unwrap_or_else(Config::default_path);
+    let load = || Config::load(&config_path).map(|mut config| {
+        if let Some(output) = args.output {
+            config.output = output;
+        }
+        config
+    });
+    let mut config = match load() {
+        Ok(config) => config,
+        Err(err) => {
+            error!("Failed to load {}: {}", config_path.display(), err);
+            std::process::exit(1);
+        }
+    };
+
     println!("{}\n[", json!({
         "version": 1,
         "stop_signal": 0,
         "click_events": true,
     }));
 
-    // NOTE: I'd rather use `new_session_local` but it leads to errors
-    let (resource, conn) = connection::new_session_sync().unwrap();
+    let invalidate = Arc::new(Notify::new());
 
-    tokio::spawn(async {
-        let err = resource.await;
-        panic!("Lost connection to DBus: {}", err);
-    });
+    let mut context = Context {
+        bus: Bus::connect(),
+        invalidate: invalidate.clone(),
+        markup: Markup::None,
+        output: config.output,
+        icons: config.icons,
+    };
+    let (mut sources, blocks): (Vec<_>, Vec<_>) = reload::build_blocks(&mut config, &context, Vec::new())
+        .into_iter()
+        .unzip();
 
-    let invalidate = Arc::new(Notify::new());
+    // `pkill -USR1 i3-status-rs` hides/shows the configured block
+    let toggles = Arc::new(Toggles::new(invalidate.clone()));
+    toggles.set_signal_target(config.toggle_block.clone());
+    toggles.listen(SignalKind::user_defined1());
 
-    let mpris = Arc::new(Mpris::new(conn.clone(), "spotify", invalidate.clone()));
+    let reload = Arc::new(Notify::new());
+    if !args.once {
+        reload::watch(&config_path, reload.clone());
+    }
+    // Set while the config on disk is broken and we're running the previous one
+    let mut config_error = false;
 
-    let mpris2 = mpris.clone();
-    tokio::spawn(async {
-        let mpris = mpris2;
+    let (click_sender, mut clicks) = mpsc::unbounded_channel::<ClickEvent>();
+    tokio::spawn(async move {
         let reader = BufReader::new(stdin());
         let mut lines = reader.lines();
-        // ignore first line
-        lines.next_line().await.unwrap();
-        while let Some(line) = lines.next_line().await.unwrap() {
-            let line = line.strip_prefix(',').unwrap_or(line.as_str());
-            let event: ClickEvent = serde_json::from_str(line).unwrap();
-            //eprintln!("{:?}", event);
-            if !event.name.is_some() {
-                continue;
-            }
-            match event.name.unwrap().as_str() {
-                "mpris-play" => if event.button == 1 { mpris.play() },
-                "mpris-pause" => if event.button == 1 { mpris.pause() },
-                "mpris-previous" => if event.button == 1 { mpris.previous() },
-                "mpris-next" => if event.button == 1 { mpris.next() },
-                _ => {}
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                // i3bar went away, there won't be any more clicks
+                Ok(None) => break,
+                Err(err) => {
+                    warn!("Failed to read click events: {}", err);
+                    break;
+                }
+            };
+            let event = match ClickEvent::parse_line(&line) {
+                Ok(Some(event)) => event,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("Ignoring malformed click event {:?}: {}", line, err);
+                    continue;
+                }
+            };
+            debug!("Click {:?}", event);
+            if click_sender.send(event).is_err() {
+                break;
             }
         }
     });
 
-    let mut sys = System::new();
+    // i3 stops us with SIGTERM, SIGINT is for running from a terminal
+    let mut terminate = signal(SignalKind::terminate()).unwrap();
+    let mut interrupt = signal(SignalKind::interrupt()).unwrap();
 
+    let mut scheduler = Scheduler::new(blocks, config.collapse_empty);
+    let mut force = true;
+    if args.once {
+        settle(&invalidate).await;
+    }
+    // i3bar redraws on every line, one that didn't change is left out
+    let mut last_frame = None;
     loop {
-        let current_track;
-        let playing;
-        {
-            let state = mpris.state();
-            if state.title.is_empty() {
-                current_track = "".to_string();
-            } else {
-                current_track = format!("{} - {}", state.artists.join(" - "), state.title.clone());
-            }
-            playing = state.playing;
+        let items = scheduler.render(&toggles, force).await;
+        let frame = frame(items, config_error, &config.theme, config.output);
+        if last_frame.as_ref() != Some(&frame) {
+            println!("{},", frame);
+            last_frame = Some(frame);
+        }
+        if args.once {
+            break;
         }
 
-        sys.refresh_disks_list();
-        sys.refresh_disks();
-
-        let disk_root = sys.disks().iter()
-            .find(|&val| val.mount_point().as_os_str() == "/")
-            .and_then(|disk| Some(format!("{:.2}", ByteCount::from(disk.available_space()))));
-
-        let disk_home = sys.disks().iter()
-            .find(|&val| val.mount_point().as_os_str() == "/home")
-            .and_then(|disk| Some(format!("{:.2}", ByteCount::from(disk.available_space()))));
-
-        let disk_hdd = sys.disks().iter()
-            .find(|&val| val.mount_point().as_os_str() == "/srv")
-            .and_then(|disk| Some(format!("{:.2}", ByteCount::from(disk.available_space()))));
-
-        sys.refresh_memory();
-        let memory = format!("M {:.2} S {:.2}",
-            ByteCount::from(sys.available_memory()),
-            ByteCount::from(sys.free_swap()));
-
-        let now_monotonic = Instant::now();
-        let now_wall = chrono::Local::now();
-
-        println!("{},", json!([
-            {
-                "full_text": current_track,
-                "separator": false,
-            },
-            {
-                "full_text": if current_track.is_empty() { "" } else { "\u{f049}" },
-                "name": "mpris-previous",
-                "separator": false,
-            },
-            {
-                "full_text": if current_track.is_empty() { "" } else
-                    if playing { "\u{f04c}" } else { "\u{f04b}" },
-                "name": if playing { "mpris-pause" } else { "mpris-play" },
-                "separator": false,
-            },
-            {
-                "full_text": if current_track.is_empty() { "" } else { "\u{f050}" },
-                "name": "mpris-next",
-            },
-            {
-                "full_text": format!("/ {}", disk_root.unwrap_or("ERROR".to_string())),
-            },
-            {
-                "full_text": format!("/home {}", disk_home.unwrap_or("ERROR".to_string())),
-            },
-            {
-                "full_text": format!("HDD {}", disk_hdd.unwrap_or("ERROR".to_string())),
-            },
-            {
-                "full_text": memory,
-            },
-            {
-                "full_text": now_wall.format("%a %d.%m.%Y %H:%M").to_string(),
+        let wake_at = scheduler.next_deadline(&toggles);
+        force = tokio::select! {
+            _ = async { match wake_at {
+                Some(wake_at) => sleep_until(wake_at).await,
+                None => std::future::pending().await,
+            } } => false,
+            _ = invalidate.notified() => true,
+            _ = reload.notified() => {
+                match load() {
+                    Ok(mut new_config) => {
+                        // Blocks render differently for another bar or icons, so none of them are kept
+                        let previous = if new_config.output == config.output && new_config.icons == config.icons {
+                            sources.drain(..).zip(scheduler.into_blocks()).collect()
+                        } else {
+                            Vec::new()
+                        };
+                        context.output = new_config.output;
+                        context.icons = new_config.icons;
+                        let blocks;
+                        (sources, blocks) = reload::build_blocks(&mut new_config, &context, previous)
+                            .into_iter()
+                            .unzip();
+                        scheduler = Scheduler::new(blocks, new_config.collapse_empty);
+                        toggles.set_signal_target(new_config.toggle_block.clone());
+                        config = new_config;
+                        config_error = false;
+                    }
+                    Err(err) => {
+                        error!("Failed to reload {}, keeping the previous config: {}", config_path.display(), err);
+                        config_error = true;
+                    }
+                }
+                true
+            }
+            Some(event) = clicks.recv() => {
+                scheduler.route_click(&event);
+                false
             }
+            _ = terminate.recv() => break,
+            _ = interrupt.recv() => break,
+        };
+    }
+
+    // Stops the blocks' background watchers
+    drop(scheduler);
+
+    // Close the infinite array so what we printed is valid JSON
+    println!("[]\n]");
+    // The runtime would wait for the blocking read of stdin, which only ends once i3bar
+    // closes it
+    std::process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use async_trait::async_trait;
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+    use tokio::sync::Notify;
+
+    use super::frame;
+    use crate::{
+        block::{Block, Markup},
+        blocks::{
+            clock::{ClockBlock, ClockConfig},
+            disk::{DiskBlock, DiskConfig},
+            memory::{MemoryBlock, MemoryConfig, MemoryStats},
+            mpris::{MprisBlock, MprisConfig},
+        },
+        bus::Bus,
+        icons::IconSet,
+        mpris::PlayerState,
+        output::OutputMode,
+        scheduler::Scheduler,
+        theme::Theme,
+        toggle::Toggles,
+    };
+
+    /// Stands in for a block, rendering what the real one rendered from injected data
+    struct Recorded {
+        name: &'static str,
+        items: Vec<Value>,
+    }
+
+    #[async_trait]
+    impl Block for Recorded {
+        fn name(&self) -> &str {
+            return self.name;
+        }
+
+        async fn render(&mut self) -> Vec<Value> {
+            return self.items.clone();
+        }
+    }
+
+    const GIB: u64 = 1 << 30;
+
+    #[tokio::test]
+    async fn frame_from_fixed_data() {
+        let clock = ClockBlock::new(ClockConfig { timezone: Some(chrono_tz::UTC), ..ClockConfig::default() })
+            .render_at(Utc.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap());
+
+        let disk = DiskBlock::new(DiskConfig::new(PathBuf::from("/"), Some("/".to_string())))
+            .render_space("/", 5 * GIB, 100 * GIB, "/: 95.0GiB of 100.0GiB used (95%)");
+
+        let memory = MemoryBlock::new(MemoryConfig::default()).render_stats(MemoryStats {
+            total: 16 * GIB,
+            available: 4 * GIB,
+            free: 2 * GIB,
+            total_swap: 8 * GIB,
+            used_swap: GIB,
+            free_swap: 7 * GIB,
+        });
+
+        let mut state = PlayerState::default();
+        state.title = "Title".to_string();
+        state.artists = vec!["Artist".to_string()];
+        state.can_control = false;
+        let mpris = MprisBlock::new(MprisConfig::default(), Bus::connect(), Arc::new(Notify::new()), Markup::None, IconSet::Ascii)
+            .render_state(&state);
+
+        let blocks: Vec<Box<dyn Block>> = [("mpris", mpris), ("disk", disk), ("memory", memory), ("clock", clock)]
+            .into_iter()
+            .map(|(name, items)| Box::new(Recorded { name, items }) as Box<dyn Block>)
+            .collect();
+        let mut scheduler = Scheduler::new(blocks, true);
+        let toggles = Toggles::new(Arc::new(Notify::new()));
+        let items = scheduler.render(&toggles, true).await;
+
+        // i3bar gets no tooltips, and the theme turned the disk's warning into a hex color
+        assert_eq!(frame(items, false, &Theme::default(), OutputMode::I3bar), json!([
+            { "name": "mpris", "instance": "0", "full_text": "Artist - Title", "separator": false },
+            { "name": "mpris", "instance": "0", "full_text": "0:00" },
+            { "name": "disk", "instance": "1", "full_text": "/ 5.00GiB", "short_text": "5.00GiB", "color": "#ffff00" },
+            { "name": "memory", "instance": "2", "full_text": "M 4.00GiB (75%) S 7.00GiB" },
+            { "name": "clock", "instance": "3", "full_text": "Sat 09.03.2024 14:05", "short_text": "14:05" },
         ]));
+    }
 
-        let next_minute_ms = 60000 - 1000 * now_wall.second() - now_wall.timestamp_subsec_millis();
-        let wait_ms = min(2000, next_minute_ms);
-        let wake_at = now_monotonic + Duration::from_millis(wait_ms.into());
-        let _ = timeout_at(wake_at, invalidate.notified()).await;
+    #[test]
+    fn config_error_goes_first() {
+        let items = vec![json!({ "name": "clock", "instance": "0", "full_text": "14:05" })];
+        assert_eq!(frame(items, true, &Theme::default(), OutputMode::I3bar), json!([
+            { "name": "config", "full_text": "CONFIG ERROR", "color": "#ff0000" },
+            { "name": "clock", "instance": "0", "full_text": "14:05" },
+        ]));
     }
 }
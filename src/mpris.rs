@@ -1,223 +1,530 @@
-use std::{sync::{Arc, Mutex, MutexGuard}, time::Duration};
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
 
-use dbus::{nonblock::{Proxy, SyncConnection, MethodReply, MsgMatch}, arg::{ReadAll, AppendAll, self, RefArg}, message::MatchRule};
+use dbus::{nonblock::{Proxy, SyncConnection, MethodReply, MsgMatch}, arg::{ReadAll, AppendAll, self, RefArg}, message::MatchRule, Message};
 use tokio::sync::Notify;
 
+use crate::{bus::Bus, log::{debug, warn}};
+
+#[derive(Clone)]
 pub struct PlayerState {
-    invalidate: Arc<Notify>,
     pub playing: bool,
     pub title: String,
     pub artists: Vec<String>,
+    pub album: String,
+    pub track_number: Option<u32>,
+    pub length: Option<Duration>,
+    /// Usually a `file://` or `http(s)://` URL of the cover
+    pub art_url: Option<String>,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_control: bool,
+    pub can_play: bool,
+    pub can_pause: bool,
+    /// None when the player doesn't have the property
+    pub shuffle: Option<bool>,
+    /// `None`, `Track` or `Playlist`, None when the player doesn't have the property
+    pub loop_status: Option<String>,
+    /// 0.0 to 1.0, None when the player doesn't have the property
+    pub volume: Option<f64>,
+    // Last known position and when it was known, Position doesn't emit change signals
+    position: Duration,
+    position_updated: Instant,
 }
 
-impl PlayerState {
-    fn new(invalidate: Arc<Notify>) -> Self {
+impl Default for PlayerState {
+    fn default() -> Self {
         return Self {
-            invalidate,
             playing: false,
             title: "".to_string(),
             artists: Vec::new(),
+            album: "".to_string(),
+            track_number: None,
+            length: None,
+            art_url: None,
+            // Assume the buttons work until the player says otherwise
+            can_go_next: true,
+            can_go_previous: true,
+            can_control: true,
+            can_play: true,
+            can_pause: true,
+            shuffle: None,
+            loop_status: None,
+            volume: None,
+            position: Duration::ZERO,
+            position_updated: Instant::now(),
         };
     }
+}
 
-    fn name_lost(&mut self) {
-        self.playing = false;
-        self.title = "".to_string();
-        self.artists.clear();
-        self.invalidate.notify_one();
-    }
-
-    fn update_metadata(&mut self, metadata: Box<dyn RefArg>) {
-        fn extract_value_from_variant(variant: &dyn RefArg) -> Option<&dyn RefArg> {
-            return variant.as_iter()?.next();
-        }
+/// The `Metadata` fields we use, `None` for the ones the player left out. Title and artists
+/// only change when sent, the rest is cleared when missing.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Metadata {
+    /// `xesam:title`
+    title: Option<String>,
+    /// `xesam:artist`, the entries that are strings
+    artists: Option<Vec<String>>,
+    /// `xesam:album`
+    album: Option<String>,
+    /// `xesam:trackNumber`, only when positive
+    track_number: Option<u32>,
+    /// `mpris:length`, only when positive
+    length: Option<Duration>,
+    /// `mpris:artUrl`
+    art_url: Option<String>,
+}
 
-        let Some(mut iter) = metadata.as_iter()
-        else { return };
+/// A signed or unsigned integer, players disagree on which one to send
+fn integer(value: &dyn RefArg) -> Option<i64> {
+    return value.as_i64()
+        .or_else(|| value.as_u64().map(|number| number as i64));
+}
 
+impl Metadata {
+    /// Reads the `a{sv}` map of the `Metadata` property, `None` when it isn't one
+    fn parse(metadata: &dyn RefArg) -> Option<Self> {
+        let mut entries = Vec::new();
+        let mut iter = metadata.as_iter()?;
         while let Some(key) = iter.next() {
-            let Some(value) = iter.next().and_then(extract_value_from_variant)
+            // Values come wrapped in a variant
+            let Some(value) = iter.next().and_then(|variant| variant.as_iter()?.next())
             else { break };
 
-            let Some(key) = key.as_str()
-            else { continue };
+            if let Some(key) = key.as_str() {
+                entries.push((key, value));
+            }
+        }
+        return Some(Self::from_entries(entries));
+    }
 
+    fn from_entries<'a>(entries: impl IntoIterator<Item = (&'a str, &'a dyn RefArg)>) -> Self {
+        let mut metadata = Self::default();
+        for (key, value) in entries {
             match key {
                 "xesam:title" => {
-                    self.title = value.as_str().unwrap_or("").to_string();
+                    metadata.title = Some(value.as_str().unwrap_or("").to_string());
                 }
                 "xesam:artist" => {
-                    self.artists.clear();
-
-                    let Some(iter) = value.as_iter()
-                    else { continue };
-
-                    for artist in iter {
-                        if let Some(artist) = artist.as_str() {
-                            self.artists.push(artist.to_string());
-                        }
-                    }
+                    // Meant to be a list, some players send a single string instead
+                    metadata.artists = Some(match value.as_str() {
+                        Some(artist) => vec![artist.to_string()],
+                        None => value.as_iter()
+                            .map(|iter| iter.filter_map(|artist| artist.as_str().map(str::to_string)).collect())
+                            .unwrap_or_default(),
+                    });
+                }
+                "xesam:album" => {
+                    metadata.album = Some(value.as_str().unwrap_or("").to_string());
+                }
+                "xesam:trackNumber" => {
+                    metadata.track_number = integer(value)
+                        .filter(|&number| number > 0)
+                        .map(|number| number as u32);
+                }
+                "mpris:artUrl" => {
+                    metadata.art_url = value.as_str().filter(|url| !url.is_empty()).map(str::to_string);
+                }
+                "mpris:length" => {
+                    // Microseconds
+                    metadata.length = integer(value)
+                        .filter(|&length| length > 0)
+                        .map(|length| Duration::from_micros(length as u64));
                 }
                 _ => {
-                    //eprintln!("{:?} -> {:?}", key, value);
+                    debug!("Unhandled metadata {:?} -> {:?}", key, value);
                 }
             }
         }
-        self.invalidate.notify_one();
+        return metadata;
+    }
+}
+
+/// A `Can*` property, anything that isn't a boolean counts as supported
+fn capability(value: &dyn RefArg) -> bool {
+    return value.as_u64().map(|value| value != 0).unwrap_or(true);
+}
+
+impl PlayerState {
+    /// Playback position extrapolated from the last known position
+    pub fn position(&self) -> Duration {
+        let position = if self.playing {
+            self.position + self.position_updated.elapsed()
+        } else {
+            self.position
+        };
+        return match self.length {
+            Some(length) => position.min(length),
+            None => position,
+        };
+    }
+
+    fn set_position(&mut self, position: Duration) {
+        self.position = position;
+        self.position_updated = Instant::now();
+    }
+
+    fn set_playing(&mut self, playing: bool) {
+        self.set_position(self.position());
+        self.playing = playing;
+    }
+
+    fn update_metadata(&mut self, metadata: Metadata) {
+        // Players leave out what they don't know, which mustn't keep the previous track's
+        if let Some(title) = metadata.title {
+            self.title = title;
+        }
+        if let Some(artists) = metadata.artists {
+            self.artists = artists;
+        }
+        self.album = metadata.album.unwrap_or_default();
+        self.track_number = metadata.track_number;
+        self.length = metadata.length;
+        self.art_url = metadata.art_url;
     }
 
     fn update(&mut self, props: arg::PropMap) {
         for (field, value) in props {
             match field.as_str() {
                 "Metadata" => {
-                    self.update_metadata(value.0);
+                    if let Some(metadata) = Metadata::parse(&value.0) {
+                        self.update_metadata(metadata);
+                    }
+                },
+                "Position" => {
+                    if let Some(position) = value.as_i64() {
+                        self.set_position(Duration::from_micros(position.max(0) as u64));
+                    }
                 },
+                "CanGoNext" => self.can_go_next = capability(&value),
+                "CanGoPrevious" => self.can_go_previous = capability(&value),
+                "CanControl" => self.can_control = capability(&value),
+                "CanPlay" => self.can_play = capability(&value),
+                "CanPause" => self.can_pause = capability(&value),
+                "Shuffle" => self.shuffle = value.as_u64().map(|shuffle| shuffle != 0),
+                "LoopStatus" => self.loop_status = value.as_str().map(str::to_string),
+                "Volume" => self.volume = value.as_f64(),
                 "PlaybackStatus" => {
                     // Playing, Paused, Stopped
-                    self.playing = value.as_str()
-                        .and_then(|value| Some(value == "Playing"))
-                        .unwrap_or(false);
+                    self.set_playing(value.as_str()
+                        .map(|value| value == "Playing")
+                        .unwrap_or(false));
                 },
                 _ => {
-                    //eprintln!("{} -> {:?}", field, value);
+                    debug!("Unhandled property {} -> {:?}", field, value);
                 }
             }
         }
+    }
+}
+
+struct Player {
+    // Unique connection name, signals are sent from it rather than the well-known name
+    owner: String,
+    state: PlayerState,
+    last_active: Instant,
+}
+
+/// Every player currently on the bus, keyed by well-known bus name
+struct Registry {
+    invalidate: Arc<Notify>,
+    players: HashMap<String, Player>,
+}
+
+impl Registry {
+    /// The player to show and control: one that's playing, otherwise the most recently active
+    fn active(&self) -> Option<(&String, &Player)> {
+        return self.players.iter()
+            .max_by_key(|(_, player)| (player.state.playing, player.last_active));
+    }
+
+    fn add(&mut self, bus_name: String, owner: String) {
+        self.players.insert(bus_name, Player {
+            owner,
+            state: PlayerState::default(),
+            last_active: Instant::now(),
+        });
+        self.invalidate.notify_one();
+    }
+
+    fn remove(&mut self, bus_name: &str) {
+        if self.players.remove(bus_name).is_some() {
+            self.invalidate.notify_one();
+        }
+    }
+
+    fn update(&mut self, bus_name: &str, props: arg::PropMap) {
+        let Some(player) = self.players.get_mut(bus_name)
+        else { return };
+
+        let was_playing = player.state.playing;
+        player.state.update(props);
+        if player.state.playing && !was_playing {
+            player.last_active = Instant::now();
+        }
         self.invalidate.notify_one();
     }
+
+    fn clear(&mut self) {
+        if !self.players.is_empty() {
+            self.players.clear();
+            self.invalidate.notify_one();
+        }
+    }
+
+    fn bus_name_of(&self, owner: &str) -> Option<String> {
+        return self.players.iter()
+            .find(|(_, player)| player.owner == owner)
+            .map(|(bus_name, _)| bus_name.clone());
+    }
 }
 
-pub struct Mpris<'a> {
-    proxy: Proxy<'a, Arc<SyncConnection>>,
+pub struct Mpris {
+    bus: Bus,
     destruct: Arc<Notify>,
-    state: Arc<Mutex<PlayerState>>,
+    registry: Arc<Mutex<Registry>>,
 }
 
-const INTERFACE: &'static str = "org.mpris.MediaPlayer2.Player";
-
-impl<'a> Drop for Mpris<'a> {
+const INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const BUS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Times to ask a player that just appeared for its properties, until it knows its track
+const FETCH_ATTEMPTS: u32 = 5;
+/// Delay before the first of those, doubling for each one after
+const FETCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+impl Drop for Mpris {
     fn drop(&mut self) {
         self.destruct.notify_one();
     }
 }
 
-impl<'a> Mpris<'a> {
-    async fn create_property_changed_handler(conn: Arc<SyncConnection>, bus_name: String, state: Arc<Mutex<PlayerState>>) -> Result<MsgMatch, dbus::Error> {
+/// Whether `bus_name` is an MPRIS player we're interested in. Players like VLC
+/// append `.instance<pid>` to their name so that's accepted as well.
+fn is_wanted(bus_name: &str, instance: Option<&str>) -> bool {
+    let Some(name) = bus_name.strip_prefix(BUS_NAME_PREFIX)
+    else { return false };
+
+    return match instance {
+        Some(instance) => name == instance || name.strip_prefix(instance).is_some_and(|rest| rest.starts_with('.')),
+        None => true,
+    };
+}
+
+fn player_proxy(conn: Arc<SyncConnection>, bus_name: String) -> Proxy<'static, Arc<SyncConnection>> {
+    return Proxy::new(bus_name, OBJECT_PATH, TIMEOUT, conn);
+}
+
+fn bus_proxy(conn: Arc<SyncConnection>) -> Proxy<'static, Arc<SyncConnection>> {
+    return Proxy::new("org.freedesktop.DBus", "/org/freedesktop/DBus", TIMEOUT, conn);
+}
+
+impl Mpris {
+    /// Gets every property of the player, `Ok(false)` when it doesn't know its track yet
+    async fn get_properties(conn: Arc<SyncConnection>, bus_name: &str, registry: &Mutex<Registry>) -> Result<bool, dbus::Error> {
+        let proxy = player_proxy(conn, bus_name.to_string());
+        let (props,) = proxy.method_call("org.freedesktop.DBus.Properties", "GetAll", (INTERFACE,)).await?;
+        let mut registry = registry.lock().unwrap();
+        registry.update(bus_name, props);
+        return Ok(registry.players.get(bus_name).is_some_and(|player| !player.state.title.is_empty()));
+    }
+
+    async fn fetch_properties(conn: Arc<SyncConnection>, bus_name: String, registry: Arc<Mutex<Registry>>) {
+        if let Err(err) = Self::get_properties(conn, &bus_name, &registry).await {
+            warn!("Failed to get properties of {}: {}", bus_name, err);
+        }
+    }
+
+    /// Like `fetch_properties` for a player that just appeared. Spotify on startup may take
+    /// some time to get the song information and won't signal when it has them, so we keep
+    /// asking a few times with growing delays.
+    async fn fetch_new_player_properties(conn: Arc<SyncConnection>, bus_name: String, registry: Arc<Mutex<Registry>>) {
+        let mut delay = FETCH_RETRY_DELAY;
+        for attempt in 1..=FETCH_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            if !registry.lock().unwrap().players.contains_key(&bus_name) {
+                // Gone again
+                return;
+            }
+            match Self::get_properties(conn.clone(), &bus_name, &registry).await {
+                Ok(true) => return,
+                Ok(false) => debug!("{} has no track yet, attempt {} of {}", bus_name, attempt, FETCH_ATTEMPTS),
+                Err(err) if attempt == FETCH_ATTEMPTS => warn!("Failed to get properties of {}: {}", bus_name, err),
+                Err(err) => debug!("Failed to get properties of {}, attempt {} of {}: {}", bus_name, attempt, FETCH_ATTEMPTS, err),
+            }
+        }
+    }
+
+    async fn create_property_changed_handler(conn: Arc<SyncConnection>, registry: Arc<Mutex<Registry>>) -> Result<MsgMatch, dbus::Error> {
         let rule: MatchRule<'_> = MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
-                .with_sender(bus_name.clone());
+                .with_path(OBJECT_PATH);
         let r#match = conn.add_match(rule);
         return Ok(r#match.await?
-            .cb(move |_, (interface_name, changed_properties, invalidated_properties,): (String, arg::PropMap, Vec<String>)| {
+            .cb(move |message: Message, (interface_name, changed_properties, invalidated_properties,): (String, arg::PropMap, Vec<String>)| {
                 if interface_name != INTERFACE {
                     return true;
                 }
 
-                if invalidated_properties.len() > 0 {
-                    eprintln!("Unhandled PropertyChanged invalidated_properties.len() > 0");
+                let Some(sender) = message.sender()
+                else { return true };
+
+                if !invalidated_properties.is_empty() {
+                    debug!("Unhandled PropertyChanged invalidated_properties.len() > 0");
                 }
 
-                state.lock().unwrap().update(changed_properties);
+                let mut registry = registry.lock().unwrap();
+                if let Some(bus_name) = registry.bus_name_of(&sender) {
+                    registry.update(&bus_name, changed_properties);
+                }
                 true
             }));
     }
 
-    async fn create_name_owner_changed_handler(conn: Arc<SyncConnection>, bus_name: String, proxy: Proxy<'static, Arc<SyncConnection>>, state: Arc<Mutex<PlayerState>>) -> Result<MsgMatch, dbus::Error> {
+    async fn create_name_owner_changed_handler(conn: Arc<SyncConnection>, instance: Option<String>, registry: Arc<Mutex<Registry>>) -> Result<MsgMatch, dbus::Error> {
         let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
             .with_sender("org.freedesktop.DBus");
         let r#match = conn.add_match(rule);
         return Ok(r#match.await?.cb(move |_, (name, _old_owner, new_owner): (String, String, String)| {
-                if name != bus_name {
+                if !is_wanted(&name, instance.as_deref()) {
                     return true;
                 }
 
                 if new_owner.is_empty() {
-                    state.lock().unwrap().name_lost();
+                    registry.lock().unwrap().remove(&name);
                 } else {
-                    let proxy2 = proxy.clone();
-                    let state2 = state.clone();
-                    tokio::spawn(async move {
-                        // Spotify on startup may take some time to get the song information and
-                        // won't signal when it has them. So we wait a bit and ask for them manually.
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                        let (metadata,): (arg::Variant<Box<dyn RefArg + 'static>>,) = match proxy2.method_call("org.freedesktop.DBus.Properties", "Get", (INTERFACE, "Metadata")).await {
-                            Ok(x) => x,
-                            Err(err) => {
-                                eprintln!("Failed to get metadata: {}", err);
-                                return;
-                            }
-                        };
-                        state2.lock().unwrap().update_metadata(metadata.0);
-                    });
+                    registry.lock().unwrap().add(name.clone(), new_owner);
+                    let conn = conn.clone();
+                    let registry = registry.clone();
+                    tokio::spawn(Self::fetch_new_player_properties(conn, name, registry));
                 }
                 true
             }));
     }
 
-    fn create_watcher(conn: Arc<SyncConnection>, bus_name: String, proxy: Proxy<'static, Arc<SyncConnection>>, destruct: Arc<Notify>, state: Arc<Mutex<PlayerState>>) -> tokio::task::JoinHandle<()> {
-        return tokio::spawn(async move {
-            let signal_property_changed = match Self::create_property_changed_handler(conn.clone(), bus_name.clone(), state.clone()).await {
-                Ok(handler) => handler,
-                Err(err) => {
-                    eprintln!("Failed to AddMatch on PropertiesChanged: {}", err);
-                    return;
-                }
-            };
+    /// Registers the players that were already on the bus before we started
+    async fn add_existing_players(conn: Arc<SyncConnection>, instance: Option<&str>, registry: Arc<Mutex<Registry>>) {
+        let bus = bus_proxy(conn.clone());
+        let names: Vec<String> = match bus.method_call("org.freedesktop.DBus", "ListNames", ()).await {
+            Ok((names,)) => names,
+            Err(err) => {
+                warn!("Failed to list bus names: {}", err);
+                return;
+            }
+        };
 
-            let signal_name_owner_changed = match Self::create_name_owner_changed_handler(conn.clone(), bus_name, proxy.clone(), state.clone()).await {
-                Ok(handler) => handler,
+        for name in names.into_iter().filter(|name| is_wanted(name, instance)) {
+            let owner: String = match bus.method_call("org.freedesktop.DBus", "GetNameOwner", (name.as_str(),)).await {
+                Ok((owner,)) => owner,
                 Err(err) => {
-                    // There must be a more elegant solution for this, maybe something like defer?
-                    let _ = conn.remove_match(signal_property_changed.token()).await;
-                    eprintln!("Failed to AddMatch on NameOwnerChanged: {}", err);
-                    return;
+                    warn!("Failed to get owner of {}: {}", name, err);
+                    continue;
                 }
             };
+            registry.lock().unwrap().add(name.clone(), owner);
+            Self::fetch_properties(conn.clone(), name, registry.clone()).await;
+        }
+    }
 
-            let props: Option<arg::PropMap> = match proxy.method_call("org.freedesktop.DBus.Properties", "GetAll", (INTERFACE,)).await {
-                Ok((x,)) => Some(x),
-                Err(err) => {
-                    eprintln!("Failed to get properties: {}", err);
-                    None
-                }
-            };
+    /// Starts following players on `conn`, the returned matches stay registered until removed
+    async fn watch_players(conn: Arc<SyncConnection>, instance: Option<&str>, registry: Arc<Mutex<Registry>>) -> Option<[MsgMatch; 2]> {
+        let signal_property_changed = match Self::create_property_changed_handler(conn.clone(), registry.clone()).await {
+            Ok(handler) => handler,
+            Err(err) => {
+                warn!("Failed to AddMatch on PropertiesChanged: {}", err);
+                return None;
+            }
+        };
 
-            if let Some(props) = props {
-                state.lock().unwrap().update(props);
+        let signal_name_owner_changed = match Self::create_name_owner_changed_handler(conn.clone(), instance.map(str::to_string), registry.clone()).await {
+            Ok(handler) => handler,
+            Err(err) => {
+                // There must be a more elegant solution for this, maybe something like defer?
+                let _ = conn.remove_match(signal_property_changed.token()).await;
+                warn!("Failed to AddMatch on NameOwnerChanged: {}", err);
+                return None;
             }
+        };
 
-            destruct.notified().await;
+        Self::add_existing_players(conn, instance, registry).await;
+        return Some([signal_name_owner_changed, signal_property_changed]);
+    }
 
-            let _ = conn.remove_match(signal_name_owner_changed.token()).await;
-            let _ = conn.remove_match(signal_property_changed.token()).await;
+    fn create_watcher(mut bus: Bus, instance: Option<String>, destruct: Arc<Notify>, registry: Arc<Mutex<Registry>>) -> tokio::task::JoinHandle<()> {
+        return tokio::spawn(async move {
+            loop {
+                let watching = match bus.current_and_update() {
+                    Some(conn) => Self::watch_players(conn.clone(), instance.as_deref(), registry.clone()).await
+                        .map(|matches| (conn, matches)),
+                    None => None,
+                };
+
+                let destructed = tokio::select! {
+                    _ = destruct.notified() => true,
+                    _ = bus.changed() => false,
+                };
+
+                // After a reconnect the old connection is gone and so are its matches,
+                // removing them would wait forever for a reply
+                if destructed {
+                    if let Some((conn, matches)) = watching {
+                        for r#match in matches {
+                            let _ = conn.remove_match(r#match.token()).await;
+                        }
+                    }
+                    return;
+                }
+
+                // Players on the old connection are unreachable, the new one reports them again
+                registry.lock().unwrap().clear();
+            }
         });
     }
 
-    pub fn new(conn: Arc<SyncConnection>, instance: &str, invalidate: Arc<Notify>) -> Self {
-        let bus_name = format!("org.mpris.MediaPlayer2.{}", instance);
-        let proxy = Proxy::new(
-            bus_name.clone(),
-            "/org/mpris/MediaPlayer2",
-            Duration::from_secs(5),
-            conn.clone());
-
+    /// Watches the player `org.mpris.MediaPlayer2.<instance>`, or every player when `instance` is `None`
+    pub fn new(bus: Bus, instance: Option<&str>, invalidate: Arc<Notify>) -> Self {
         let destruct = Arc::new(Notify::new());
-        let state = Arc::new(Mutex::new(PlayerState::new(invalidate)));
+        let registry = Arc::new(Mutex::new(Registry {
+            invalidate,
+            players: HashMap::new(),
+        }));
 
-        Self::create_watcher(conn, bus_name, proxy.clone(), destruct.clone(), state.clone());
+        Self::create_watcher(bus.clone(), instance.map(str::to_string), destruct.clone(), registry.clone());
 
         return Self {
-            proxy,
+            bus,
             destruct,
-            state,
+            registry,
         };
     }
 
+    fn active_bus_name(&self) -> Option<String> {
+        return self.registry.lock().unwrap()
+            .active()
+            .map(|(bus_name, _)| bus_name.clone());
+    }
+
     fn send_call_simple<R, A>(&self, method: &'static str, args: A)
         where
             R: ReadAll + 'static,
             A: AppendAll,
     {
-        let reply: MethodReply<R> = self.proxy.method_call(INTERFACE, method, args);
+        self.send_call::<R, A>(INTERFACE, method, args);
+    }
+
+    fn send_call<R, A>(&self, interface: &'static str, method: &'static str, args: A)
+        where
+            R: ReadAll + 'static,
+            A: AppendAll,
+    {
+        let Some(conn) = self.bus.current()
+        else { return };
+
+        let Some(bus_name) = self.active_bus_name()
+        else { return };
+
+        let reply: MethodReply<R> = player_proxy(conn, bus_name).method_call(interface, method, args);
         tokio::spawn(async { let _ = reply.await; });
     }
 
@@ -236,10 +543,152 @@ impl<'a> Mpris<'a> {
     #[allow(dead_code)]
     pub fn next(&self) { self.send_call_simple::<(), _>("Next", ()); }
 
+    /// Moves the playback position by `offset` microseconds, negative seeks backwards
+    pub fn seek(&self, offset: i64) { self.send_call_simple::<(), _>("Seek", (offset,)); }
+
     #[allow(dead_code)]
     pub fn previous(&self) { self.send_call_simple::<(), _>("Previous", ()); }
 
-    pub fn state(&self) -> MutexGuard<'_, PlayerState> {
-        return self.state.lock().unwrap();
+    pub fn set_shuffle(&self, shuffle: bool) {
+        self.send_call::<(), _>("org.freedesktop.DBus.Properties", "Set", (INTERFACE, "Shuffle", arg::Variant(shuffle)));
+    }
+
+    /// `None`, `Track` or `Playlist`
+    pub fn set_loop_status(&self, loop_status: &str) {
+        self.send_call::<(), _>("org.freedesktop.DBus.Properties", "Set", (INTERFACE, "LoopStatus", arg::Variant(loop_status.to_string())));
+    }
+
+    /// Player volume from 0.0 to 1.0, values outside are clamped
+    pub fn set_volume(&self, volume: f64) {
+        self.send_call::<(), _>("org.freedesktop.DBus.Properties", "Set", (INTERFACE, "Volume", arg::Variant(volume.clamp(0.0, 1.0))));
+    }
+
+    /// Brings the player's window to the front
+    pub fn raise(&self) { self.send_call::<(), _>(ROOT_INTERFACE, "Raise", ()); }
+
+    /// Asks the active player for its current position, the result is picked up by a later `state()`
+    pub fn refresh_position(&self) {
+        let Some(conn) = self.bus.current()
+        else { return };
+
+        let Some(bus_name) = self.active_bus_name()
+        else { return };
+
+        let reply: MethodReply<(arg::Variant<i64>,)> = player_proxy(conn, bus_name.clone())
+            .method_call("org.freedesktop.DBus.Properties", "Get", (INTERFACE, "Position"));
+        let registry = self.registry.clone();
+        tokio::spawn(async move {
+            match reply.await {
+                Ok((position,)) => {
+                    if let Some(player) = registry.lock().unwrap().players.get_mut(&bus_name) {
+                        player.state.set_position(Duration::from_micros(position.0.max(0) as u64));
+                    }
+                }
+                Err(err) => warn!("Failed to get position: {}", err),
+            }
+        });
+    }
+
+    /// State of the active player, empty when there is none
+    pub fn state(&self) -> PlayerState {
+        return self.registry.lock().unwrap()
+            .active()
+            .map(|(_, player)| player.state.clone())
+            .unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use dbus::arg::{PropMap, RefArg, Variant};
+
+    use super::{Metadata, PlayerState};
+
+    fn entry<'a>(key: &'a str, value: &'a dyn RefArg) -> (&'a str, &'a dyn RefArg) {
+        return (key, value);
+    }
+
+    #[test]
+    fn one_artist() {
+        let title = "Song".to_string();
+        let artists = vec!["Artist".to_string()];
+        let metadata = Metadata::from_entries([entry("xesam:title", &title), entry("xesam:artist", &artists)]);
+        assert_eq!(metadata.title.as_deref(), Some("Song"));
+        assert_eq!(metadata.artists, Some(vec!["Artist".to_string()]));
+    }
+
+    #[test]
+    fn several_artists_keep_their_order() {
+        let artists = vec!["First".to_string(), "Second".to_string(), "Third".to_string()];
+        let metadata = Metadata::from_entries([entry("xesam:artist", &artists)]);
+        assert_eq!(metadata.artists, Some(artists));
+    }
+
+    #[test]
+    fn missing_title_keeps_the_previous_one() {
+        let artists = vec!["Artist".to_string()];
+        let metadata = Metadata::from_entries([entry("xesam:artist", &artists)]);
+        assert_eq!(metadata.title, None);
+
+        let mut state = PlayerState { title: "Before".to_string(), album: "Old album".to_string(), ..PlayerState::default() };
+        state.update_metadata(metadata);
+        assert_eq!(state.title, "Before");
+        // Unlike the title the album doesn't outlive a track that leaves it out
+        assert_eq!(state.album, "");
+    }
+
+    #[test]
+    fn missing_artist_is_none() {
+        let title = "Song".to_string();
+        let metadata = Metadata::from_entries([entry("xesam:title", &title)]);
+        assert_eq!(metadata.artists, None);
+    }
+
+    #[test]
+    fn non_string_artists_are_skipped() {
+        let numbers: Vec<i32> = vec![1, 2];
+        assert_eq!(Metadata::from_entries([entry("xesam:artist", &numbers)]).artists, Some(Vec::new()));
+
+        let number = 7u32;
+        assert_eq!(Metadata::from_entries([entry("xesam:artist", &number)]).artists, Some(Vec::new()));
+
+        let single = "Solo".to_string();
+        assert_eq!(Metadata::from_entries([entry("xesam:artist", &single)]).artists, Some(vec!["Solo".to_string()]));
+    }
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let rating = 0.5f64;
+        let url = "https://example.com/track".to_string();
+        let metadata = Metadata::from_entries([entry("xesam:userRating", &rating), entry("xesam:url", &url)]);
+        assert_eq!(metadata, Metadata::default());
+    }
+
+    #[test]
+    fn parse_unwraps_the_variants() {
+        let mut map = PropMap::new();
+        map.insert("xesam:title".to_string(), Variant(Box::new("Song".to_string())));
+        map.insert("xesam:artist".to_string(), Variant(Box::new(vec!["A".to_string(), "B".to_string()])));
+        map.insert("xesam:trackNumber".to_string(), Variant(Box::new(3i32)));
+        map.insert("mpris:length".to_string(), Variant(Box::new(200_000_000i64)));
+        map.insert("mpris:artUrl".to_string(), Variant(Box::new("".to_string())));
+
+        let metadata = Metadata::parse(&map).unwrap();
+        assert_eq!(metadata, Metadata {
+            title: Some("Song".to_string()),
+            artists: Some(vec!["A".to_string(), "B".to_string()]),
+            album: None,
+            track_number: Some(3),
+            length: Some(Duration::from_secs(200)),
+            // An empty URL is no art
+            art_url: None,
+        });
+    }
+
+    #[test]
+    fn parse_rejects_non_maps() {
+        assert_eq!(Metadata::parse(&"not a map".to_string()), None);
     }
 }
@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Which bar reads the status line. They share the protocol but Waybar knows a few more fields.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    #[default]
+    #[serde(rename = "i3bar")]
+    I3bar,
+    #[serde(rename = "waybar")]
+    Waybar,
+}
+
+/// Fields blocks may set that only Waybar understands
+const WAYBAR_FIELDS: [&str; 2] = ["tooltip", "class"];
+
+/// Adjusts rendered items to what `mode` supports: i3bar doesn't get the Waybar only fields
+/// and Waybar gets the block name as CSS class unless the block picked one
+pub fn adapt(items: &mut [Value], mode: OutputMode) {
+    for item in items.iter_mut() {
+        let Value::Object(fields) = item
+        else { continue };
+
+        match mode {
+            OutputMode::I3bar => {
+                for field in WAYBAR_FIELDS {
+                    fields.remove(field);
+                }
+            }
+            OutputMode::Waybar => {
+                if !fields.contains_key("class") {
+                    if let Some(name) = fields.get("name").cloned() {
+                        fields.insert("class".to_string(), json!(name));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use tokio::{sync::Notify, time::sleep};
+
+use crate::{block::{Block, Context}, config::Config, inotify::{self, Inotify}, log::warn};
+
+/// Editors often write a file in several steps, wait for them to finish before reloading
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Notifies `reload` whenever the file at `path` has been written or replaced. The directory
+/// is watched rather than the file so editors that save by renaming a new file get noticed.
+pub fn watch(path: &Path, reload: Arc<Notify>) {
+    let (Some(directory), Some(name)) = (path.parent(), path.file_name())
+    else { return };
+
+    let mut inotify = match Inotify::new() {
+        Ok(inotify) => inotify,
+        Err(err) => {
+            warn!("Failed to set up inotify, the config won't be reloaded: {}", err);
+            return;
+        }
+    };
+    let directory = if directory.as_os_str().is_empty() { Path::new(".") } else { directory };
+    if let Err(err) = inotify.watch(directory, inotify::IN_CLOSE_WRITE | inotify::IN_MOVED_TO | inotify::IN_CREATE) {
+        warn!("Failed to watch {}, the config won't be reloaded: {}", directory.display(), err);
+        return;
+    }
+
+    let name = name.to_os_string();
+    tokio::spawn(async move {
+        let mut changed = false;
+        loop {
+            let events = tokio::select! {
+                events = inotify.read() => events,
+                _ = sleep(DEBOUNCE), if changed => {
+                    changed = false;
+                    reload.notify_one();
+                    continue;
+                }
+            };
+            match events {
+                Ok(events) => changed |= events.iter().any(|event| event.name.as_ref() == Some(&name)),
+                Err(err) => {
+                    warn!("Failed to read inotify events, the config won't be reloaded anymore: {}", err);
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Builds the blocks of `config`, reusing blocks of the previous config whose section didn't
+/// change. That keeps their state, like connections and running commands.
+pub fn build_blocks(config: &mut Config, context: &Context, mut previous: Vec<(toml::Value, Box<dyn Block>)>) -> Vec<(toml::Value, Box<dyn Block>)> {
+    let sources = std::mem::take(&mut config.block_sources);
+    let mut blocks = Vec::new();
+    for (index, entry) in std::mem::take(&mut config.blocks).into_iter().enumerate() {
+        // The default config has no sections, its blocks are never reused
+        let source = sources.get(index).cloned().unwrap_or(toml::Value::Boolean(false));
+        let reused = sources.get(index)
+            .and_then(|source| previous.iter().position(|(old, _)| old == source))
+            .map(|position| previous.remove(position).1);
+        let block = reused.unwrap_or_else(|| entry.build(context));
+        blocks.push((source, block));
+    }
+    return blocks;
+}
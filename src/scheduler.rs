@@ -0,0 +1,378 @@
+use serde_json::{json, Value};
+use tokio::time::{Duration, Instant};
+
+use crate::{block::{self, Block, ClickEvent}, color, log::error, toggle::Toggles};
+
+/// Blocks due this soon are rendered along with the ones that are already due,
+/// so that deadlines a few milliseconds apart don't each cause an update
+const SLACK: Duration = Duration::from_millis(50);
+
+struct Scheduled {
+    block: Box<dyn Block>,
+    /// Output of the last render, reused until the block is due again
+    output: Vec<Value>,
+    /// `None` when the block only updates on invalidation or clicks
+    deadline: Option<Instant>,
+    /// The block panicked, it may be in any state so it's left alone from then on
+    failed: bool,
+}
+
+impl Scheduled {
+    /// Stops using the block after it panicked, it shows an error in its place instead
+    fn fail(&mut self, payload: &(dyn std::any::Any + Send)) {
+        let name = self.block.name().to_string();
+        let location = block::caught_at().unwrap_or_else(|| "unknown location".to_string());
+        error!("Block {} panicked at {}, showing an error in its place: {}", name, location, block::panic_message(payload));
+        self.failed = true;
+        self.deadline = None;
+        self.output = vec![json!({ "name": name, "full_text": format!("{} ERROR", name.to_uppercase()), "color": color::CRITICAL })];
+    }
+}
+
+/// Renders each block only when its interval has passed and keeps the output of the rest
+pub struct Scheduler {
+    blocks: Vec<Scheduled>,
+    /// Drop items without text, a block with nothing to show disappears along with its separator
+    collapse_empty: bool,
+}
+
+/// Instance of an item rendered by the block at `index`. i3bar only tells us the name and
+/// instance of the item that was clicked, so the index in front tells apart blocks of the
+/// same type.
+fn tag_instance(index: usize, instance: Option<&str>) -> String {
+    return match instance {
+        Some(instance) => format!("{}:{}", index, instance),
+        None => index.to_string(),
+    };
+}
+
+/// Splits an instance made by `tag_instance` back into the block index and the instance the
+/// block set itself
+fn untag_instance(tagged: &str) -> Option<(usize, Option<&str>)> {
+    let (index, instance) = match tagged.split_once(':') {
+        Some((index, instance)) => (index, Some(instance)),
+        None => (tagged, None),
+    };
+    return Some((index.parse().ok()?, instance));
+}
+
+/// The earliest of `deadlines`, `None` if no block wants a timed update
+fn next_deadline(deadlines: impl Iterator<Item = Option<Instant>>) -> Option<Instant> {
+    return deadlines.flatten().min();
+}
+
+impl Scheduler {
+    pub fn new(blocks: Vec<Box<dyn Block>>, collapse_empty: bool) -> Self {
+        let now = Instant::now();
+        return Self {
+            blocks: blocks.into_iter()
+                .map(|block| Scheduled { block, output: Vec::new(), deadline: Some(now), failed: false })
+                .collect(),
+            collapse_empty,
+        };
+    }
+
+    /// Renders the blocks that are due, or every block when `force` is set, and
+    /// returns the status line of all enabled blocks
+    pub async fn render(&mut self, toggles: &Toggles, force: bool) -> Vec<Value> {
+        let mut output = Vec::new();
+        // Group of the last block that showed anything
+        let mut previous_group = None;
+        for (index, scheduled) in self.blocks.iter_mut().enumerate() {
+            if !toggles.is_enabled(scheduled.block.id()) {
+                continue;
+            }
+
+            let due = scheduled.deadline.is_some_and(|deadline| deadline <= Instant::now() + SLACK);
+            if (force || due) && !scheduled.failed {
+                match block::catch_unwind(block::render_block(scheduled.block.as_mut())).await {
+                    Ok(output) => scheduled.output = output,
+                    Err(payload) => scheduled.fail(payload.as_ref()),
+                }
+                if self.collapse_empty {
+                    scheduled.output.retain(|item| item.get("full_text").and_then(Value::as_str) != Some(""));
+                }
+                for item in scheduled.output.iter_mut() {
+                    let Value::Object(fields) = item
+                    else { continue };
+
+                    let instance = fields.get("instance").and_then(Value::as_str);
+                    let tagged = tag_instance(index, instance);
+                    fields.insert("instance".to_string(), Value::String(tagged));
+                }
+                if !scheduled.failed {
+                    scheduled.deadline = scheduled.block.interval()
+                        .map(|interval| Instant::now() + interval);
+                }
+            }
+            if scheduled.output.is_empty() {
+                continue;
+            }
+
+            let group = scheduled.block.group();
+            if group.is_some() && group == previous_group {
+                if let Some(Value::Object(last)) = output.last_mut() {
+                    last.entry("separator").or_insert(Value::Bool(false));
+                }
+            }
+            previous_group = group;
+            output.extend(scheduled.output.iter().cloned());
+        }
+        return output;
+    }
+
+    /// Takes the blocks back out, in the order they were passed to `new`
+    pub fn into_blocks(self) -> Vec<Box<dyn Block>> {
+        return self.blocks.into_iter()
+            .map(|scheduled| scheduled.block)
+            .collect();
+    }
+
+    /// When the next enabled block is due. Hidden blocks keep the deadline they had, they get
+    /// rendered again right away once the toggle invalidates.
+    pub fn next_deadline(&self, toggles: &Toggles) -> Option<Instant> {
+        return next_deadline(self.blocks.iter()
+            .filter(|scheduled| toggles.is_enabled(scheduled.block.id()))
+            .map(|scheduled| scheduled.deadline));
+    }
+
+    /// Hands the click to the block it was on and makes that block due. The block sees the
+    /// instance it set itself.
+    pub fn route_click(&mut self, event: &ClickEvent) {
+        let tagged = event.instance.as_deref().and_then(untag_instance);
+        let (scheduled, event) = match tagged {
+            Some((index, instance)) => {
+                let Some(scheduled) = self.blocks.get_mut(index)
+                else { return };
+
+                let event = ClickEvent { instance: instance.map(str::to_string), ..event.clone() };
+                (scheduled, event)
+            }
+            // Not one of our instances, the first block with that name gets it
+            None => {
+                let Some(name) = &event.name
+                else { return };
+
+                let Some(scheduled) = self.blocks.iter_mut().find(|scheduled| scheduled.block.name() == name)
+                else { return };
+
+                (scheduled, event.clone())
+            }
+        };
+
+        if scheduled.failed {
+            return;
+        }
+        if let Err(payload) = block::catch_panic(|| block::dispatch_click(scheduled.block.as_mut(), &event)) {
+            scheduled.fail(payload.as_ref());
+            return;
+        }
+        scheduled.deadline = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex};
+
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use tokio::{sync::Notify, time::{Duration, Instant}};
+
+    use super::{next_deadline, tag_instance, untag_instance, Scheduler};
+    use crate::{block::{Block, ClickEvent}, color, config::CommonConfig, decorated::Decorated, output::OutputMode, toggle::Toggles};
+
+    /// Renders `output` every `interval`
+    struct FakeBlock {
+        name: &'static str,
+        output: Vec<Value>,
+        interval: Option<Duration>,
+    }
+
+    #[async_trait]
+    impl Block for FakeBlock {
+        fn name(&self) -> &str {
+            return self.name;
+        }
+
+        async fn render(&mut self) -> Vec<Value> {
+            return self.output.clone();
+        }
+
+        fn interval(&self) -> Option<Duration> {
+            return self.interval;
+        }
+    }
+
+    /// Panics on every render, counting how often it was tried
+    struct Panicking {
+        renders: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Block for Panicking {
+        fn name(&self) -> &str {
+            return "broken";
+        }
+
+        async fn render(&mut self) -> Vec<Value> {
+            self.renders.fetch_add(1, Ordering::Relaxed);
+            panic!("unexpected {} value", "DBus");
+        }
+
+        fn interval(&self) -> Option<Duration> {
+            return Some(Duration::ZERO);
+        }
+    }
+
+    /// Which mount got a click and the instance it saw
+    type Clicks = Arc<Mutex<Vec<(&'static str, Option<String>)>>>;
+
+    /// A disk with its mount point as the item instance, collecting the clicks it gets
+    struct Mount {
+        mount: &'static str,
+        clicks: Clicks,
+    }
+
+    #[async_trait]
+    impl Block for Mount {
+        fn name(&self) -> &str {
+            return "disk";
+        }
+
+        async fn render(&mut self) -> Vec<Value> {
+            return vec![json!({ "full_text": self.mount, "instance": self.mount })];
+        }
+
+        fn handle_click(&mut self, event: &ClickEvent) {
+            self.clicks.lock().unwrap().push((self.mount, event.instance.clone()));
+        }
+    }
+
+    fn click(name: &str, instance: Option<&str>) -> ClickEvent {
+        return ClickEvent {
+            name: Some(name.to_string()),
+            instance: instance.map(str::to_string),
+            x: 0, y: 0, button: 1, relative_x: 0, relative_y: 0, width: 0, height: 0,
+        };
+    }
+
+    fn fake(name: &'static str, interval: Option<Duration>) -> Box<dyn Block> {
+        return Box::new(FakeBlock { name, output: vec![json!({ "full_text": name })], interval });
+    }
+
+    #[test]
+    fn next_deadline_is_the_earliest() {
+        let now = Instant::now();
+        let deadlines = [Some(now + Duration::from_secs(60)), None, Some(now + Duration::from_secs(1))];
+        assert_eq!(next_deadline(deadlines.into_iter()), Some(now + Duration::from_secs(1)));
+        assert_eq!(next_deadline([None, None].into_iter()), None);
+    }
+
+    #[tokio::test]
+    async fn disabled_block_has_no_deadline() {
+        let toggles = Toggles::new(Arc::new(Notify::new()));
+        let mut scheduler = Scheduler::new(vec![fake("fast", Some(Duration::from_secs(1)))], true);
+        scheduler.render(&toggles, true).await;
+        assert!(scheduler.next_deadline(&toggles).is_some());
+
+        toggles.toggle("fast");
+        assert_eq!(scheduler.render(&toggles, false).await, Vec::<Value>::new());
+        assert_eq!(scheduler.next_deadline(&toggles), None);
+    }
+
+    #[tokio::test]
+    async fn only_timed_blocks_have_deadlines() {
+        let toggles = Toggles::new(Arc::new(Notify::new()));
+        let mut scheduler = Scheduler::new(vec![fake("clock", Some(Duration::from_secs(60))), fake("mpris", None)], true);
+        let before = Instant::now();
+        scheduler.render(&toggles, true).await;
+        let deadline = scheduler.next_deadline(&toggles).unwrap();
+        assert!(deadline >= before + Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn panicking_block_shows_an_error() {
+        let toggles = Toggles::new(Arc::new(Notify::new()));
+        let renders = Arc::new(AtomicUsize::new(0));
+        let blocks = vec![fake("clock", None), Box::new(Panicking { renders: renders.clone() }) as Box<dyn Block>, fake("disk", None)];
+        let mut scheduler = Scheduler::new(blocks, true);
+
+        let expected = vec![
+            json!({ "name": "clock", "full_text": "clock", "instance": "0" }),
+            json!({ "name": "broken", "full_text": "BROKEN ERROR", "color": color::CRITICAL, "instance": "1" }),
+            json!({ "name": "disk", "full_text": "disk", "instance": "2" }),
+        ];
+        assert_eq!(scheduler.render(&toggles, true).await, expected);
+
+        // The block isn't tried again, the others keep rendering
+        assert_eq!(scheduler.render(&toggles, true).await, expected);
+        assert_eq!(renders.load(Ordering::Relaxed), 1);
+        assert_eq!(scheduler.next_deadline(&toggles), None);
+    }
+
+    #[tokio::test]
+    async fn blocks_of_one_type_toggle_by_id() {
+        let toggles = Toggles::new(Arc::new(Notify::new()));
+        let with_id = |id: Option<&str>| -> Box<dyn Block> {
+            let common = CommonConfig { id: id.map(str::to_string), ..CommonConfig::default() };
+            return Box::new(Decorated::new(fake("disk", None), common, OutputMode::I3bar));
+        };
+        let mut scheduler = Scheduler::new(vec![with_id(Some("root")), with_id(Some("home")), with_id(None)], true);
+        let instances = |items: Vec<Value>| items.iter().map(|item| item["instance"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+
+        toggles.toggle("home");
+        assert_eq!(instances(scheduler.render(&toggles, true).await), ["0", "2"]);
+
+        // Blocks without an id go by their type
+        toggles.toggle("disk");
+        assert_eq!(instances(scheduler.render(&toggles, true).await), ["0"]);
+
+        toggles.toggle("home");
+        assert_eq!(instances(scheduler.render(&toggles, true).await), ["0", "1"]);
+    }
+
+    #[test]
+    fn instances_keep_the_block_index() {
+        assert_eq!(tag_instance(3, Some("/home")), "3:/home");
+        assert_eq!(untag_instance("3:/home"), Some((3, Some("/home"))));
+        // The block's own instance may have colons in it too
+        assert_eq!(untag_instance(&tag_instance(0, Some("a:b"))), Some((0, Some("a:b"))));
+        assert_eq!(untag_instance(&tag_instance(12, None)), Some((12, None)));
+        assert_eq!(untag_instance("utc"), None);
+    }
+
+    #[tokio::test]
+    async fn same_named_blocks_get_their_own_clicks() {
+        let toggles = Toggles::new(Arc::new(Notify::new()));
+        let clicks = Arc::new(Mutex::new(Vec::new()));
+        let mount = |mount| Box::new(Mount { mount, clicks: clicks.clone() }) as Box<dyn Block>;
+        let mut scheduler = Scheduler::new(vec![fake("clock", None), mount("/"), mount("/home")], true);
+
+        let items = scheduler.render(&toggles, true).await;
+        let instances: Vec<_> = items.iter().map(|item| item["instance"].as_str().unwrap()).collect();
+        assert_eq!(instances, ["0", "1:/", "2:/home"]);
+
+        scheduler.route_click(&click("disk", Some("2:/home")));
+        scheduler.route_click(&click("disk", Some("1:/")));
+        assert_eq!(*clicks.lock().unwrap(), [("/home", Some("/home".to_string())), ("/", Some("/".to_string()))]);
+    }
+
+    #[tokio::test]
+    async fn untagged_clicks_go_by_name() {
+        let clicks = Arc::new(Mutex::new(Vec::new()));
+        let mount = |mount| Box::new(Mount { mount, clicks: clicks.clone() }) as Box<dyn Block>;
+        let mut scheduler = Scheduler::new(vec![mount("/"), mount("/home")], true);
+
+        // Like from a bar that passes on instances it didn't get from us
+        scheduler.route_click(&click("disk", None));
+        scheduler.route_click(&click("disk", Some("elsewhere")));
+        scheduler.route_click(&click("mpris", None));
+        let clicked: Vec<_> = clicks.lock().unwrap().iter().map(|(mount, _)| *mount).collect();
+        assert_eq!(clicked, ["/", "/"]);
+
+        // An index past the last block is ignored
+        scheduler.route_click(&click("disk", Some("7:/srv")));
+        assert_eq!(clicks.lock().unwrap().len(), 2);
+    }
+}
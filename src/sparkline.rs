@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+const BARS: [char; 8] = ['\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}', '\u{2587}', '\u{2588}'];
+
+/// A block element as high as `value` is of `max`. Values outside of `0..=max` are clamped and
+/// a `max` of zero makes everything the lowest bar.
+pub fn bar(value: f64, max: f64) -> char {
+    let fraction = if max > 0.0 { (value / max).clamp(0.0, 1.0) } else { 0.0 };
+    let index = (fraction * (BARS.len() - 1) as f64).round() as usize;
+    return BARS[index];
+}
+
+/// One `bar` per value, like `▂▅█▃`
+pub fn sparkline(values: &[f64], max: f64) -> String {
+    return values.iter().map(|&value| bar(value, max)).collect();
+}
+
+/// The last few samples of a value, for showing its trend as a sparkline
+pub struct History {
+    samples: VecDeque<f64>,
+    length: usize,
+}
+
+impl History {
+    pub fn new(length: usize) -> Self {
+        return Self {
+            samples: VecDeque::with_capacity(length),
+            length,
+        };
+    }
+
+    /// Adds a sample, dropping the oldest once there are `length`
+    pub fn push(&mut self, sample: f64) {
+        if self.length == 0 {
+            return;
+        }
+        if self.samples.len() == self.length {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn max(&self) -> f64 {
+        return self.samples.iter().copied().fold(0.0, f64::max);
+    }
+
+    /// Oldest sample first, scaled to `max`
+    pub fn sparkline(&self, max: f64) -> String {
+        return self.samples.iter().map(|&sample| bar(sample, max)).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentages_map_onto_the_eight_bars() {
+        assert_eq!(bar(0.0, 100.0), '▁');
+        assert_eq!(bar(14.0, 100.0), '▂');
+        assert_eq!(bar(50.0, 100.0), '▅');
+        assert_eq!(bar(93.0, 100.0), '█');
+        assert_eq!(bar(100.0, 100.0), '█');
+    }
+
+    #[test]
+    fn bars_scale_to_the_max() {
+        assert_eq!(bar(4.0, 8.0), bar(50.0, 100.0));
+        assert_eq!(bar(3.0, 7.0), BARS[3]);
+    }
+
+    #[test]
+    fn sparkline_of_known_values() {
+        assert_eq!(sparkline(&[10.0, 55.0, 100.0, 30.0], 100.0), "▂▅█▃");
+        assert_eq!(sparkline(&[], 100.0), "");
+    }
+
+    #[test]
+    fn all_zero_is_flat() {
+        assert_eq!(sparkline(&[0.0, 0.0, 0.0], 100.0), "▁▁▁");
+        // Nothing to scale by either
+        assert_eq!(sparkline(&[0.0, 0.0], 0.0), "▁▁");
+        assert_eq!(sparkline(&[5.0], 0.0), "▁");
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        assert_eq!(sparkline(&[150.0, -20.0, f64::INFINITY], 100.0), "█▁█");
+    }
+
+    #[test]
+    fn history_keeps_the_latest_samples() {
+        let mut history = History::new(3);
+        assert_eq!(history.sparkline(1.0), "");
+        for sample in [7.0, 1.0, 0.0, 4.0] {
+            history.push(sample);
+        }
+        // The 7 dropped out, the rest is scaled to the new max
+        assert_eq!(history.max(), 4.0);
+        assert_eq!(history.sparkline(history.max()), "▃▁█");
+    }
+
+    #[test]
+    fn history_of_no_length() {
+        let mut history = History::new(0);
+        history.push(50.0);
+        assert_eq!(history.sparkline(100.0), "");
+        assert_eq!(history.max(), 0.0);
+    }
+}
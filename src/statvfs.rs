@@ -0,0 +1,76 @@
+use std::{ffi::CString, io, mem::MaybeUninit, os::unix::ffi::OsStrExt, path::Path};
+
+/// The interesting parts of `struct statvfs`, sizes in bytes
+pub struct FsStats {
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+}
+
+impl FsStats {
+    pub fn used(&self) -> u64 {
+        return self.total.saturating_sub(self.free);
+    }
+
+    /// Used space as a percentage the same way df(1) calculates it,
+    /// space reserved for root doesn't count towards the total
+    pub fn used_percent(&self) -> f64 {
+        let usable = self.used() + self.available;
+        if usable == 0 {
+            return 0.0;
+        }
+        return self.used() as f64 * 100.0 / usable as f64;
+    }
+}
+
+pub fn statvfs(path: &Path) -> io::Result<FsStats> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: path is a valid nul terminated string and stat is only read on success
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat.assume_init()
+    };
+
+    let fragment_size = stat.f_frsize as u64;
+    return Ok(FsStats {
+        total: stat.f_blocks as u64 * fragment_size,
+        free: stat.f_bfree as u64 * fragment_size,
+        available: stat.f_bavail as u64 * fragment_size,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn used_percent_leaves_out_reserved_space() {
+        // 100 blocks, 30 free of which only 20 are available to users, like ext4's 5% for root
+        let stats = FsStats { total: 100, free: 30, available: 20 };
+        assert_eq!(stats.used(), 70);
+        assert_eq!(stats.used_percent(), 70.0 * 100.0 / 90.0);
+    }
+
+    #[test]
+    fn tmpfs_has_nothing_reserved() {
+        let stats = FsStats { total: 4096, free: 1024, available: 1024 };
+        assert_eq!(stats.used_percent(), 75.0);
+    }
+
+    #[test]
+    fn empty_filesystem() {
+        assert_eq!(FsStats { total: 0, free: 0, available: 0 }.used_percent(), 0.0);
+    }
+
+    #[test]
+    fn statvfs_of_a_real_path() {
+        let stats = statvfs(Path::new("/")).unwrap();
+        assert!(stats.total > 0 && stats.free <= stats.total && stats.available <= stats.free);
+        assert!(statvfs(Path::new("/nonexistent")).is_err());
+    }
+}
@@ -0,0 +1,11 @@
+use std::{fs, path::Path, str::FromStr};
+
+/// Reads a single value sysfs attribute, trailing newline removed
+pub fn read_string(path: impl AsRef<Path>) -> Option<String> {
+    return fs::read_to_string(path).ok()
+        .map(|value| value.trim_end().to_string());
+}
+
+pub fn read_value<T: FromStr>(path: impl AsRef<Path>) -> Option<T> {
+    return read_string(path)?.parse().ok();
+}
@@ -0,0 +1,164 @@
+use std::{convert::Infallible, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+
+const OPENING: &[char] = &['(', '[', '{', '<'];
+const CLOSING: &[char] = &[')', ']', '}', '>'];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    name: String,
+    /// Brackets right in front of and behind the placeholder, they go away with it
+    open: String,
+    close: String,
+    /// Text up to the next placeholder
+    separator: String,
+}
+
+/// A format string like `{artist} - {title} ({album})` with named placeholders.
+///
+/// Placeholders without a value disappear together with the brackets around them, and the
+/// text between two placeholders is only used when at least one of them has a value, so a
+/// missing artist or album doesn't leave stray separators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    leading: String,
+    fields: Vec<Field>,
+    trailing: String,
+}
+
+impl FromStr for Template {
+    type Err = Infallible;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut literals = Vec::new();
+        let mut names = Vec::new();
+        let mut rest = source;
+        let mut literal = String::new();
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}')
+            else { break };
+
+            literal += &rest[..start];
+            literals.push(std::mem::take(&mut literal));
+            names.push(rest[start + 1..start + length].trim().to_string());
+            rest = &rest[start + length + 1..];
+        }
+        // An unclosed `{` is just text
+        literal += rest;
+        literals.push(literal);
+
+        // There's one more literal than placeholders, each one between two is split into the
+        // closing bracket of the one before, the separator and the opening bracket of the next
+        let mut fields: Vec<Field> = Vec::new();
+        let mut leading = "".to_string();
+        for (index, literal) in literals.iter().enumerate() {
+            let close_length = if index > 0 { literal.len() - literal.trim_start_matches(CLOSING).len() } else { 0 };
+            let (close, literal) = literal.split_at(close_length);
+            let open_length = if index < names.len() { literal.len() - literal.trim_end_matches(OPENING).len() } else { 0 };
+            let (separator, open) = literal.split_at(literal.len() - open_length);
+
+            match fields.last_mut() {
+                Some(previous) => {
+                    previous.close = close.to_string();
+                    previous.separator = separator.to_string();
+                }
+                None => leading = separator.to_string(),
+            }
+            if let Some(name) = names.get(index) {
+                fields.push(Field {
+                    name: name.clone(),
+                    open: open.to_string(),
+                    close: "".to_string(),
+                    separator: "".to_string(),
+                });
+            }
+        }
+
+        // Whatever follows the last placeholder ended up as its separator
+        let trailing = fields.last_mut()
+            .map(|last| std::mem::take(&mut last.separator))
+            .unwrap_or_default();
+        return Ok(Self { leading, fields, trailing });
+    }
+}
+
+impl<'de> Deserialize<'de> for Template {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        let Ok(template) = source.parse();
+        return Ok(template);
+    }
+}
+
+impl Template {
+    /// Fills in the placeholders with what `value` gives for their names, unknown ones and
+    /// `None` count as empty
+    pub fn render(&self, value: impl Fn(&str) -> Option<String>) -> String {
+        if self.fields.is_empty() {
+            return self.leading.clone();
+        }
+
+        let mut output = String::new();
+        let mut previous: Option<&Field> = None;
+        for field in &self.fields {
+            let Some(value) = value(&field.name).filter(|value| !value.is_empty())
+            else { continue };
+
+            // Between two values the separator that followed the first one is used
+            output += match previous {
+                Some(previous) => &previous.separator,
+                None => &self.leading,
+            };
+            output += &field.open;
+            output += &value;
+            output += &field.close;
+            previous = Some(field);
+        }
+        if previous.is_some() {
+            output += &self.trailing;
+        }
+        return output;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, values: &[(&str, &str)]) -> String {
+        let template: Template = template.parse().unwrap();
+        return template.render(|name| {
+            values.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        });
+    }
+
+    #[test]
+    fn every_placeholder_filled() {
+        let values = [("artist", "Portishead"), ("title", "Roads"), ("album", "Dummy")];
+        assert_eq!(render("{artist} \u{2014} {title} ({album})", &values), "Portishead \u{2014} Roads (Dummy)");
+        assert_eq!(render("Now: { title }!", &values), "Now: Roads!");
+    }
+
+    #[test]
+    fn missing_values_take_their_separators_along() {
+        let template = "{artist} - {title} [{album}]";
+        assert_eq!(render(template, &[("title", "Roads")]), "Roads");
+        assert_eq!(render(template, &[("artist", "Portishead"), ("album", "Dummy")]), "Portishead - [Dummy]");
+        assert_eq!(render(template, &[("artist", "Portishead"), ("title", "")]), "Portishead");
+        assert_eq!(render(template, &[]), "");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_empty() {
+        assert_eq!(render("{title}{lyrics}", &[("title", "Roads")]), "Roads");
+        assert_eq!(render("<{lyrics}>", &[]), "");
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(render("no placeholders", &[]), "no placeholders");
+        // An unclosed brace isn't a placeholder
+        assert_eq!(render("{title} {oops", &[("title", "Roads")]), "Roads {oops");
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+/// Replaces control characters so dynamic text (track titles etc.) can't break the bar,
+/// line breaks and tabs become spaces and everything else is dropped
+pub fn sanitize_text(text: &str) -> String {
+    return text.chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect();
+}
+
+/// Escapes `text` so that pango shows it literally
+pub fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// Formats `duration` as `m:ss`, minutes aren't wrapped into hours
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    return format!("{}:{:02}", seconds / 60, seconds % 60);
+}
+
+/// `text` cut down to `max_chars` characters, the last one being an ellipsis. Counts chars
+/// rather than bytes so multibyte characters are never split.
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('\u{2026}');
+    return truncated;
+}
+
+/// Space between the end of the text and its start coming around again
+const MARQUEE_GAP: &str = "   ";
+
+/// A `width` characters wide window into `text` scrolled by `offset` characters, wrapping
+/// around. Text that already fits is returned as is.
+pub fn marquee(text: &str, width: usize, offset: usize) -> String {
+    let length = text.chars().count();
+    if length <= width {
+        return text.to_string();
+    }
+
+    let cycle = length + MARQUEE_GAP.chars().count();
+    return text.chars()
+        .chain(MARQUEE_GAP.chars())
+        .cycle()
+        .skip(offset % cycle)
+        .take(width)
+        .collect();
+}
+
+/// The numbers in `text` and the text around them, `CPU 4.5%` gives `["CPU ", "%"]` and `[4.5]`
+fn split_numbers(text: &str) -> (Vec<&str>, Vec<f64>) {
+    let mut literals = Vec::new();
+    let mut numbers = Vec::new();
+    let mut literal_start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+        let mut end = start + 1;
+        while let Some(&(index, c)) = chars.peek() {
+            // A dot only belongs to the number when a digit follows it
+            let fraction = c == '.' && text[index + 1..].starts_with(|c: char| c.is_ascii_digit()) && !text[start..index].contains('.');
+            if !c.is_ascii_digit() && !fraction {
+                break;
+            }
+            end = index + 1;
+            chars.next();
+        }
+        literals.push(&text[literal_start..start]);
+        numbers.push(text[start..end].parse().unwrap_or(0.0));
+        literal_start = end;
+    }
+    literals.push(&text[literal_start..]);
+    return (literals, numbers);
+}
+
+/// Whether `new` reads the same as `old` apart from numbers that moved by less than `threshold`
+pub fn numbers_within(old: &str, new: &str, threshold: f64) -> bool {
+    let (old_literals, old_numbers) = split_numbers(old);
+    let (new_literals, new_numbers) = split_numbers(new);
+    return old_literals == new_literals
+        && old_numbers.iter().zip(&new_numbers).all(|(old, new)| (old - new).abs() < threshold);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marquee_leaves_text_that_fits() {
+        assert_eq!(marquee("short", 5, 3), "short");
+        assert_eq!(marquee("", 5, 1), "");
+    }
+
+    #[test]
+    fn marquee_scrolls_and_wraps_around() {
+        let text = "Artist - Title";
+        assert_eq!(marquee(text, 6, 0), "Artist");
+        assert_eq!(marquee(text, 6, 9), "Title ");
+        // Past the end the gap comes before the start again
+        assert_eq!(marquee(text, 6, 12), "le   A");
+        // One full cycle is the text and the gap
+        assert_eq!(marquee(text, 6, 17), "Artist");
+        assert_eq!(marquee(text, 6, 17 * 3 + 2), marquee(text, 6, 2));
+    }
+
+    #[test]
+    fn marquee_counts_characters() {
+        assert_eq!(marquee("Björk – Jóga", 5, 3), "rk – ");
+        assert_eq!(marquee("🎵🎶 music", 3, 1), "🎶 m");
+    }
+
+    #[test]
+    fn escapes_what_pango_would_read_as_markup() {
+        assert_eq!(escape_markup("Simon & Garfunkel"), "Simon &amp; Garfunkel");
+        assert_eq!(escape_markup("<b>not bold</b>"), "&lt;b&gt;not bold&lt;/b&gt;");
+        assert_eq!(escape_markup("Don't \"quote\" me"), "Don&apos;t &quot;quote&quot; me");
+    }
+
+    #[test]
+    fn plain_text_is_left_alone() {
+        assert_eq!(escape_markup("Sigur Rós – Hoppípolla"), "Sigur Rós – Hoppípolla");
+        assert_eq!(escape_markup(""), "");
+        // Already escaped text is escaped again, it's taken literally
+        assert_eq!(escape_markup("&amp;"), "&amp;amp;");
+    }
+
+    #[test]
+    fn truncate_ascii() {
+        assert_eq!(truncate("Wireless Network 5GHz", 10), "Wireless \u{2026}");
+        assert_eq!(truncate("home", 10), "home");
+    }
+
+    #[test]
+    fn truncate_exactly_at_the_limit() {
+        assert_eq!(truncate("exactly10!", 10), "exactly10!");
+        assert_eq!(truncate("exactly11!!", 10), "exactly11\u{2026}");
+        assert_eq!(truncate("anything", 0), "\u{2026}");
+    }
+
+    #[test]
+    fn truncate_never_splits_a_character() {
+        // Each of these is four bytes, a byte count would cut one in half
+        assert_eq!(truncate("🎸🎹🎺🎻🥁", 3), "🎸🎹\u{2026}");
+        assert_eq!(truncate("🎸🎹🎺", 3), "🎸🎹🎺");
+        assert_eq!(truncate("Café Müller", 6), "Café \u{2026}");
+    }
+}
@@ -0,0 +1,72 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::color::{self, HexColor};
+
+/// Item fields holding a color
+const COLOR_FIELDS: [&str; 3] = ["color", "background", "border"];
+
+/// Hex colors for the names in `color`, set in the `[theme]` table of the config
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Theme {
+    pub good: HexColor,
+    pub warning: HexColor,
+    pub critical: HexColor,
+    pub muted: HexColor,
+    pub accent: HexColor,
+    /// Text color of items that don't pick one, unset leaves it to the bar
+    pub foreground: Option<HexColor>,
+    /// Background of items that don't pick one, unset leaves it to the bar
+    pub background: Option<HexColor>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let hex = |color: &str| HexColor::try_from(color.to_string()).unwrap();
+        return Self {
+            good: hex("#00ff00"),
+            warning: hex("#ffff00"),
+            critical: hex("#ff0000"),
+            muted: hex("#808080"),
+            accent: hex("#285577"),
+            foreground: None,
+            background: None,
+        };
+    }
+}
+
+impl Theme {
+    fn resolve(&self, name: &str) -> Option<&str> {
+        return match name {
+            color::GOOD => Some(self.good.as_str()),
+            color::WARNING => Some(self.warning.as_str()),
+            color::CRITICAL => Some(self.critical.as_str()),
+            color::MUTED => Some(self.muted.as_str()),
+            color::ACCENT => Some(self.accent.as_str()),
+            _ => None,
+        };
+    }
+
+    /// Replaces color names in rendered items with the theme's colors and fills in the default
+    /// foreground and background. Hex colors, like ones from commands, are left alone.
+    pub fn apply(&self, items: &mut [Value]) {
+        for item in items.iter_mut() {
+            let Value::Object(fields) = item
+            else { continue };
+
+            for field in COLOR_FIELDS {
+                if let Some(Value::String(name)) = fields.get_mut(field) {
+                    if let Some(hex) = self.resolve(name) {
+                        *name = hex.to_string();
+                    }
+                }
+            }
+            for (field, default) in [("color", &self.foreground), ("background", &self.background)] {
+                if let (false, Some(default)) = (fields.contains_key(field), default) {
+                    fields.insert(field.to_string(), json!(default.as_str()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use tokio::{signal::unix::{signal, SignalKind}, sync::Notify};
+
+use crate::log::warn;
+
+struct BlockState {
+    enabled: bool,
+}
+
+/// Runtime visibility of blocks, keyed by block id. Nothing is persisted,
+/// every block starts out enabled.
+pub struct Toggles {
+    invalidate: Arc<Notify>,
+    blocks: Mutex<HashMap<String, BlockState>>,
+    /// Block toggled by the signal passed to `listen`
+    signal_target: Mutex<Option<String>>,
+}
+
+impl Toggles {
+    pub fn new(invalidate: Arc<Notify>) -> Self {
+        return Self {
+            invalidate,
+            blocks: Mutex::new(HashMap::new()),
+            signal_target: Mutex::new(None),
+        };
+    }
+
+    pub fn is_enabled(&self, id: &str) -> bool {
+        return self.blocks.lock().unwrap()
+            .get(id)
+            .map(|block| block.enabled)
+            .unwrap_or(true);
+    }
+
+    pub fn toggle(&self, id: &str) {
+        let mut blocks = self.blocks.lock().unwrap();
+        let block = blocks.entry(id.to_string())
+            .or_insert(BlockState { enabled: true });
+        block.enabled = !block.enabled;
+        self.invalidate.notify_one();
+    }
+
+    pub fn set_signal_target(&self, id: Option<String>) {
+        *self.signal_target.lock().unwrap() = id;
+    }
+
+    /// Toggles the block set with `set_signal_target` every time `kind` is received
+    pub fn listen(self: &Arc<Self>, kind: SignalKind) {
+        let mut stream = match signal(kind) {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to install toggle signal handler: {}", err);
+                return;
+            }
+        };
+
+        let toggles = self.clone();
+        tokio::spawn(async move {
+            while stream.recv().await.is_some() {
+                let target = toggles.signal_target.lock().unwrap().clone();
+                if let Some(id) = target {
+                    toggles.toggle(&id);
+                }
+            }
+        });
+    }
+}